@@ -0,0 +1,1018 @@
+// Two pass Z80 assembler for the M80 / ZMAC style syntax of the bundled exercisers.
+// Instructions are encoded through the CPU opcode tables: every operand is classified into
+// the placeholder forms used by the table mnemonics ("LD (IX+*), *") & the first matching
+// row supplies prefixes, opcode & size, so the assembler, the disassembler & the CPU agree.
+//
+// Supported: labels (the colon is optional in column 0), expressions, ORG, DB/DW/DS,
+// EQU/SET, IF/ELSE/ENDIF, REPT & MACRO/LOCAL/ENDM with `&` concatenation, `<..>` argument
+// groups & unique labels for omitted `?` parameters.
+use crate::cpu::opcodes::{OpcodeInfo, CB, ED, INDEX, INDEX_BIT, MAIN};
+use crate::error::AsmError;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+const DIRECTIVES: [&str; 25] = [
+    "org", "db", "defb", "defm", "dw", "defw", "ds", "defs", "equ", "set", "defl", "end", "if",
+    "else", "endif", "macro", "endm", "rept", "local", "error", "aseg", "title", ".title", "page",
+    "eject",
+];
+
+const REGISTERS: [&str; 29] = [
+    "a", "b", "c", "d", "e", "h", "l", "i", "r", "af", "af'", "bc", "de", "hl", "sp", "ix", "iy",
+    "ixh", "ixl", "iyh", "iyl", "nz", "z", "nc", "po", "pe", "p", "m", "(c)",
+];
+
+// Accepted with an explicit accumulator operand, e.g. "and a,0fh"
+const ALU: [&str; 5] = ["sub", "and", "xor", "or", "cp"];
+
+const MAX_DEPTH: usize = 64;
+
+// Assembled image, spanning every address written by an instruction or DB/DW/DS
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+    pub symbols: HashMap<String, i32>, // Lower case names
+}
+
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    let lines: Vec<(usize, String)> = source
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n + 1, strip_comment(line).trim_end().to_string()))
+        .collect();
+
+    // Pass 1 collects the symbols, forward references evaluate to 0 until pass 2
+    let mut first = Assembler::new(None);
+    first.block(&lines, 0)?;
+
+    let mut second = Assembler::new(Some(&first.symbols));
+    second.block(&lines, 0)?;
+    Ok(second.program())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Form {
+    Main,
+    Cb,
+    Ed,
+    Index,
+    IndexBit,
+}
+
+// Table mnemonic ("ld (ix+*),*") -> opcode row
+fn opcode_table() -> &'static HashMap<String, (Form, u8, OpcodeInfo)> {
+    static TABLE: OnceLock<HashMap<String, (Form, u8, OpcodeInfo)>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let tables = [
+            (Form::Main, &MAIN),
+            (Form::Cb, &CB),
+            (Form::Ed, &ED),
+            (Form::Index, &INDEX),
+            (Form::IndexBit, &INDEX_BIT),
+        ];
+        let mut table = HashMap::new();
+        for (form, rows) in tables.iter() {
+            for (op, info) in rows.iter().enumerate() {
+                if let Some(info) = info {
+                    let key = info.name.to_lowercase().replace(", ", ",");
+                    let row = (*form, op as u8, *info);
+                    // Mirrored opcodes assemble to the first (documented) row, except for
+                    // BIT n,(IX+d) which is documented with (HL) register bits
+                    if let (Form::IndexBit, 6) = (form, op & 7) {
+                        table.insert(key, row);
+                    } else {
+                        table.entry(key).or_insert(row);
+                    }
+                }
+            }
+        }
+        table
+    })
+}
+
+fn mnemonics() -> &'static HashSet<String> {
+    static MNEMONICS: OnceLock<HashSet<String>> = OnceLock::new();
+    MNEMONICS.get_or_init(|| {
+        opcode_table()
+            .keys()
+            .map(|key| key.split(' ').next().unwrap_or_default().to_string())
+            .collect()
+    })
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<(usize, String)>,
+}
+
+struct Statement<'t> {
+    label: Option<&'t str>,
+    op: String,
+    args: &'t str,
+}
+
+// An operand & the table forms it can match, e.g. "(1234h)" is "(*)" or "(**)"
+struct Operand {
+    forms: Vec<String>,
+    value: i32,
+    index: Option<u8>, // DD / FD prefix for operands naming IX or IY
+}
+
+struct Assembler<'a> {
+    previous: Option<&'a HashMap<String, i32>>, // Pass 1 symbols, None during pass 1
+    symbols: HashMap<String, i32>,
+    variables: HashSet<String>, // Defined by SET, may be redefined
+    macros: HashMap<String, Macro>,
+    image: Vec<u8>,
+    range: Option<(usize, usize)>,
+    pc: usize,
+    start: usize, // Address of the current statement ($)
+    locals: usize,
+    line: usize,
+    ended: bool,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(previous: Option<&'a HashMap<String, i32>>) -> Self {
+        Assembler {
+            previous,
+            symbols: HashMap::new(),
+            variables: HashSet::new(),
+            macros: HashMap::new(),
+            image: vec![0; 0x10000],
+            range: None,
+            pc: 0,
+            start: 0,
+            locals: 0,
+            line: 0,
+            ended: false,
+        }
+    }
+
+    fn final_pass(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn program(self) -> Program {
+        let (lo, hi) = self.range.unwrap_or((0, 0));
+        Program {
+            origin: lo as u16,
+            bytes: self.image[lo..hi].to_vec(),
+            symbols: self.symbols,
+        }
+    }
+
+    fn block(&mut self, lines: &[(usize, String)], depth: usize) -> Result<(), AsmError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("Macro expansion nested too deep"));
+        }
+        let mut conds: Vec<bool> = Vec::new();
+        let mut i = 0;
+
+        while i < lines.len() && !self.ended {
+            let (number, text) = &lines[i];
+            self.line = *number;
+            self.start = self.pc;
+            i += 1;
+
+            let stmt = self.statement(text);
+            let active = conds.iter().all(|&c| c);
+            match stmt.op.as_str() {
+                "if" => {
+                    let cond = active && self.eval(stmt.args)? != 0;
+                    conds.push(cond);
+                }
+                "else" => match conds.last_mut() {
+                    Some(cond) => *cond = !*cond,
+                    None => return Err(self.error("ELSE without IF")),
+                },
+                "endif" => {
+                    if conds.pop().is_none() {
+                        return Err(self.error("ENDIF without IF"));
+                    }
+                }
+                _ if !active => {}
+                "macro" | "rept" => {
+                    let end = self.body_end(lines, i)?;
+                    let body = lines[i..end].to_vec();
+                    i = end + 1;
+
+                    if stmt.op == "macro" {
+                        let name = stmt.label.ok_or_else(|| self.error("MACRO without name"))?;
+                        let params = split_args(stmt.args)
+                            .iter()
+                            .map(|p| p.to_lowercase())
+                            .collect();
+                        self.macros
+                            .insert(name.to_lowercase(), Macro { params, body });
+                    } else {
+                        if let Some(label) = stmt.label {
+                            self.define(label, self.pc as i32, false)?;
+                        }
+                        for _ in 0..self.eval(stmt.args)?.max(0) {
+                            self.block(&body, depth + 1)?;
+                        }
+                    }
+                }
+                _ => self.execute(&stmt, depth)?,
+            }
+        }
+
+        if !conds.is_empty() && !self.ended {
+            return Err(self.error("IF without ENDIF"));
+        }
+        Ok(())
+    }
+
+    // Index of the ENDM closing the MACRO / REPT body starting at `start`
+    fn body_end(&self, lines: &[(usize, String)], start: usize) -> Result<usize, AsmError> {
+        let mut depth = 1;
+        for (i, (_, text)) in lines.iter().enumerate().skip(start) {
+            match self.statement(text).op.as_str() {
+                "macro" | "rept" => depth += 1,
+                "endm" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(i);
+            }
+        }
+        Err(self.error("Missing ENDM"))
+    }
+
+    // Splits a line into label, operation & arguments. A word in column 0 is a label unless
+    // it names an instruction, directive or macro.
+    fn statement<'t>(&self, text: &'t str) -> Statement<'t> {
+        let column0 = text.starts_with(|c: char| !c.is_whitespace());
+        let mut rest = text.trim();
+        let mut label = None;
+
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == ':')
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        if rest[end..].starts_with(':') {
+            label = Some(word);
+            rest = rest[end..].trim_start_matches(':').trim_start();
+        } else if column0 && !self.is_op(word) {
+            label = Some(word);
+            rest = rest[end..].trim_start();
+        }
+
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        Statement {
+            label,
+            op: rest[..end].to_lowercase(),
+            args: rest[end..].trim(),
+        }
+    }
+
+    fn is_op(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        DIRECTIVES.contains(&word.as_str())
+            || mnemonics().contains(&word)
+            || self.macros.contains_key(&word)
+    }
+
+    fn execute(&mut self, stmt: &Statement, depth: usize) -> Result<(), AsmError> {
+        let args = stmt.args;
+
+        // SET is the bit instruction unless it assigns a single value to a label
+        let assign = match stmt.op.as_str() {
+            "equ" | "defl" => true,
+            "set" => stmt.label.is_some() && split_args(args).len() == 1,
+            _ => false,
+        };
+        if assign {
+            let name = stmt
+                .label
+                .ok_or_else(|| self.error("Missing symbol name"))?;
+            let value = self.eval(args)?;
+            return self.define(name, value, stmt.op != "equ");
+        }
+        if let Some(label) = stmt.label {
+            self.define(label, self.pc as i32, false)?;
+        }
+
+        match stmt.op.as_str() {
+            "" | "aseg" | "title" | ".title" | "page" | "eject" | "local" => {}
+            "org" => self.pc = self.eval(args)? as u16 as usize,
+            "end" => self.ended = true,
+            "db" | "defb" | "defm" => {
+                for arg in split_args(args) {
+                    match string_literal(&arg) {
+                        Some(bytes) => {
+                            for byte in bytes {
+                                self.emit(byte)?;
+                            }
+                        }
+                        None => {
+                            let byte = self.byte(self.eval(&arg)?)?;
+                            self.emit(byte)?;
+                        }
+                    }
+                }
+            }
+            "dw" | "defw" => {
+                for arg in split_args(args) {
+                    let word = self.word(self.eval(&arg)?)?;
+                    self.emit(word as u8)?;
+                    self.emit((word >> 8) as u8)?;
+                }
+            }
+            "ds" | "defs" => {
+                let args = split_args(args);
+                let count = self.eval(args.first().map_or("", |a| a.as_str()))?;
+                let fill = match args.get(1) {
+                    Some(fill) => self.byte(self.eval(fill)?)?,
+                    None => 0,
+                };
+                if count < 0 {
+                    return Err(self.error("Negative DS size"));
+                }
+                for _ in 0..count {
+                    self.emit(fill)?;
+                }
+            }
+            "error" => {
+                if self.final_pass() {
+                    let message = string_literal(args).unwrap_or_else(|| args.into());
+                    return Err(self.error(String::from_utf8_lossy(&message)));
+                }
+            }
+            "endm" => return Err(self.error("ENDM without MACRO")),
+            op => match self.macros.get(op) {
+                Some(m) => {
+                    let (params, body) = (m.params.clone(), m.body.clone());
+                    self.expand(&params, &body, args, depth)?;
+                }
+                None => self.instruction(op, args)?,
+            },
+        }
+        Ok(())
+    }
+
+    fn define(&mut self, name: &str, value: i32, variable: bool) -> Result<(), AsmError> {
+        let valid = name.starts_with(is_ident_start) && name.chars().all(is_ident_char);
+        if !valid {
+            return Err(self.error(format!("Invalid label: {}", name)));
+        }
+
+        let name = name.to_lowercase();
+        if variable {
+            self.variables.insert(name.clone());
+        } else if self.symbols.contains_key(&name) || self.variables.contains(&name) {
+            return Err(self.error(format!("Duplicate symbol: {}", name)));
+        } else if self.previous.and_then(|p| p.get(&name)) != Some(&value) && self.final_pass() {
+            return Err(self.error(format!("Phase error: {}", name)));
+        }
+        self.symbols.insert(name, value);
+        Ok(())
+    }
+
+    fn local_name(&mut self) -> String {
+        self.locals += 1;
+        format!("??{:04}", self.locals)
+    }
+
+    fn expand(
+        &mut self,
+        params: &[String],
+        body: &[(usize, String)],
+        args: &str,
+        depth: usize,
+    ) -> Result<(), AsmError> {
+        let args = split_args(args);
+        let mut names = HashMap::new();
+
+        for (i, param) in params.iter().enumerate() {
+            let value = match args.get(i) {
+                Some(arg) if !arg.is_empty() => strip_group(arg).to_string(),
+                _ if param.starts_with('?') => self.local_name(),
+                _ => String::new(),
+            };
+            names.insert(param.clone(), value);
+        }
+        for (_, text) in body {
+            let stmt = self.statement(text);
+            if stmt.op == "local" {
+                for name in split_args(stmt.args) {
+                    let local = self.local_name();
+                    names.insert(name.to_lowercase(), local);
+                }
+            }
+        }
+
+        let lines: Vec<(usize, String)> = body
+            .iter()
+            .map(|(_, text)| (self.line, substitute(text, &names)))
+            .collect();
+        self.block(&lines, depth + 1)
+    }
+
+    fn emit(&mut self, byte: u8) -> Result<(), AsmError> {
+        if self.pc > 0xFFFF {
+            return Err(self.error("Address beyond FFFFh"));
+        }
+        self.image[self.pc] = byte;
+        let (lo, hi) = self.range.unwrap_or((self.pc, self.pc));
+        self.range = Some((lo.min(self.pc), hi.max(self.pc + 1)));
+        self.pc += 1;
+        Ok(())
+    }
+
+    fn check(&self, value: i32, min: i32, max: i32, what: &str) -> Result<i32, AsmError> {
+        if self.final_pass() && (value < min || value > max) {
+            return Err(self.error(format!("{} out of range: {}", what, value)));
+        }
+        Ok(value)
+    }
+
+    fn byte(&self, value: i32) -> Result<u8, AsmError> {
+        Ok(self.check(value, -256, 255, "Byte")? as u8)
+    }
+
+    fn word(&self, value: i32) -> Result<u16, AsmError> {
+        Ok(self.check(value, -65536, 65535, "Word")? as u16)
+    }
+
+    fn instruction(&mut self, mnemonic: &str, args: &str) -> Result<(), AsmError> {
+        if !mnemonics().contains(mnemonic) {
+            return Err(self.error(format!("Unknown instruction: {}", mnemonic)));
+        }
+        let mut args = split_args(args);
+        if ALU.contains(&mnemonic) && args.len() == 2 && args[0].eq_ignore_ascii_case("a") {
+            args.remove(0);
+        }
+        let operands = args
+            .iter()
+            .map(|arg| self.operand(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut index = None;
+        for prefix in operands.iter().filter_map(|o| o.index) {
+            if index.replace(prefix).is_some_and(|p| p != prefix) {
+                return Err(self.error("IX & IY in the same instruction"));
+            }
+        }
+
+        // Try every combination of operand forms, the last operand varying fastest
+        let mut choice = vec![0; operands.len()];
+        loop {
+            let forms: Vec<&str> = operands
+                .iter()
+                .zip(&choice)
+                .map(|(o, &c)| o.forms[c].as_str())
+                .collect();
+            let key = format!("{} {}", mnemonic, forms.join(","));
+            if let Some(&row) = opcode_table().get(key.trim_end()) {
+                return self.encode(mnemonic, row, &operands, &choice, index);
+            }
+
+            let mut k = operands.len();
+            loop {
+                if k == 0 {
+                    return Err(self.error(format!(
+                        "Invalid operands: {} {}",
+                        mnemonic,
+                        args.join(",")
+                    )));
+                }
+                k -= 1;
+                choice[k] += 1;
+                if choice[k] < operands[k].forms.len() {
+                    break;
+                }
+                choice[k] = 0;
+            }
+        }
+    }
+
+    fn operand(&self, text: &str) -> Result<Operand, AsmError> {
+        let lower: String = text.to_lowercase().split_whitespace().collect();
+        let register = |name: &str, value: i32| {
+            let index = match () {
+                _ if name.contains("ix") => Some(0xDD),
+                _ if name.contains("iy") => Some(0xFD),
+                _ => None,
+            };
+            let forms = match name {
+                "(ix)" | "(iy)" => vec!["(ix)".to_string(), "(ix+*)".to_string()],
+                _ => vec![name.replace("iy", "ix")],
+            };
+            Operand {
+                forms,
+                value,
+                index,
+            }
+        };
+
+        if REGISTERS.contains(&lower.as_str()) {
+            return Ok(register(&lower, 0));
+        }
+        if let Some(inner) = parenthesized(text) {
+            let inner = inner.trim();
+            let name = inner.get(..2).unwrap_or_default().to_lowercase();
+            let rest = inner.get(2..).unwrap_or_default().trim_start();
+
+            return Ok(match lower.as_str() {
+                "(bc)" | "(de)" | "(hl)" | "(sp)" | "(ix)" | "(iy)" => register(&lower, 0),
+                _ if (name == "ix" || name == "iy") && rest.starts_with(['+', '-']) => {
+                    let mut operand = register(&name, self.eval(rest)?);
+                    operand.forms = vec!["(ix+*)".to_string()];
+                    operand
+                }
+                _ => Operand {
+                    forms: vec!["(*)".to_string(), "(**)".to_string()],
+                    value: self.eval(inner)?,
+                    index: None,
+                },
+            });
+        }
+
+        // Immediates also match literal table operands: IM 1, BIT 7, RST 38H & OUT (C), 0
+        let value = self.eval(text)?;
+        Ok(Operand {
+            forms: vec![
+                value.to_string(),
+                format!("{:02x}h", value),
+                "*".to_string(),
+                "**".to_string(),
+            ],
+            value,
+            index: None,
+        })
+    }
+
+    fn encode(
+        &mut self,
+        mnemonic: &str,
+        (form, opcode, info): (Form, u8, OpcodeInfo),
+        operands: &[Operand],
+        choice: &[usize],
+        index: Option<u8>,
+    ) -> Result<(), AsmError> {
+        let prefix = index.unwrap_or(0xDD);
+        let mut bytes = match form {
+            Form::Main => vec![opcode],
+            Form::Cb => vec![0xCB, opcode],
+            Form::Ed => vec![0xED, opcode],
+            Form::Index => vec![prefix, opcode],
+            Form::IndexBit => vec![prefix, 0xCB],
+        };
+        let mut operand_bytes = Vec::new();
+
+        for (operand, &c) in operands.iter().zip(choice) {
+            let shape = operand.forms[c].as_str();
+            let value = operand.value;
+            if shape == "(ix+*)" {
+                // DD CB d op: the displacement precedes the opcode
+                let d = self.check(value, -128, 127, "Displacement")? as u8;
+                match form {
+                    Form::IndexBit => bytes.push(d),
+                    _ => operand_bytes.push(d),
+                }
+            } else if shape.contains("**") {
+                let word = self.word(value)?;
+                operand_bytes.extend_from_slice(&[word as u8, (word >> 8) as u8]);
+            } else if shape.contains('*') && (mnemonic == "jr" || mnemonic == "djnz") {
+                let offset = value - (self.start as i32 + info.bytes as i32);
+                operand_bytes.push(self.check(offset, -128, 127, "Relative jump")? as u8);
+            } else if shape.contains('*') {
+                operand_bytes.push(self.byte(value)?);
+            }
+        }
+        if let Form::IndexBit = form {
+            bytes.push(opcode);
+        }
+        bytes.extend(operand_bytes);
+
+        for byte in bytes {
+            self.emit(byte)?;
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Result<i32, AsmError> {
+        if let Some(&value) = self.symbols.get(name) {
+            return Ok(value);
+        }
+        match self.previous {
+            Some(previous) => previous
+                .get(name)
+                .copied()
+                .ok_or_else(|| self.error(format!("Undefined symbol: {}", name))),
+            None => Ok(0),
+        }
+    }
+
+    fn eval(&self, text: &str) -> Result<i32, AsmError> {
+        let tokens = self.tokenize(text)?;
+        let mut expr = Expr {
+            asm: self,
+            tokens: &tokens,
+            pos: 0,
+        };
+        let value = expr.or()?;
+        match expr.tokens.get(expr.pos) {
+            None => Ok(value),
+            Some(_) => Err(self.error(format!("Invalid expression: {}", text))),
+        }
+    }
+
+    fn tokenize(&self, text: &str) -> Result<Vec<Token>, AsmError> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let start = i;
+            i += 1;
+            match c {
+                _ if c.is_whitespace() => {}
+                _ if c.is_ascii_digit() => {
+                    while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                        i += 1;
+                    }
+                    let number: String = chars[start..i].iter().collect();
+                    let value = parse_number(&number)
+                        .ok_or_else(|| self.error(format!("Invalid number: {}", number)))?;
+                    tokens.push(Token::Num(value));
+                }
+                '$' if i < chars.len() && chars[i].is_ascii_hexdigit() => {
+                    while i < chars.len() && chars[i].is_ascii_hexdigit() {
+                        i += 1;
+                    }
+                    let number: String = chars[start + 1..i].iter().collect();
+                    tokens.push(Token::Num(
+                        i64::from_str_radix(&number, 16).unwrap_or(0) as i32
+                    ));
+                }
+                '$' => tokens.push(Token::Num(self.start as i32)),
+                _ if is_ident_start(c) => {
+                    while i < chars.len() && is_ident_char(chars[i]) {
+                        i += 1;
+                    }
+                    let ident: String = chars[start..i].iter().collect();
+                    tokens.push(Token::Ident(ident.to_lowercase()));
+                }
+                '\'' | '"' => {
+                    let mut value = 0i32;
+                    loop {
+                        match chars.get(i) {
+                            Some(&q) if q == c && chars.get(i + 1) == Some(&c) => i += 1,
+                            Some(&q) if q == c => break,
+                            Some(_) => {}
+                            None => return Err(self.error("Unterminated string")),
+                        }
+                        value = (value << 8) | (chars[i] as i32 & 0xFF);
+                        i += 1;
+                    }
+                    i += 1;
+                    tokens.push(Token::Num(value));
+                }
+                _ => {
+                    let pair: String = chars[start..(start + 2).min(chars.len())].iter().collect();
+                    match pair.as_str() {
+                        "<=" | ">=" | "<>" | "!=" | "==" | "<<" | ">>" => {
+                            i += 1;
+                            tokens.push(Token::Op(pair));
+                        }
+                        _ if "+-*/%()&|^~<>=".contains(c) => tokens.push(Token::Op(c.to_string())),
+                        _ => return Err(self.error(format!("Invalid character: {}", c))),
+                    }
+                }
+            }
+        }
+        Ok(tokens)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(i32),
+    Ident(String), // Symbols & word operators (HIGH, XOR, NE ..)
+    Op(String),
+}
+
+// Recursive descent, lowest precedence first: OR/XOR, AND, NOT, relations, +/-, * / MOD
+// SHL SHR, unary - + ~ HIGH LOW. Relations are -1 (true) or 0.
+struct Expr<'e, 'a> {
+    asm: &'e Assembler<'a>,
+    tokens: &'e [Token],
+    pos: usize,
+}
+
+impl<'e, 'a> Expr<'e, 'a> {
+    // Consumes the next token if it is one of `ops`
+    fn accept(&mut self, ops: &[&str]) -> Option<String> {
+        let op = match self.tokens.get(self.pos)? {
+            Token::Op(op) | Token::Ident(op) if ops.contains(&op.as_str()) => op.clone(),
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(op)
+    }
+
+    fn or(&mut self) -> Result<i32, AsmError> {
+        let mut value = self.and()?;
+        while let Some(op) = self.accept(&["or", "|", "xor", "^"]) {
+            let rhs = self.and()?;
+            value = match op.as_str() {
+                "or" | "|" => value | rhs,
+                _ => value ^ rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<i32, AsmError> {
+        let mut value = self.not()?;
+        while self.accept(&["and", "&"]).is_some() {
+            value &= self.not()?;
+        }
+        Ok(value)
+    }
+
+    fn not(&mut self) -> Result<i32, AsmError> {
+        match self.accept(&["not"]) {
+            Some(_) => Ok(!self.not()?),
+            None => self.relation(),
+        }
+    }
+
+    fn relation(&mut self) -> Result<i32, AsmError> {
+        let ops = [
+            "eq", "ne", "lt", "le", "gt", "ge", "=", "==", "<>", "!=", "<", "<=", ">", ">=",
+        ];
+        let mut value = self.sum()?;
+        while let Some(op) = self.accept(&ops) {
+            let rhs = self.sum()?;
+            let result = match op.as_str() {
+                "eq" | "=" | "==" => value == rhs,
+                "ne" | "<>" | "!=" => value != rhs,
+                "lt" | "<" => value < rhs,
+                "le" | "<=" => value <= rhs,
+                "gt" | ">" => value > rhs,
+                _ => value >= rhs,
+            };
+            value = -(result as i32);
+        }
+        Ok(value)
+    }
+
+    fn sum(&mut self) -> Result<i32, AsmError> {
+        let mut value = self.product()?;
+        while let Some(op) = self.accept(&["+", "-"]) {
+            let rhs = self.product()?;
+            value = match op.as_str() {
+                "+" => value.wrapping_add(rhs),
+                _ => value.wrapping_sub(rhs),
+            };
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<i32, AsmError> {
+        let ops = ["*", "/", "%", "mod", "shl", "<<", "shr", ">>"];
+        let mut value = self.unary()?;
+        while let Some(op) = self.accept(&ops) {
+            let rhs = self.unary()?;
+            value = match op.as_str() {
+                "*" => value.wrapping_mul(rhs),
+                "shl" | "<<" => value.wrapping_shl(rhs as u32),
+                "shr" | ">>" => value.wrapping_shr(rhs as u32),
+                _ if rhs == 0 => return Err(self.asm.error("Division by zero")),
+                "/" => value.wrapping_div(rhs),
+                _ => value.wrapping_rem(rhs),
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<i32, AsmError> {
+        match self.accept(&["-", "+", "~", "high", "low"]).as_deref() {
+            Some("-") => Ok(self.unary()?.wrapping_neg()),
+            Some("+") => self.unary(),
+            Some("~") => Ok(!self.unary()?),
+            Some("high") => Ok((self.unary()? >> 8) & 0xFF),
+            Some("low") => Ok(self.unary()? & 0xFF),
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<i32, AsmError> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Num(value)) => Ok(value),
+            Some(Token::Ident(name)) => self.asm.lookup(&name),
+            Some(Token::Op(op)) if op == "(" => {
+                let value = self.or()?;
+                match self.accept(&[")"]) {
+                    Some(_) => Ok(value),
+                    None => Err(self.asm.error("Missing )")),
+                }
+            }
+            _ => Err(self.asm.error("Invalid expression")),
+        }
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || "_?.@".contains(c)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_?.@".contains(c)
+}
+
+// Numbers: 255, 0ffh, 0x00ff, $ff, 11111111b & 377o / 377q
+fn parse_number(text: &str) -> Option<i32> {
+    let text = text.to_lowercase();
+    let last = text.len() - 1;
+    let (digits, radix) = match text.as_bytes()[last] {
+        _ if text.starts_with("0x") => (&text[2..], 16),
+        b'h' => (&text[..last], 16),
+        b'b' => (&text[..last], 2),
+        b'o' | b'q' => (&text[..last], 8),
+        b'd' => (&text[..last], 10),
+        _ => (&text[..], 10),
+    };
+    i64::from_str_radix(digits, radix).ok().map(|v| v as i32)
+}
+
+// A quote following a letter or digit is part of a name (EX AF,AF')
+fn opens_quote(c: char, prev: char) -> bool {
+    (c == '\'' || c == '"') && !prev.is_ascii_alphanumeric()
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if opens_quote(c, prev) => quote = Some(c),
+            None if c == ';' => return &line[..i],
+            None => {}
+        }
+        prev = c;
+    }
+    line
+}
+
+// Splits at top level commas, outside strings, parentheses & <..> macro argument groups
+fn split_args(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let (mut parens, mut group) = (0, 0);
+    let mut prev = ' ';
+
+    for c in text.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if opens_quote(c, prev) => quote = Some(c),
+            None => match c {
+                '(' => parens += 1,
+                ')' => parens -= 1,
+                '<' if group > 0 || current.trim().is_empty() => group += 1,
+                '>' if group > 0 => group -= 1,
+                ',' if parens == 0 && group == 0 => {
+                    args.push(current.trim().to_string());
+                    current.clear();
+                    prev = c;
+                    continue;
+                }
+                _ => {}
+            },
+        }
+        current.push(c);
+        prev = c;
+    }
+    if !args.is_empty() || !current.trim().is_empty() {
+        args.push(current.trim().to_string());
+    }
+    args
+}
+
+fn strip_group(arg: &str) -> &str {
+    match arg.strip_prefix('<').and_then(|a| a.strip_suffix('>')) {
+        Some(inner) => inner,
+        None => arg,
+    }
+}
+
+// The text between an opening parenthesis at the start & its match at the end
+fn parenthesized(text: &str) -> Option<&str> {
+    let text = text.trim();
+    if !text.starts_with('(') {
+        return None;
+    }
+    let mut depth = 0;
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if opens_quote(c, prev) => quote = Some(c),
+            None if c == '(' => depth += 1,
+            None if c == ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return if i == text.len() - 1 {
+                        Some(&text[1..i])
+                    } else {
+                        None
+                    };
+                }
+            }
+            None => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+// Bytes of an operand consisting of a single string, '' is an escaped quote
+fn string_literal(text: &str) -> Option<Vec<u8>> {
+    let quote = text.chars().next().filter(|&c| c == '\'' || c == '"')?;
+    let mut bytes = Vec::new();
+    let mut chars = text[1..].chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == quote {
+            if chars.peek() == Some(&quote) {
+                chars.next();
+            } else {
+                return if chars.next().is_none() {
+                    Some(bytes)
+                } else {
+                    None
+                };
+            }
+        }
+        bytes.push(c as u8);
+    }
+    None
+}
+
+// Replaces macro parameters & locals: whole names anywhere outside strings, `&name` also
+// inside strings & glued to other text (lab&pcond)
+fn substitute(text: &str, names: &HashMap<String, String>) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut quote = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let prev = if i > 0 { chars[i - 1] } else { ' ' };
+        let amp = c == '&' && chars.get(i + 1).is_some_and(|&n| is_ident_start(n));
+
+        if amp || (quote.is_none() && is_ident_start(c)) {
+            let start = if amp { i + 1 } else { i };
+            let mut end = start;
+            while end < chars.len() && is_ident_char(chars[end]) {
+                end += 1;
+            }
+            let name: String = chars[start..end].iter().collect();
+            match names.get(&name.to_lowercase()) {
+                Some(value) => out.push_str(value),
+                None if amp => out.push_str(&format!("&{}", name)),
+                None => out.push_str(&name),
+            }
+            i = end;
+            continue;
+        }
+
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if opens_quote(c, prev) => quote = Some(c),
+            None if c.is_ascii_digit() => {
+                // Numbers like 0d7h are never parameter names
+                while i + 1 < chars.len() && chars[i + 1].is_ascii_alphanumeric() {
+                    out.push(chars[i]);
+                    i += 1;
+                }
+            }
+            None => {}
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
@@ -0,0 +1,28 @@
+// Compares two execution traces & prints the first divergence.
+//   trace-diff OURS THEIRS [CONTEXT]
+// Traces are binary (`Cpu::trace`) or MAME style text, see `pacman_rs::trace`. Exits with 1
+// when the traces diverge.
+use pacman_rs::trace::{compare, open};
+use std::process::exit;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: {} OURS THEIRS [CONTEXT]", args[0]);
+        exit(2);
+    }
+    let context = args.get(3).and_then(|n| n.parse().ok()).unwrap_or(10);
+    let traces = open(&args[1]).and_then(|ours| Ok((ours, open(&args[2])?)));
+    let result = traces.and_then(|(ours, theirs)| compare(ours, theirs, context));
+    match result {
+        Ok(None) => println!("Traces match"),
+        Ok(Some(divergence)) => {
+            println!("{}", divergence);
+            exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    }
+}
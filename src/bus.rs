@@ -0,0 +1,44 @@
+// The system bus as seen from the CPU.
+// Every machine we emulate (the Pacman board, the CP/M test harness etc.) provides its own
+// memory map & I/O port decoding by implementing this trait.
+pub trait Bus {
+    fn read8(&self, addr: u16) -> u8;
+    fn write8(&mut self, addr: u16, byte: u8);
+
+    // I/O requests carry the full 16 bit port address (upper byte is A or B depending on
+    // the instruction). Unmapped ports read back as an open bus (0xFF).
+    fn port_in(&mut self, _port: u16) -> u8 {
+        0xFF
+    }
+    fn port_out(&mut self, _port: u16, _byte: u8) {}
+
+    // The byte the interrupting device puts on the data bus. IM0 executes it as an
+    // instruction (RST 38h for an open bus) & IM2 uses it as the low byte of the vector table
+    // address. The CPU checks it before acknowledging.
+    fn int_data(&self) -> u8 {
+        0xFF
+    }
+
+    // Interrupt acknowledge, returns the byte on the data bus
+    fn int_ack(&mut self) -> u8 {
+        self.int_data()
+    }
+
+    // True (once) when the device has withdrawn its interrupt request, a pending INT that
+    // hasn't been accepted yet is dropped. Checked before every interrupt poll.
+    fn int_cleared(&mut self) -> bool {
+        false
+    }
+
+    // Called by the machine loop at the start of every vertical blank, true if the machine
+    // raises INT for it
+    fn vblank(&mut self) -> bool {
+        false
+    }
+
+    // Called by the machine loop at the end of every vertical blank (the end of the frame)
+    fn vblank_end(&mut self) {}
+
+    // Called every 32 T states, the 96 kHz clock of the sound chip
+    fn sound_tick(&mut self) {}
+}
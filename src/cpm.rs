@@ -0,0 +1,267 @@
+// CP/M 2.2 for running .COM programs, in two flavours:
+//
+// `Cpm::new` is the minimal environment used for the exercisers in `tests/`. Page zero holds
+// the usual entry points as real instructions, so programs see the same timing as on the
+// widely used test harnesses:
+//   0x0000  OUT (0), A   warm boot, ends the program
+//   0x0005  IN A, (0)    BDOS entry, A receives the result of the call
+//   0x0007  RET
+//
+// `Cpm::system` is a complete 64K machine: BDOS at 0xEC06, a BIOS jump table at 0xFA00 with
+// disk parameter headers for the attached drives & the CCP (in `ccp`) reading commands
+// from the console. Drives are host directories or 8" disk images (see `disk`).
+//
+// In both cases the BDOS & BIOS functions are trapped by `Cpm::step` when PC reaches their
+// entry points, where a RET returns to the caller.
+use crate::bus::Bus;
+use crate::cpu::{Cpu, StepInfo};
+use crate::dap::DapServer;
+use crate::debugger::Debugger;
+use crate::error::CpuError;
+use crate::gdb::GdbStub;
+use std::collections::VecDeque;
+use std::thread;
+use std::time::Duration;
+
+mod bdos;
+mod bios;
+mod ccp;
+pub mod disk;
+
+use disk::Drive;
+
+pub const BOOT: u16 = 0x0000;
+pub const BDOS: u16 = 0x0005;
+pub const TPA: u16 = 0x0100;
+pub const DEFAULT_FCB: u16 = 0x005C;
+pub const DEFAULT_DMA: u16 = 0x0080;
+
+// Full system memory map
+pub const BDOS_ENTRY: u16 = 0xEC06;
+pub const BIOS: u16 = 0xFA00;
+pub const BIOS_FUNCTIONS: u16 = 17;
+const BIOS_TRAPS: u16 = 0xFA40; // One RET per jump table entry
+const DPB: u16 = 0xFA60; // Shared by all drives, they have the same geometry
+const XLT: u16 = 0xFA80; // Sector skew table
+const DIRBUF: u16 = 0xFAA0;
+const DPH: u16 = 0xFB00; // 16 bytes per drive
+const ALV: u16 = 0xFC00; // 32 bytes per drive
+const CSV: u16 = 0xFE00; // 16 bytes per drive
+
+pub const DRIVES: usize = 16;
+
+pub struct CpmBus {
+    pub memory: Vec<u8>,
+    pub output: String,      // Everything the program wrote to the console
+    pub printer: String,     // List device output
+    pub input: VecDeque<u8>, // Scripted console input
+    pub warm_boot: bool,
+    result: u8, // BDOS return value, read by the IN A, (0) at the BDOS entry
+}
+
+impl CpmBus {
+    pub fn new() -> Self {
+        let mut memory = vec![0; 0x1_0000];
+        memory[BOOT as usize..BOOT as usize + 2].copy_from_slice(&[0xD3, 0x00]);
+        memory[BDOS as usize..BDOS as usize + 3].copy_from_slice(&[0xDB, 0x00, 0xC9]);
+        CpmBus {
+            memory,
+            output: String::new(),
+            printer: String::new(),
+            input: VecDeque::new(),
+            warm_boot: false,
+            result: 0,
+        }
+    }
+
+    fn write16(&mut self, addr: u16, value: u16) {
+        self.write8(addr, value as u8);
+        self.write8(addr.wrapping_add(1), (value >> 8) as u8);
+    }
+}
+
+impl Default for CpmBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus for CpmBus {
+    fn read8(&self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+    fn write8(&mut self, addr: u16, byte: u8) {
+        self.memory[addr as usize] = byte;
+    }
+    fn port_in(&mut self, port: u16) -> u8 {
+        match port & 0xFF {
+            0 => self.result,
+            _ => 0xFF,
+        }
+    }
+    fn port_out(&mut self, port: u16, _byte: u8) {
+        if port & 0xFF == 0 {
+            self.warm_boot = true;
+        }
+    }
+}
+
+pub struct Cpm {
+    pub cpu: Cpu<CpmBus>,
+    pub drives: Vec<Option<Box<dyn Drive>>>, // A: to P:
+    pub debugger: Debugger,
+    pub gdb: Option<GdbStub>, // Polled by `run`
+    pub dap: Option<DapServer>,
+    bdos_entry: u16,
+    system: bool,
+    drive: u8, // Current drive & user number
+    user: u8,
+    dma: u16,
+    search: VecDeque<[u8; 32]>, // Directory entries left for BDOS 18 (search next)
+    bios: bios::State,
+}
+
+impl Cpm {
+    pub fn new() -> Self {
+        let mut cpu = Cpu::new(CpmBus::new());
+        cpu.reset();
+        cpu.reg.pc = TPA;
+        Cpm {
+            cpu,
+            drives: (0..DRIVES).map(|_| None).collect(),
+            debugger: Debugger::new(),
+            gdb: None,
+            dap: None,
+            bdos_entry: BDOS,
+            system: false,
+            drive: 0,
+            user: 0,
+            dma: DEFAULT_DMA,
+            search: VecDeque::new(),
+            bios: bios::State::default(),
+        }
+    }
+
+    // Complete machine without drives, see `attach`. `session` runs the CCP.
+    pub fn system() -> Self {
+        let mut cpm = Cpm::new();
+        cpm.system = true;
+        cpm.bdos_entry = BDOS_ENTRY;
+
+        let bus = &mut cpm.cpu.bus;
+        bus.memory[..DEFAULT_DMA as usize]
+            .iter_mut()
+            .for_each(|b| *b = 0);
+        bus.write8(BOOT, 0xC3); // JP WBOOT
+        bus.write16(BOOT + 1, BIOS + 3);
+        bus.write8(BDOS, 0xC3); // JP BDOS
+        bus.write16(BDOS + 1, BDOS_ENTRY);
+        bus.write8(BDOS_ENTRY, 0xC9);
+        for i in 0..BIOS_FUNCTIONS {
+            bus.write8(BIOS + i * 3, 0xC3);
+            bus.write16(BIOS + i * 3 + 1, BIOS_TRAPS + i);
+            bus.write8(BIOS_TRAPS + i, 0xC9);
+        }
+        bios::write_tables(bus);
+        cpm
+    }
+
+    // Attaches a drive (0 = A:), replacing what was there before
+    pub fn attach(&mut self, drive: usize, storage: Box<dyn Drive>) {
+        self.drives[drive] = Some(storage);
+        if self.system {
+            bios::write_dph(&mut self.cpu.bus, drive as u16);
+        }
+    }
+
+    // Copies a .COM image to the TPA & points PC at it
+    pub fn load_com(&mut self, program: &[u8]) {
+        let start = TPA as usize;
+        self.cpu.bus.memory[start..start + program.len()].copy_from_slice(program);
+        self.cpu.reg.pc = TPA;
+        self.cpu.bus.warm_boot = false;
+    }
+
+    // Queues console input, line ends may be given as '\n'
+    pub fn input(&mut self, text: &str) {
+        let bytes = text.bytes().map(|b| if b == b'\n' { b'\r' } else { b });
+        self.cpu.bus.input.extend(bytes);
+    }
+
+    pub fn output(&self) -> &str {
+        &self.cpu.bus.output
+    }
+
+    // Runs until the program warm boots or halts, returns the total T states. Without a GDB
+    // stub or DAP server a debugger stop returns early as well.
+    pub fn run(&mut self) -> Result<usize, CpuError> {
+        let mut next_poll = self.cpu.cycles;
+        // Nothing raises interrupts here, a HALT never resumes (even after EI)
+        while !self.cpu.bus.warm_boot && !self.cpu.halted {
+            let remote = self.gdb.is_some() || self.dap.is_some();
+            // About once per emulated millisecond, continuously while stopped
+            if remote && (self.debugger.is_stopped() || self.cpu.cycles >= next_poll) {
+                next_poll = self.cpu.cycles + 4000;
+                self.poll_remote();
+            }
+            if !self.debugger.enabled {
+                self.step()?;
+                continue;
+            }
+            if self.debugger.check(&mut self.cpu) {
+                if !remote {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(1));
+                continue;
+            }
+            let (pc, sp) = (self.cpu.reg.pc, self.cpu.reg.sp);
+            self.step()?;
+            self.debugger.after_step(&mut self.cpu, pc, sp);
+        }
+        if let Some(dap) = &mut self.dap {
+            dap.terminated().ok();
+        }
+        Ok(self.cpu.cycles)
+    }
+
+    // Closed connections are dropped
+    fn poll_remote(&mut self) {
+        if let Some(gdb) = &mut self.gdb {
+            if let Err(e) = gdb.poll(&mut self.debugger, &mut self.cpu) {
+                log::error!("GDB stub closed: {}", e);
+                self.gdb = None;
+            }
+        }
+        if let Some(dap) = &mut self.dap {
+            if let Err(e) = dap.poll(&mut self.debugger, &mut self.cpu) {
+                log::error!("DAP server closed: {}", e);
+                self.dap = None;
+            } else if !dap.connected() {
+                self.dap = None;
+            }
+        }
+    }
+
+    pub fn step(&mut self) -> Result<StepInfo, CpuError> {
+        let pc = self.cpu.reg.pc;
+        if pc == self.bdos_entry {
+            self.bdos()?;
+        } else if self.system && (BIOS_TRAPS..BIOS_TRAPS + BIOS_FUNCTIONS).contains(&pc) {
+            self.bios(pc - BIOS_TRAPS)?;
+        }
+        self.cpu.step()
+    }
+
+    // Runs CCP command lines until the console input is exhausted
+    pub fn session(&mut self) -> Result<(), CpuError> {
+        while self.ccp()? {}
+        Ok(())
+    }
+}
+
+impl Default for Cpm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,502 @@
+// BDOS functions: console I/O & FCB based file access on the attached drives.
+//
+// File positions live in the FCB like on CP/M 2.2, an extent covers 128 records (16K):
+// record = ((S2 * 32) + EX) * 128 + CR. Files are addressed by name only, so programs
+// can copy or move their FCBs around freely.
+use super::disk::{dir_name, file_name, RECORD};
+use super::{Cpm, ALV, DEFAULT_DMA, DPB};
+use crate::bus::Bus;
+use crate::error::CpuError;
+use crate::instruction_info::Register::DE;
+
+const EXTENT: usize = 128;
+
+impl Cpm {
+    // Performs the BDOS function in C. Functions returning a value set HL, B = H & A = L
+    // like CP/M 2.2. On the test harness the stub at the entry point then loads A again.
+    pub(super) fn bdos(&mut self) -> Result<(), CpuError> {
+        let de = self.cpu.get_pair(DE);
+        let e = self.cpu.reg.e;
+        self.cpu.bus.result = 0;
+        let result = match self.cpu.reg.c {
+            0 => {
+                self.cpu.bus.warm_boot = true;
+                return Ok(());
+            }
+            1 => {
+                let c = self.read_console();
+                self.echo(c);
+                Some(c as u16)
+            }
+            2 => {
+                self.cpu.bus.output.push(e as char);
+                None
+            }
+            3 => Some(0x1A), // No reader attached
+            4 => None,
+            5 => {
+                self.cpu.bus.printer.push(e as char);
+                None
+            }
+            6 => match e {
+                0xFF => Some(self.cpu.bus.input.pop_front().unwrap_or(0) as u16),
+                0xFE => Some(self.console_status() as u16),
+                e => {
+                    self.cpu.bus.output.push(e as char);
+                    None
+                }
+            },
+            7 => Some(self.cpu.bus.read8(0x0003) as u16),
+            8 => {
+                self.cpu.bus.write8(0x0003, e);
+                None
+            }
+            9 => {
+                // At most once around the 64K, a string without '$' stops the program
+                let bus = &self.cpu.bus;
+                let text: Vec<u8> = (0..=0xFFFF)
+                    .map(|i| bus.read8(de.wrapping_add(i)))
+                    .take_while(|&b| b != b'$')
+                    .collect();
+                if text.len() > 0xFFFF {
+                    return Err(CpuError::BadCall {
+                        call: String::from("BDOS function 9"),
+                        reason: format!("no '$' after the string at {:04X}", de),
+                    });
+                }
+                self.cpu.bus.output.extend(text.iter().map(|&b| b as char));
+                None
+            }
+            10 => {
+                self.read_buffer(de);
+                None
+            }
+            11 => Some(self.console_status() as u16),
+            12 => Some(0x0022), // CP/M 2.2
+            13 => {
+                self.dma = DEFAULT_DMA;
+                self.select(0);
+                Some(0)
+            }
+            14 => {
+                self.select(e);
+                Some(0)
+            }
+            15 => Some(self.open(de) as u16),
+            16 => Some(self.close(de) as u16),
+            17 => Some(self.search_first(de) as u16),
+            18 => Some(self.search_next() as u16),
+            19 => Some(self.delete(de) as u16),
+            20 => Some(self.read_sequential(de) as u16),
+            21 => Some(self.write_sequential(de) as u16),
+            22 => Some(self.make(de) as u16),
+            23 => Some(self.rename(de) as u16),
+            24 => Some(
+                (0..self.drives.len())
+                    .filter(|&d| self.drives[d].is_some())
+                    .fold(0, |vector, d| vector | 1 << d),
+            ),
+            25 => Some(self.drive as u16),
+            26 => {
+                self.dma = de;
+                None
+            }
+            27 => Some(self.allocation_vector()),
+            28..=30 | 37 => Some(0), // Nothing is write protected, attributes aren't kept
+            31 => Some(DPB),
+            32 => match e {
+                0xFF => Some(self.user as u16),
+                user => {
+                    self.user = user & 0x0F;
+                    self.store_drive_user();
+                    None
+                }
+            },
+            33 => Some(self.read_random(de) as u16),
+            34 | 40 => Some(self.write_random(de) as u16),
+            35 => {
+                let records = self.fcb(de).and_then(|(d, name)| self.records(d, &name));
+                self.set_random(de, records.unwrap_or(0));
+                None
+            }
+            36 => {
+                let record = self.position(de);
+                self.set_random(de, record);
+                None
+            }
+            c => {
+                return Err(CpuError::Unimplemented {
+                    mnemonic: format!("BDOS function {}", c),
+                })
+            }
+        };
+
+        if let Some(hl) = result {
+            self.cpu.reg.h = (hl >> 8) as u8;
+            self.cpu.reg.l = hl as u8;
+            self.cpu.reg.b = self.cpu.reg.h;
+            self.cpu.reg.a = self.cpu.reg.l;
+            self.cpu.bus.result = self.cpu.reg.l;
+        }
+        Ok(())
+    }
+
+    // The scripted input reads as end of file (^Z) once exhausted
+    pub(super) fn read_console(&mut self) -> u8 {
+        self.cpu.bus.input.pop_front().unwrap_or(0x1A)
+    }
+
+    fn console_status(&self) -> u8 {
+        match self.cpu.bus.input.is_empty() {
+            true => 0x00,
+            false => 0xFF,
+        }
+    }
+
+    pub(super) fn echo(&mut self, c: u8) {
+        match c {
+            b'\r' => self.cpu.bus.output.push_str("\r\n"),
+            c if c >= 0x20 => self.cpu.bus.output.push(c as char),
+            _ => {}
+        }
+    }
+
+    // BDOS 10, line input into the buffer at `addr`: max length, count, characters
+    fn read_buffer(&mut self, addr: u16) {
+        let max = self.cpu.bus.read8(addr);
+        let mut count = 0;
+        while count < max {
+            let c = self.read_console();
+            if c == b'\r' || c == 0x1A {
+                break;
+            }
+            self.echo(c);
+            self.cpu.bus.write8(addr.wrapping_add(2 + count as u16), c);
+            count += 1;
+        }
+        self.echo(b'\r');
+        self.cpu.bus.write8(addr.wrapping_add(1), count);
+    }
+
+    // Makes `drive` current, selecting a missing drive is fatal like on CP/M 2.2
+    pub(super) fn select(&mut self, drive: u8) -> bool {
+        let attached = self.attached(drive as usize);
+        if attached {
+            self.drive = drive;
+            self.store_drive_user();
+        }
+        attached
+    }
+
+    pub(super) fn attached(&mut self, drive: usize) -> bool {
+        let attached = self.drives.get(drive).is_some_and(Option::is_some);
+        if !attached {
+            let letter = (b'A' + (drive as u8 & 0x0F)) as char;
+            self.cpu
+                .bus
+                .output
+                .push_str(&format!("\r\nBdos Err On {}: Select", letter));
+            self.cpu.bus.warm_boot = true;
+        }
+        attached
+    }
+
+    // Page zero copy for programs reading it directly
+    fn store_drive_user(&mut self) {
+        if self.system {
+            self.cpu.bus.write8(0x0004, self.user << 4 | self.drive);
+        }
+    }
+
+    // Drive & blank padded name of the FCB at `fcb`, None if the drive is missing
+    pub(super) fn fcb(&mut self, fcb: u16) -> Option<(usize, [u8; 11])> {
+        let drive = match self.cpu.bus.read8(fcb) & 0x1F {
+            0 => self.drive as usize,
+            d => d as usize - 1,
+        };
+        let mut name = [0; 11];
+        for (i, b) in name.iter_mut().enumerate() {
+            *b = (self.cpu.bus.read8(fcb.wrapping_add(1 + i as u16)) & 0x7F).to_ascii_uppercase();
+        }
+        match self.attached(drive) {
+            true => Some((drive, name)),
+            false => None,
+        }
+    }
+
+    // Files on `drive` matching a name with `?` wildcards
+    pub(super) fn matching(&self, drive: usize, pattern: &[u8; 11]) -> Vec<String> {
+        let storage = match &self.drives[drive] {
+            Some(storage) => storage,
+            None => return Vec::new(),
+        };
+        storage
+            .files()
+            .into_iter()
+            .filter(|file| {
+                dir_name(file).is_some_and(|name| {
+                    name.iter()
+                        .zip(pattern.iter())
+                        .all(|(n, p)| *p == b'?' || n == p)
+                })
+            })
+            .collect()
+    }
+
+    fn records(&self, drive: usize, name: &[u8; 11]) -> Option<usize> {
+        self.drives[drive].as_ref()?.records(&file_name(name))
+    }
+
+    fn position(&self, fcb: u16) -> usize {
+        let byte = |offset: u16| self.cpu.bus.read8(fcb.wrapping_add(offset)) as usize;
+        let extent = (byte(14) & 0x3F) * 32 + (byte(12) & 0x1F);
+        extent * EXTENT + byte(32).min(EXTENT - 1)
+    }
+
+    // Moves the FCB to `record`, RC counts the records of the file in its extent
+    fn set_position(&mut self, fcb: u16, record: usize, records: usize) {
+        let extent = record / EXTENT;
+        let in_extent = records.saturating_sub(extent * EXTENT).min(EXTENT);
+        let bus = &mut self.cpu.bus;
+        bus.write8(fcb.wrapping_add(12), (extent % 32) as u8);
+        bus.write8(fcb.wrapping_add(14), (extent / 32) as u8);
+        bus.write8(fcb.wrapping_add(15), in_extent as u8);
+        bus.write8(fcb.wrapping_add(32), (record % EXTENT) as u8);
+    }
+
+    fn set_random(&mut self, fcb: u16, record: usize) {
+        for i in 0..3 {
+            let byte = (record >> (8 * i)) as u8;
+            self.cpu.bus.write8(fcb.wrapping_add(33 + i as u16), byte);
+        }
+    }
+
+    fn open(&mut self, fcb: u16) -> u8 {
+        let (drive, pattern) = match self.fcb(fcb) {
+            Some(found) => found,
+            None => return 0xFF,
+        };
+        let name = match self
+            .matching(drive, &pattern)
+            .first()
+            .and_then(|f| dir_name(f))
+        {
+            Some(name) => name,
+            None => return 0xFF,
+        };
+        for (i, &b) in name.iter().enumerate() {
+            self.cpu.bus.write8(fcb.wrapping_add(1 + i as u16), b);
+        }
+
+        let records = self.records(drive, &name).unwrap_or(0);
+        let record = self.position(fcb);
+        if record >= EXTENT && record / EXTENT * EXTENT >= records {
+            return 0xFF;
+        }
+        self.set_position(fcb, record, records);
+        0
+    }
+
+    fn close(&mut self, fcb: u16) -> u8 {
+        match self.fcb(fcb) {
+            Some((drive, name)) if self.records(drive, &name).is_some() => 0,
+            _ => 0xFF,
+        }
+    }
+
+    // Collects a directory entry per extent of every matching file, a `?` drive code
+    // matches all files
+    fn search_first(&mut self, fcb: u16) -> u8 {
+        self.search.clear();
+        let all = self.cpu.bus.read8(fcb) == b'?';
+        let (drive, pattern) = match all {
+            true if self.attached(self.drive as usize) => (self.drive as usize, [b'?'; 11]),
+            true => return 0xFF,
+            false => match self.fcb(fcb) {
+                Some(found) => found,
+                None => return 0xFF,
+            },
+        };
+        let ex = self.cpu.bus.read8(fcb.wrapping_add(12));
+
+        for file in self.matching(drive, &pattern) {
+            let name = dir_name(&file).unwrap_or([b' '; 11]);
+            let records = self.records(drive, &name).unwrap_or(0);
+            let extents = records.div_ceil(EXTENT).max(1);
+            for extent in (0..extents).filter(|&x| all || ex == b'?' || x == ex as usize) {
+                let mut entry = [0; 32];
+                entry[0] = self.user;
+                entry[1..12].copy_from_slice(&name);
+                entry[12] = (extent % 32) as u8;
+                entry[14] = (extent / 32) as u8;
+                entry[15] = records.saturating_sub(extent * EXTENT).min(EXTENT) as u8;
+                self.search.push_back(entry);
+            }
+        }
+        self.search_next()
+    }
+
+    // The entry goes to the start of the DMA buffer, A is its index in the record
+    fn search_next(&mut self) -> u8 {
+        match self.search.pop_front() {
+            Some(entry) => {
+                for (i, &b) in entry.iter().enumerate() {
+                    self.cpu.bus.write8(self.dma.wrapping_add(i as u16), b);
+                }
+                0
+            }
+            None => 0xFF,
+        }
+    }
+
+    fn delete(&mut self, fcb: u16) -> u8 {
+        let (drive, pattern) = match self.fcb(fcb) {
+            Some(found) => found,
+            None => return 0xFF,
+        };
+        let files = self.matching(drive, &pattern);
+        let storage = self.drives[drive].as_mut().unwrap();
+        match files.iter().filter(|file| storage.delete(file)).count() {
+            0 => 0xFF,
+            _ => 0,
+        }
+    }
+
+    fn make(&mut self, fcb: u16) -> u8 {
+        let (drive, name) = match self.fcb(fcb) {
+            Some(found) => found,
+            None => return 0xFF,
+        };
+        match self.drives[drive]
+            .as_mut()
+            .unwrap()
+            .create(&file_name(&name))
+        {
+            true => {
+                self.set_position(fcb, self.position(fcb), 0);
+                0
+            }
+            false => 0xFF,
+        }
+    }
+
+    // New name in the second half of the FCB
+    fn rename(&mut self, fcb: u16) -> u8 {
+        let (drive, from) = match self.fcb(fcb) {
+            Some(found) => found,
+            None => return 0xFF,
+        };
+        let to = match self.fcb(fcb.wrapping_add(16)) {
+            Some((_, to)) => to,
+            None => return 0xFF,
+        };
+        let storage = self.drives[drive].as_mut().unwrap();
+        match storage.rename(&file_name(&from), &file_name(&to)) {
+            true => 0,
+            false => 0xFF,
+        }
+    }
+
+    fn read_record(&mut self, fcb: u16, record: usize) -> Option<usize> {
+        let (drive, name) = self.fcb(fcb)?;
+        let storage = self.drives[drive].as_mut().unwrap();
+        let data = storage.read(&file_name(&name), record)?;
+        let records = storage.records(&file_name(&name)).unwrap_or(0);
+        for (i, &b) in data.iter().enumerate() {
+            self.cpu.bus.write8(self.dma.wrapping_add(i as u16), b);
+        }
+        Some(records)
+    }
+
+    fn write_record(&mut self, fcb: u16, record: usize) -> Option<usize> {
+        let (drive, name) = self.fcb(fcb)?;
+        let mut data = [0; RECORD];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = self.cpu.bus.read8(self.dma.wrapping_add(i as u16));
+        }
+        let storage = self.drives[drive].as_mut().unwrap();
+        match storage.write(&file_name(&name), record, &data) {
+            true => storage.records(&file_name(&name)),
+            false => None,
+        }
+    }
+
+    // 1 at the end of the file
+    fn read_sequential(&mut self, fcb: u16) -> u8 {
+        let record = self.position(fcb);
+        match self.read_record(fcb, record) {
+            Some(records) => {
+                self.set_position(fcb, record + 1, records);
+                0
+            }
+            None => 1,
+        }
+    }
+
+    // 2 when the disk is full
+    fn write_sequential(&mut self, fcb: u16) -> u8 {
+        let record = self.position(fcb);
+        match self.write_record(fcb, record) {
+            Some(records) => {
+                self.set_position(fcb, record + 1, records);
+                0
+            }
+            None => 2,
+        }
+    }
+
+    // Random access leaves the FCB at the record, the next sequential access repeats it
+    fn random_record(&self, fcb: u16) -> Option<usize> {
+        let byte = |offset: u16| self.cpu.bus.read8(fcb.wrapping_add(offset)) as usize;
+        match byte(35) {
+            0 => Some(byte(34) << 8 | byte(33)),
+            _ => None,
+        }
+    }
+
+    // 1 reading unwritten data, 6 past the end of the disk
+    fn read_random(&mut self, fcb: u16) -> u8 {
+        let record = match self.random_record(fcb) {
+            Some(record) => record,
+            None => return 6,
+        };
+        match self.read_record(fcb, record) {
+            Some(records) => {
+                self.set_position(fcb, record, records);
+                0
+            }
+            None => 1,
+        }
+    }
+
+    fn write_random(&mut self, fcb: u16) -> u8 {
+        let record = match self.random_record(fcb) {
+            Some(record) => record,
+            None => return 6,
+        };
+        match self.write_record(fcb, record) {
+            Some(records) => {
+                self.set_position(fcb, record, records);
+                0
+            }
+            None => 2,
+        }
+    }
+
+    // Bit map of the blocks in use on the current drive, most significant bit first
+    fn allocation_vector(&mut self) -> u16 {
+        let addr = ALV + self.drive as u16 * 32;
+        let used = match &self.drives[self.drive as usize] {
+            Some(storage) => storage.used_blocks(),
+            None => Vec::new(),
+        };
+        for i in 0..32u16 {
+            let byte = (0..8).fold(0, |byte, bit| {
+                let block = (i * 8 + bit) as usize;
+                byte | (used.get(block).copied().unwrap_or(false) as u8) << (7 - bit)
+            });
+            self.cpu.bus.write8(addr + i, byte);
+        }
+        addr
+    }
+}
@@ -0,0 +1,169 @@
+// BIOS jump table functions & the disk parameter tables of the full system. Every drive
+// is described with the IBM 3740 geometry, host directories simply have no sectors.
+use super::disk::{BLOCKS, DIR_ENTRIES, RECORD, SECTORS, SKEW, SYSTEM_TRACKS};
+use super::{Cpm, CpmBus, ALV, CSV, DIRBUF, DPB, DPH, XLT};
+use crate::bus::Bus;
+use crate::error::CpuError;
+use crate::instruction_info::Register::{BC, DE};
+
+// Selected drive, track, sector & DMA address for READ / WRITE
+#[derive(Default, Clone, Copy)]
+pub(super) struct State {
+    disk: usize,
+    track: u16,
+    sector: u16,
+    dma: u16,
+}
+
+// DPB & skew table
+pub(super) fn write_tables(bus: &mut CpmBus) {
+    bus.write16(DPB, SECTORS as u16); // SPT
+    bus.write8(DPB + 2, 3); // BSH, 1K blocks
+    bus.write8(DPB + 3, 7); // BLM
+    bus.write8(DPB + 4, 0); // EXM
+    bus.write16(DPB + 5, BLOCKS as u16 - 1); // DSM
+    bus.write16(DPB + 7, DIR_ENTRIES as u16 - 1); // DRM
+    bus.write8(DPB + 9, 0xC0); // AL0 & AL1, two directory blocks
+    bus.write8(DPB + 10, 0x00);
+    bus.write16(DPB + 11, DIR_ENTRIES as u16 / 4); // CKS
+    bus.write16(DPB + 13, SYSTEM_TRACKS as u16); // OFF
+    for (i, &sector) in SKEW.iter().enumerate() {
+        bus.write8(XLT + i as u16, sector);
+    }
+}
+
+// Disk parameter header of `drive`, returned by SELDSK
+pub(super) fn write_dph(bus: &mut CpmBus, drive: u16) {
+    let dph = DPH + drive * 16;
+    bus.write16(dph, XLT);
+    (2..8).for_each(|i| bus.write8(dph + i, 0)); // BDOS scratch area
+    bus.write16(dph + 8, DIRBUF);
+    bus.write16(dph + 10, DPB);
+    bus.write16(dph + 12, CSV + drive * 16);
+    bus.write16(dph + 14, ALV + drive * 32);
+}
+
+impl Cpm {
+    // Jump table entry `function`: BOOT, WBOOT, CONST, CONIN, CONOUT, LIST, PUNCH, READER,
+    // HOME, SELDSK, SETTRK, SETSEC, SETDMA, READ, WRITE, LISTST, SECTRAN
+    pub(super) fn bios(&mut self, function: u16) -> Result<(), CpuError> {
+        let bc = self.cpu.get_pair(BC);
+        let c = self.cpu.reg.c;
+        let a = match function {
+            0 | 1 => {
+                self.cpu.bus.warm_boot = true;
+                None
+            }
+            2 => Some(match self.cpu.bus.input.is_empty() {
+                true => 0x00,
+                false => 0xFF,
+            }),
+            3 => Some(self.read_console()),
+            4 => {
+                self.cpu.bus.output.push(c as char);
+                None
+            }
+            5 => {
+                self.cpu.bus.printer.push(c as char);
+                None
+            }
+            6 => None,
+            7 => Some(0x1A),
+            8 => {
+                self.bios.track = 0;
+                None
+            }
+            9 => {
+                let dph = match self.drives.get(c as usize) {
+                    Some(Some(_)) => {
+                        self.bios.disk = c as usize;
+                        DPH + c as u16 * 16
+                    }
+                    _ => 0,
+                };
+                self.cpu.reg.h = (dph >> 8) as u8;
+                self.cpu.reg.l = dph as u8;
+                None
+            }
+            10 => {
+                self.bios.track = bc;
+                None
+            }
+            11 => {
+                self.bios.sector = bc;
+                None
+            }
+            12 => {
+                self.bios.dma = bc;
+                None
+            }
+            13 => Some(self.read_sector()),
+            14 => Some(self.write_sector()),
+            15 => Some(0xFF), // The printer is always ready
+            16 => {
+                let xlt = self.cpu.get_pair(DE);
+                let sector = match xlt {
+                    0 => bc,
+                    xlt => self.cpu.bus.read8(xlt.wrapping_add(bc)) as u16,
+                };
+                self.cpu.reg.h = (sector >> 8) as u8;
+                self.cpu.reg.l = sector as u8;
+                None
+            }
+            f => {
+                return Err(CpuError::Unimplemented {
+                    mnemonic: format!("BIOS function {}", f),
+                })
+            }
+        };
+
+        if let Some(a) = a {
+            self.cpu.reg.a = a;
+        }
+        Ok(())
+    }
+
+    // 0 on success, 1 for a drive without sectors or a bad track / sector
+    fn read_sector(&mut self) -> u8 {
+        let State {
+            disk,
+            track,
+            sector,
+            dma,
+        } = self.bios;
+        let data = match &mut self.drives[disk] {
+            Some(storage) => storage.read_sector(track, sector),
+            None => None,
+        };
+        match data {
+            Some(data) => {
+                for (i, &b) in data.iter().enumerate() {
+                    self.cpu.bus.write8(dma.wrapping_add(i as u16), b);
+                }
+                0
+            }
+            None => 1,
+        }
+    }
+
+    fn write_sector(&mut self) -> u8 {
+        let State {
+            disk,
+            track,
+            sector,
+            dma,
+        } = self.bios;
+        let mut data = [0; RECORD];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = self.cpu.bus.read8(dma.wrapping_add(i as u16));
+        }
+        let written = match &mut self.drives[disk] {
+            Some(storage) => storage.write_sector(track, sector, &data),
+            None => false,
+        };
+        match written {
+            true => 0,
+            false => 1,
+        }
+    }
+}
@@ -0,0 +1,278 @@
+// Console command processor. Built in commands are DIR, ERA, REN, TYPE, SAVE, USER & drive
+// changes ("B:"), anything else loads NAME.COM into the TPA & runs it until it warm boots.
+// Page zero is set up like CP/M 2.2 does: FCBs for the first two arguments at 0x5C &
+// 0x6C, the command tail at 0x80.
+use super::disk::{dir_name, file_name, RECORD};
+use super::{Cpm, BDOS_ENTRY, DEFAULT_DMA, DEFAULT_FCB, TPA};
+use crate::bus::Bus;
+use crate::error::CpuError;
+
+// Drive code (0 = current, 1 = A:) & name of "B:NAME.EXT", `*` fills a field with `?`
+fn parse_name(token: &str) -> (u8, [u8; 11]) {
+    let bytes = token.as_bytes();
+    let (drive, rest) = match bytes {
+        [d @ b'A'..=b'P', b':', ..] => (d - b'A' + 1, &token[2..]),
+        _ => (0, token),
+    };
+    let (base, ext) = match rest.find('.') {
+        Some(dot) => (&rest[..dot], &rest[dot + 1..]),
+        None => (rest, ""),
+    };
+
+    let mut name = [b' '; 11];
+    let fill = |field: &mut [u8], text: &str| {
+        for (i, c) in text.bytes().take(field.len()).enumerate() {
+            if c == b'*' {
+                field[i..].iter_mut().for_each(|b| *b = b'?');
+                break;
+            }
+            field[i] = c;
+        }
+    };
+    let (base_field, ext_field) = name.split_at_mut(8);
+    fill(base_field, base);
+    fill(ext_field, ext);
+    (drive, name)
+}
+
+impl Cpm {
+    // Prompts for & executes one command line, false once the console input is exhausted
+    pub(super) fn ccp(&mut self) -> Result<bool, CpuError> {
+        self.cpu.bus.warm_boot = false;
+        self.dma = DEFAULT_DMA;
+        let prompt = format!("\r\n{}>", (b'A' + self.drive) as char);
+        self.cpu.bus.output.push_str(&prompt);
+        if self.cpu.bus.input.is_empty() {
+            return Ok(false);
+        }
+
+        let line = self.read_line().to_ascii_uppercase();
+        let line = line.trim_start();
+        let command = match line.split_whitespace().next() {
+            Some(command) => command,
+            None => return Ok(true),
+        };
+        let tail = &line[command.len()..];
+        let args: Vec<&str> = tail.split_whitespace().collect();
+
+        match command {
+            "DIR" => self.dir(args.first().copied().unwrap_or("*.*")),
+            "ERA" if args.len() == 1 => self.era(args[0]),
+            "REN" if args.len() == 1 => self.ren(args[0]),
+            "TYPE" if args.len() == 1 => self.type_file(args[0]),
+            "SAVE" if args.len() == 2 => self.save(args[0], args[1]),
+            "USER" => match args.first().and_then(|n| n.parse::<u8>().ok()) {
+                Some(user) if user < 16 => self.user = user,
+                _ => self.error(line),
+            },
+            drive if drive.len() == 2 && drive.ends_with(':') => {
+                self.select(drive.as_bytes()[0].wrapping_sub(b'A'));
+            }
+            "ERA" | "REN" | "TYPE" | "SAVE" => self.error(line),
+            _ => self.transient(command, tail)?,
+        }
+        Ok(true)
+    }
+
+    // Echoed console line without the CR
+    fn read_line(&mut self) -> String {
+        let mut line = String::new();
+        loop {
+            let c = self.read_console();
+            self.echo(c);
+            match c {
+                b'\r' | 0x1A => return line,
+                c => line.push(c as char),
+            }
+        }
+    }
+
+    fn print(&mut self, text: &str) {
+        self.cpu.bus.output.push_str(text);
+    }
+
+    // Unknown commands & bad arguments are echoed with a question mark
+    fn error(&mut self, text: &str) {
+        self.print(&format!("\r\n{}?", text.trim()));
+    }
+
+    fn drive_of(&self, code: u8) -> usize {
+        match code {
+            0 => self.drive as usize,
+            d => d as usize - 1,
+        }
+    }
+
+    fn dir(&mut self, pattern: &str) {
+        let (code, pattern) = parse_name(pattern);
+        let drive = self.drive_of(code);
+        if !self.attached(drive) {
+            return;
+        }
+        let files = self.matching(drive, &pattern);
+        if files.is_empty() {
+            self.print("\r\nNO FILE");
+        }
+        for (i, file) in files.iter().enumerate() {
+            let name = dir_name(file).unwrap_or([b' '; 11]);
+            let separator = match i % 4 {
+                0 => format!("\r\n{}", (b'A' + drive as u8) as char),
+                _ => String::from(" "),
+            };
+            let base = String::from_utf8_lossy(&name[..8]).into_owned();
+            let ext = String::from_utf8_lossy(&name[8..]).into_owned();
+            self.print(&format!("{}: {} {}", separator, base, ext));
+        }
+    }
+
+    fn era(&mut self, pattern: &str) {
+        let (code, pattern) = parse_name(pattern);
+        let drive = self.drive_of(code);
+        if pattern == [b'?'; 11] {
+            self.print("\r\nALL (Y/N)?");
+            if !self.read_line().eq_ignore_ascii_case("Y") {
+                return;
+            }
+        }
+        if !self.attached(drive) {
+            return;
+        }
+        let files = self.matching(drive, &pattern);
+        let storage = self.drives[drive].as_mut().unwrap();
+        if files.iter().filter(|file| storage.delete(file)).count() == 0 {
+            self.print("\r\nNO FILE");
+        }
+    }
+
+    // REN NEW=OLD
+    fn ren(&mut self, arg: &str) {
+        let (to, from) = match arg.find('=') {
+            Some(eq) => (parse_name(&arg[..eq]), parse_name(&arg[eq + 1..])),
+            None => return self.error(arg),
+        };
+        let drive = self.drive_of(from.0.max(to.0));
+        if !self.attached(drive) {
+            return;
+        }
+        if !self.matching(drive, &to.1).is_empty() {
+            return self.print("\r\nFILE EXISTS");
+        }
+        let storage = self.drives[drive].as_mut().unwrap();
+        if !storage.rename(&file_name(&from.1), &file_name(&to.1)) {
+            self.print("\r\nNO FILE");
+        }
+    }
+
+    // Text up to the first ^Z
+    fn type_file(&mut self, arg: &str) {
+        let (code, name) = parse_name(arg);
+        let drive = self.drive_of(code);
+        if !self.attached(drive) {
+            return;
+        }
+        let storage = self.drives[drive].as_mut().unwrap();
+        let file = file_name(&name);
+        if storage.records(&file).is_none() {
+            return self.print("\r\nNO FILE");
+        }
+
+        let mut text = String::from("\r\n");
+        let mut record = 0;
+        while let Some(data) = storage.read(&file, record) {
+            match data.iter().position(|&c| c == 0x1A) {
+                Some(end) => {
+                    text.extend(data[..end].iter().map(|&c| c as char));
+                    break;
+                }
+                None => text.extend(data.iter().map(|&c| c as char)),
+            }
+            record += 1;
+        }
+        self.print(&text);
+    }
+
+    // SAVE N FILE, writes N pages from the TPA
+    fn save(&mut self, pages: &str, arg: &str) {
+        let pages = match pages.parse::<usize>() {
+            Ok(pages) if pages < 256 => pages,
+            _ => return self.error(arg),
+        };
+        let (code, name) = parse_name(arg);
+        let drive = self.drive_of(code);
+        if !self.attached(drive) {
+            return;
+        }
+        let file = file_name(&name);
+        let memory = &self.cpu.bus.memory;
+        let storage = self.drives[drive].as_mut().unwrap();
+        let saved = storage.create(&file)
+            && (0..pages * 2).all(|record| {
+                let start = TPA as usize + record * RECORD;
+                let mut data = [0; RECORD];
+                data.copy_from_slice(&memory[start..start + RECORD]);
+                storage.write(&file, record, &data)
+            });
+        if !saved {
+            self.print("\r\nNO SPACE");
+        }
+    }
+
+    // Loads COMMAND.COM & runs it with `tail` as arguments
+    fn transient(&mut self, command: &str, tail: &str) -> Result<(), CpuError> {
+        let (code, mut name) = parse_name(command);
+        if name.contains(&b'?') || &name[8..] != b"   " {
+            self.error(command);
+            return Ok(());
+        }
+        name[8..].copy_from_slice(b"COM");
+        let drive = self.drive_of(code);
+        if !self.attached(drive) {
+            return Ok(());
+        }
+
+        let file = file_name(&name);
+        let storage = self.drives[drive].as_mut().unwrap();
+        let mut addr = TPA as usize;
+        let mut record = 0;
+        while addr + RECORD <= BDOS_ENTRY as usize {
+            match storage.read(&file, record) {
+                Some(data) => self.cpu.bus.memory[addr..addr + RECORD].copy_from_slice(&data),
+                None => break,
+            }
+            addr += RECORD;
+            record += 1;
+        }
+        if record == 0 {
+            self.error(command);
+            return Ok(());
+        }
+
+        // Default FCBs & command tail
+        let args: Vec<&str> = tail.split_whitespace().collect();
+        for addr in DEFAULT_FCB..DEFAULT_DMA {
+            self.cpu.bus.write8(addr, 0);
+        }
+        for i in 0..2 {
+            let fcb = DEFAULT_FCB + i as u16 * 16;
+            let (code, name) = args.get(i).map_or((0, [b' '; 11]), |arg| parse_name(arg));
+            self.cpu.bus.write8(fcb, code);
+            for (j, &b) in name.iter().enumerate() {
+                self.cpu.bus.write8(fcb + 1 + j as u16, b);
+            }
+        }
+        let tail = &tail.as_bytes()[..tail.len().min(127)];
+        self.cpu.bus.write8(DEFAULT_DMA, tail.len() as u8);
+        for (i, &b) in tail.iter().enumerate() {
+            self.cpu.bus.write8(DEFAULT_DMA + 1 + i as u16, b);
+        }
+
+        // A RET from the program warm boots
+        self.cpu.reg.sp = BDOS_ENTRY & 0xFF00;
+        self.cpu.reg.sp -= 2;
+        self.cpu.bus.write16(self.cpu.reg.sp, 0x0000);
+        self.cpu.reg.pc = TPA;
+        self.cpu.halted = false;
+        self.run()?;
+        Ok(())
+    }
+}
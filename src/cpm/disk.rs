@@ -0,0 +1,409 @@
+// Storage behind the CP/M drives. The BDOS works on files & 128 byte records, so a drive
+// can be a host directory just as well as a real CP/M file system. Only disk images have
+// sectors for the BIOS READ / WRITE calls.
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const RECORD: usize = 128;
+
+pub trait Drive {
+    // Files as upper case "NAME.EXT" (or "NAME" without type)
+    fn files(&self) -> Vec<String>;
+    // File size in records
+    fn records(&self, name: &str) -> Option<usize>;
+    // None past the end of the file, a partial last record is padded with ^Z
+    fn read(&mut self, name: &str, record: usize) -> Option<[u8; RECORD]>;
+    fn write(&mut self, name: &str, record: usize, data: &[u8; RECORD]) -> bool;
+    // Creates an empty file, replacing an existing one
+    fn create(&mut self, name: &str) -> bool;
+    fn delete(&mut self, name: &str) -> bool;
+    fn rename(&mut self, from: &str, to: &str) -> bool;
+
+    // Physical sector access (sectors start at 1)
+    fn read_sector(&mut self, _track: u16, _sector: u16) -> Option<[u8; RECORD]> {
+        None
+    }
+    fn write_sector(&mut self, _track: u16, _sector: u16, _data: &[u8; RECORD]) -> bool {
+        false
+    }
+    // Allocation blocks in use, for the BDOS allocation vector
+    fn used_blocks(&self) -> Vec<bool> {
+        Vec::new()
+    }
+}
+
+// Splits "NAME.EXT" into a blank padded 8 + 3 directory name. None if it isn't a valid
+// CP/M file name.
+pub fn dir_name(name: &str) -> Option<[u8; 11]> {
+    let (base, ext) = match name.find('.') {
+        Some(dot) => (&name[..dot], &name[dot + 1..]),
+        None => (name, ""),
+    };
+    let valid = |s: &str, max: usize| {
+        s.len() <= max
+            && s.bytes()
+                .all(|b| b.is_ascii_graphic() && !b"<>.,;:=?*[]".contains(&b))
+    };
+    if base.is_empty() || !valid(base, 8) || !valid(ext, 3) {
+        return None;
+    }
+
+    let mut bytes = [b' '; 11];
+    bytes[..base.len()].copy_from_slice(base.to_ascii_uppercase().as_bytes());
+    bytes[8..8 + ext.len()].copy_from_slice(ext.to_ascii_uppercase().as_bytes());
+    Some(bytes)
+}
+
+// Inverse of `dir_name`, attribute bits (bit 7) are ignored
+pub fn file_name(bytes: &[u8]) -> String {
+    let part = |b: &[u8]| -> String {
+        let s: String = b.iter().map(|&c| (c & 0x7F) as char).collect();
+        s.trim_end().to_string()
+    };
+    let (base, ext) = (part(&bytes[..8]), part(&bytes[8..11]));
+    match ext.is_empty() {
+        true => base,
+        false => format!("{}.{}", base, ext),
+    }
+}
+
+// A host directory, file names are matched case insensitively
+pub struct HostDir {
+    path: PathBuf,
+}
+
+impl HostDir {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        HostDir { path: path.into() }
+    }
+
+    fn host_path(&self, name: &str) -> Option<PathBuf> {
+        fs::read_dir(&self.path)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.is_file()
+                    && path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.eq_ignore_ascii_case(name))
+            })
+    }
+}
+
+impl Drive for HostDir {
+    fn files(&self) -> Vec<String> {
+        let mut files: Vec<String> = match fs::read_dir(&self.path) {
+            Ok(entries) => entries
+                .flatten()
+                .filter(|entry| entry.path().is_file())
+                .filter_map(|entry| entry.file_name().to_str().map(str::to_uppercase))
+                .filter(|name| dir_name(name).is_some())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        files.sort();
+        files
+    }
+
+    fn records(&self, name: &str) -> Option<usize> {
+        let len = fs::metadata(self.host_path(name)?).ok()?.len() as usize;
+        Some(len.div_ceil(RECORD))
+    }
+
+    fn read(&mut self, name: &str, record: usize) -> Option<[u8; RECORD]> {
+        let mut file = File::open(self.host_path(name)?).ok()?;
+        file.seek(SeekFrom::Start((record * RECORD) as u64)).ok()?;
+        let mut data = [0x1A; RECORD];
+        let len = file.read(&mut data).ok()?;
+        match len {
+            0 => None,
+            _ => Some(data),
+        }
+    }
+
+    fn write(&mut self, name: &str, record: usize, data: &[u8; RECORD]) -> bool {
+        let write = |path: PathBuf| -> io::Result<()> {
+            let mut file = OpenOptions::new().write(true).open(path)?;
+            file.seek(SeekFrom::Start((record * RECORD) as u64))?;
+            file.write_all(data)
+        };
+        self.host_path(name).is_some_and(|path| write(path).is_ok())
+    }
+
+    fn create(&mut self, name: &str) -> bool {
+        let path = self
+            .host_path(name)
+            .unwrap_or_else(|| self.path.join(name.to_uppercase()));
+        File::create(path).is_ok()
+    }
+
+    fn delete(&mut self, name: &str) -> bool {
+        self.host_path(name)
+            .is_some_and(|path| fs::remove_file(path).is_ok())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> bool {
+        match self.host_path(from) {
+            Some(path) => fs::rename(path, self.path.join(to.to_uppercase())).is_ok(),
+            None => false,
+        }
+    }
+}
+
+// 8" single sided single density IBM 3740 format, the CP/M 2.2 distribution disk:
+// 77 tracks of 26 sectors, 2 system tracks, 1K blocks & 64 directory entries
+pub const TRACKS: usize = 77;
+pub const SECTORS: usize = 26;
+pub const SYSTEM_TRACKS: usize = 2;
+pub const BLOCKS: usize = 243;
+pub const DIR_ENTRIES: usize = 64;
+const DIR_BLOCKS: usize = 2;
+const RECORDS_PER_BLOCK: usize = 8;
+const RECORDS_PER_EXTENT: usize = 128;
+pub const SKEW: [u8; SECTORS] = [
+    1, 7, 13, 19, 25, 5, 11, 17, 23, 3, 9, 15, 21, 2, 8, 14, 20, 26, 6, 12, 18, 24, 4, 10, 16, 22,
+];
+const EMPTY: u8 = 0xE5;
+
+// Raw image in physical sector order (track 0 sector 1, sector 2 ..), 256256 bytes. Images
+// opened from a file are written through on every change.
+pub struct DiskImage {
+    data: Vec<u8>,
+    file: Option<File>,
+}
+
+impl DiskImage {
+    // Freshly formatted, every byte 0xE5
+    pub fn blank() -> Self {
+        DiskImage {
+            data: vec![EMPTY; TRACKS * SECTORS * RECORD],
+            file: None,
+        }
+    }
+
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        if data.len() != TRACKS * SECTORS * RECORD {
+            let message = format!("{:?} is not an 8\" SSSD image", path);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        Ok(DiskImage {
+            data,
+            file: Some(file),
+        })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn sector_offset(track: usize, sector: usize) -> usize {
+        (track * SECTORS + sector - 1) * RECORD
+    }
+
+    // Records of the data area are numbered from the first directory sector
+    fn record_offset(logical: usize) -> usize {
+        let track = SYSTEM_TRACKS + logical / SECTORS;
+        Self::sector_offset(track, SKEW[logical % SECTORS] as usize)
+    }
+
+    fn store(&mut self, offset: usize, bytes: &[u8]) {
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        if let Some(file) = &mut self.file {
+            let _ = file
+                .seek(SeekFrom::Start(offset as u64))
+                .and_then(|_| file.write_all(bytes));
+        }
+    }
+
+    fn entry(&self, i: usize) -> [u8; 32] {
+        let offset = Self::record_offset(i / 4) + (i % 4) * 32;
+        let mut entry = [0; 32];
+        entry.copy_from_slice(&self.data[offset..offset + 32]);
+        entry
+    }
+
+    fn set_entry(&mut self, i: usize, entry: &[u8; 32]) {
+        self.store(Self::record_offset(i / 4) + (i % 4) * 32, entry);
+    }
+
+    // Directory entries (user 0) of a file: (index, entry)
+    fn extents(&self, name: &str) -> Vec<(usize, [u8; 32])> {
+        let name = match dir_name(name) {
+            Some(name) => name,
+            None => return Vec::new(),
+        };
+        (0..DIR_ENTRIES)
+            .map(|i| (i, self.entry(i)))
+            .filter(|(_, e)| {
+                e[0] == 0 && e[1..12].iter().map(|b| b & 0x7F).eq(name.iter().copied())
+            })
+            .collect()
+    }
+
+    fn extent_number(entry: &[u8; 32]) -> usize {
+        (entry[14] as usize & 0x3F) * 32 + (entry[12] as usize & 0x1F)
+    }
+}
+
+impl Drive for DiskImage {
+    fn files(&self) -> Vec<String> {
+        let mut files: Vec<String> = (0..DIR_ENTRIES)
+            .map(|i| self.entry(i))
+            .filter(|e| e[0] == 0 && Self::extent_number(e) == 0)
+            .map(|e| file_name(&e[1..12]))
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn records(&self, name: &str) -> Option<usize> {
+        let extents = self.extents(name);
+        extents
+            .iter()
+            .map(|(_, e)| Self::extent_number(e) * RECORDS_PER_EXTENT + e[15] as usize)
+            .max()
+    }
+
+    fn read(&mut self, name: &str, record: usize) -> Option<[u8; RECORD]> {
+        let extent = record / RECORDS_PER_EXTENT;
+        let index = record % RECORDS_PER_EXTENT;
+        let (_, entry) = self
+            .extents(name)
+            .into_iter()
+            .find(|(_, e)| Self::extent_number(e) == extent)?;
+        if index >= entry[15] as usize {
+            return None;
+        }
+
+        let mut data = [0; RECORD];
+        let block = entry[16 + index / RECORDS_PER_BLOCK] as usize;
+        if block != 0 {
+            let offset = Self::record_offset(block * RECORDS_PER_BLOCK + index % RECORDS_PER_BLOCK);
+            data.copy_from_slice(&self.data[offset..offset + RECORD]);
+        }
+        Some(data)
+    }
+
+    fn write(&mut self, name: &str, record: usize, data: &[u8; RECORD]) -> bool {
+        let extent = record / RECORDS_PER_EXTENT;
+        let index = record % RECORDS_PER_EXTENT;
+        let extents = self.extents(name);
+        if extents.is_empty() {
+            return false;
+        }
+
+        let found = extents
+            .iter()
+            .find(|(_, e)| Self::extent_number(e) == extent)
+            .copied();
+        let (slot, mut entry) = match found {
+            Some(found) => found,
+            None => {
+                let free = (0..DIR_ENTRIES).find(|&i| self.entry(i)[0] == EMPTY);
+                let mut entry = [0; 32];
+                entry[1..12].copy_from_slice(&extents[0].1[1..12]);
+                entry[12] = (extent % 32) as u8;
+                entry[14] = (extent / 32) as u8;
+                match free {
+                    Some(free) => (free, entry),
+                    None => return false,
+                }
+            }
+        };
+
+        let pointer = 16 + index / RECORDS_PER_BLOCK;
+        if entry[pointer] == 0 {
+            match self.used_blocks().iter().position(|used| !used) {
+                Some(block) => entry[pointer] = block as u8,
+                None => return false,
+            }
+        }
+        let block = entry[pointer] as usize;
+        let offset = Self::record_offset(block * RECORDS_PER_BLOCK + index % RECORDS_PER_BLOCK);
+        self.store(offset, data);
+        entry[15] = entry[15].max(index as u8 + 1);
+        self.set_entry(slot, &entry);
+        true
+    }
+
+    fn create(&mut self, name: &str) -> bool {
+        self.delete(name);
+        let name = match dir_name(name) {
+            Some(name) => name,
+            None => return false,
+        };
+        match (0..DIR_ENTRIES).find(|&i| self.entry(i)[0] == EMPTY) {
+            Some(free) => {
+                let mut entry = [0; 32];
+                entry[1..12].copy_from_slice(&name);
+                self.set_entry(free, &entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn delete(&mut self, name: &str) -> bool {
+        let extents = self.extents(name);
+        for (i, mut entry) in extents.iter().copied() {
+            entry[0] = EMPTY;
+            self.set_entry(i, &entry);
+        }
+        !extents.is_empty()
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> bool {
+        let to = match dir_name(to) {
+            Some(to) => to,
+            None => return false,
+        };
+        let extents = self.extents(from);
+        for (i, mut entry) in extents.iter().copied() {
+            entry[1..12].copy_from_slice(&to);
+            self.set_entry(i, &entry);
+        }
+        !extents.is_empty()
+    }
+
+    fn read_sector(&mut self, track: u16, sector: u16) -> Option<[u8; RECORD]> {
+        let (track, sector) = (track as usize, sector as usize);
+        if track >= TRACKS || sector == 0 || sector > SECTORS {
+            return None;
+        }
+        let offset = Self::sector_offset(track, sector);
+        let mut data = [0; RECORD];
+        data.copy_from_slice(&self.data[offset..offset + RECORD]);
+        Some(data)
+    }
+
+    fn write_sector(&mut self, track: u16, sector: u16, data: &[u8; RECORD]) -> bool {
+        let (track, sector) = (track as usize, sector as usize);
+        if track >= TRACKS || sector == 0 || sector > SECTORS {
+            return false;
+        }
+        self.store(Self::sector_offset(track, sector), data);
+        true
+    }
+
+    fn used_blocks(&self) -> Vec<bool> {
+        let mut used = vec![false; BLOCKS];
+        used[..DIR_BLOCKS].iter_mut().for_each(|b| *b = true);
+        for e in (0..DIR_ENTRIES)
+            .map(|i| self.entry(i))
+            .filter(|e| e[0] <= 15)
+        {
+            for &block in e[16..].iter().filter(|&&b| b != 0) {
+                if let Some(b) = used.get_mut(block as usize) {
+                    *b = true;
+                }
+            }
+        }
+        used
+    }
+}
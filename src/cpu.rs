@@ -1,20 +1,56 @@
+use crate::bus::Bus;
+use crate::debugger::{Access, Watchpoints};
+use crate::error::CpuError;
 use crate::instruction_info::{Instruction, Register, Register::*};
-use crate::memory::{Memory, MemoryRW};
+use crate::memory::MemoryRW;
+use crate::trace::{Record, TraceWriter};
+use opcodes::{Handler, OpcodeInfo, ED_NOP, I8080_CYCLES};
+use std::cell::Cell;
+use std::io::Write;
 
-pub struct Cpu {
+pub mod opcodes;
+
+// The CPU owns the bus it is attached to, every memory access and I/O request goes
+// through the `Bus` implementation supplied by the machine (Pacman board, CP/M harness etc).
+pub struct Cpu<B: Bus> {
     pub current_instruction: String,
     pub opcode: u16,
-    pub breakpoint: bool,
+    pub breakpoint: bool, // Execution is held by the debugger
     pub debug: bool,
+    pub halted: bool, // Set by HALT, cleared once an interrupt is accepted
     pub reg: Registers,
     pub flags: Flags,
     pub cycles: usize, // CPU T states
     pub io: Io,
     pub int: Interrupt,
     pub instruction: Instruction,
-    pub int_pending: bool,
-    pub cpm_compat: bool,
-    pub memory: Memory,
+    pub bus: B,
+    pub model: CpuModel,
+    pub trace: Option<TraceWriter<Box<dyn Write>>>, // Records every executed instruction
+    pub watch: Watchpoints,
+    branched: bool, // Set by `jump` & `repeat`, PC is not advanced past the current opcode
+    // Set by the register & operand helpers when an opcode table entry hands them an operand
+    // they don't handle, `step` reports it once the handler has finished (see CpuError)
+    fault: Cell<Option<CpuError>>,
+}
+
+// Instruction set & flag behaviour of the core
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CpuModel {
+    #[default]
+    Z80,
+    // Intel 8080: the Z80 prefixes & relative jumps execute as their 8080 aliases, P is
+    // always parity, AC follows the 8080 rules & bits 1, 3 & 5 of F are fixed (1, 0, 0)
+    I8080,
+}
+
+// What a single `step` did, for the frontends, debugger & trace tooling
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StepInfo {
+    pub pc: u16,       // Address of the executed instruction
+    pub opcode: u16,   // Last opcode byte fetched (after any prefixes)
+    pub cycles: usize, // T states spent
+    pub halted: bool,  // CPU is (still) halted after the step
 }
 
 #[derive(Default)]
@@ -47,14 +83,16 @@ pub struct Registers {
     pub sp: u16,
     pub ix: u16,
     pub iy: u16,
+
+    // Internal WZ register (MEMPTR), not visible to software except through X & Y after BIT n, (HL)
+    pub memptr: u16,
 }
 
 #[derive(Default)]
 pub struct Io {
-    pub port: u8,
+    // Last port address & data seen on the bus by an IN / OUT instruction
+    pub port: u16,
     pub value: u8,
-    input: bool,
-    output: bool,
 }
 
 #[derive(Default, Debug)]
@@ -85,15 +123,14 @@ pub struct Flags {
 // http://z80.info/z80info.htm (see f)
 #[derive(Default, Debug)]
 pub struct Interrupt {
-    pub irq: bool,
-    pub vector: u8,
-    pub nmi_pending: bool,
-    pub nmi: bool,
-    pub int: bool,
+    pub irq: bool,         // INT line, raised by the machine & cleared once accepted
+    pub nmi_pending: bool, // NMI edge
     pub iff1: bool,
     pub iff2: bool,
     pub mode: u8,
-    pub data: u8,
+    // Set by EI (& ignored DD / FD prefixes), interrupts are not accepted until the next
+    // instruction has executed
+    pub ei_delay: bool,
 }
 
 impl Flags {
@@ -118,14 +155,14 @@ impl Flags {
         }
     }
     pub(crate) fn get(&self) -> u8 {
-        return if self.sf { 0x80 } else { 0x0 }
+        (if self.sf { 0x80 } else { 0x0 }
             | if self.zf { 0x40 } else { 0x0 }
             | if self.yf { 0x20 } else { 0x0 }
             | if self.hf { 0x10 } else { 0x0 }
             | if self.xf { 0x08 } else { 0x0 }
             | if self.pf { 0x04 } else { 0x0 }
             | if self.nf { 0x02 } else { 0x0 }
-            | if self.cf { 0x01 } else { 0x0 };
+            | if self.cf { 0x01 } else { 0x0 })
     }
     pub fn set(&mut self, value: u8) {
         self.sf = value & 0x80 != 0;
@@ -138,14 +175,14 @@ impl Flags {
         self.cf = value & 0x01 != 0;
     }
     pub(crate) fn get_shadow(&self) -> u8 {
-        return if self.sf_ { 0x80 } else { 0x0 }
+        (if self.sf_ { 0x80 } else { 0x0 }
             | if self.zf_ { 0x40 } else { 0x0 }
             | if self.yf_ { 0x20 } else { 0x0 }
             | if self.hf_ { 0x10 } else { 0x0 }
             | if self.xf_ { 0x08 } else { 0x0 }
             | if self.pf_ { 0x04 } else { 0x0 }
             | if self.nf_ { 0x02 } else { 0x0 }
-            | if self.cf_ { 0x01 } else { 0x0 };
+            | if self.cf_ { 0x01 } else { 0x0 })
     }
 
     pub fn set_shadow(&mut self, value: u8) {
@@ -166,24 +203,16 @@ impl Flags {
     }
 }
 
-impl MemoryRW for Cpu {
+impl<B: Bus> MemoryRW for Cpu<B> {
     fn read8(&self, addr: u16) -> u8 {
-        if self.cpm_compat {
-            return self.memory[addr];
-        } else {
-            if addr < 0x4000 {
-                self.memory.rom[addr as usize]
-            } else if addr == 0x5000 {
-                return self.int.int as u8;
-            } else if addr < 0x5000 {
-                self.memory.ram[addr as usize - 0x4000]
-            } else {
-                self.memory.rom[addr as usize]
-            }
+        let byte = self.bus.read8(addr);
+        if !self.watch.is_empty() {
+            self.watch.check(Access::Read, addr, byte);
         }
+        byte
     }
     fn read16(&self, addr: u16) -> u16 {
-        u16::from_le_bytes([self.read8(addr), self.read8(addr + 1)])
+        u16::from_le_bytes([self.read8(addr), self.read8(addr.wrapping_add(1))])
     }
 
     fn write16(&mut self, addr: u16, word: u16) {
@@ -191,26 +220,15 @@ impl MemoryRW for Cpu {
         self.write8(addr.wrapping_add(1), (word >> 8) as u8);
     }
     fn write8(&mut self, addr: u16, byte: u8) {
-        if self.cpm_compat {
-            return self.memory[addr] = byte;
-        } else {
-            if addr < 0x4000 {
-                eprintln!("Attempting write to ROM: {:04x}", addr);
-                eprintln!("Called by:{:#?}", self.instruction);
-            } else if addr < 0x5000 {
-                self.memory.ram[addr as usize - 0x4000] = byte;
-            } else if addr == 0x5000 {
-                self.int_pending = true;
-                self.int.irq = true;
-            } else {
-                self.memory.ram[addr as usize] = byte;
-            }
+        if !self.watch.is_empty() {
+            self.watch.check(Access::Write, addr, byte);
         }
+        self.bus.write8(addr, byte)
     }
 }
 
-impl Cpu {
-    pub fn new() -> Cpu {
+impl<B: Bus> Cpu<B> {
+    pub fn new(bus: B) -> Cpu<B> {
         Cpu {
             opcode: 0,
             reg: Registers::default(),
@@ -219,15 +237,26 @@ impl Cpu {
             current_instruction: String::new(),
             debug: false,
             breakpoint: false,
+            halted: false,
             io: Io::default(),
             int: Interrupt::default(),
-            int_pending: false,
             instruction: Instruction::new(),
-            memory: Memory::new(),
-            cpm_compat: false,
+            bus,
+            model: CpuModel::Z80,
+            trace: None,
+            watch: Watchpoints::default(),
+            branched: false,
+            fault: Cell::new(None),
         }
     }
 
+    // Records a broken operand for `step`, the caller carries on with a placeholder value
+    fn fault(&self, reg: Register) {
+        self.fault.set(Some(CpuError::Unimplemented {
+            mnemonic: format!("{:?} operand of opcode {:02X}", reg, self.opcode),
+        }));
+    }
+
     fn read_reg(&self, reg: Register) -> u8 {
         match reg {
             A => self.reg.a,
@@ -240,23 +269,17 @@ impl Cpu {
             M => self.reg.m,
             I => self.reg.i,
             R => self.reg.r,
-            IX => self.reg.ix as u8,
             IXH => (self.reg.ix >> 8) as u8,
-            IXL => ((self.reg.ix as u8) & 0xff),
-            IY => self.reg.iy as u8,
+            IXL => self.reg.ix as u8,
             IYH => (self.reg.iy >> 8) as u8,
-            IYL => ((self.reg.iy as u8) & 0xff),
+            IYL => self.reg.iy as u8,
             // TODO Potential value loss here
             BC => self.get_pair(BC) as u8,
             DE => self.get_pair(DE) as u8,
             HL => self.get_pair(HL) as u8,
             _ => {
-                println!(
-                    "Called by:{}, Opcode:{:02X}",
-                    self.current_instruction, self.opcode
-                );
-                eprintln!("Instruction:{:?}", Instruction::decode(self.opcode));
-                panic!("Register not supported:{:#?}", reg)
+                self.fault(reg);
+                0
             }
         }
     }
@@ -272,7 +295,11 @@ impl Cpu {
             M => self.reg.m = value,
             I => self.reg.i = value,
             R => self.reg.r = value,
-            _ => panic!(format!("Writing to register pairs is not supported by write_reg, called by: {}, opcode:{:02x}", self.current_instruction, self.opcode)),
+            IXH => self.reg.ix = (self.reg.ix & 0x00FF) | (value as u16) << 8,
+            IXL => self.reg.ix = (self.reg.ix & 0xFF00) | value as u16,
+            IYH => self.reg.iy = (self.reg.iy & 0x00FF) | (value as u16) << 8,
+            IYL => self.reg.iy = (self.reg.iy & 0xFF00) | value as u16,
+            _ => self.fault(reg),
         }
     }
 
@@ -296,26 +323,28 @@ impl Cpu {
                 self.reg.l = (value & 0xFF) as u8;
             }
             IX => self.reg.ix = value,
-            IXH => self.reg.ix = (value >> 8) as u16,
-            IXL => self.reg.ix = (value & 0xFF) as u16,
             IY => self.reg.iy = value,
-            IYH => self.reg.iy = (value >> 8) as u16,
-            IYL => self.reg.iy = (value & 0xFF) as u16,
             SP => self.reg.sp = value,
-            _ => panic!("Attempting to write to a non register pair: {:#?}", reg),
+            _ => self.fault(reg),
         }
     }
     pub fn get_pair(&self, reg: Register) -> u16 {
-        return match reg {
+        match reg {
             BC => (self.reg.b as u16) << 8 | (self.reg.c as u16),
             DE => (self.reg.d as u16) << 8 | (self.reg.e as u16),
             HL => (self.reg.h as u16) << 8 | (self.reg.l as u16),
             IX => self.reg.ix,
             IY => self.reg.iy,
             SP => self.reg.sp,
+            AF if self.model == CpuModel::I8080 => {
+                (self.reg.a as u16) << 8 | (self.flags.get() & 0xD5 | 0x02) as u16
+            }
             AF => (self.reg.a as u16) << 8 | (self.flags.get() as u16),
-            _ => unimplemented!("{:?}", reg),
-        };
+            _ => {
+                self.fault(reg);
+                0
+            }
+        }
     }
     fn adv_pc(&mut self, t: u16) {
         self.reg.prev_pc = self.reg.pc;
@@ -326,476 +355,467 @@ impl Cpu {
         self.cycles = self.cycles.wrapping_add(t);
     }
 
-    // TODO refactor ADD / ADC instructions
-    // pass value in from the caller and have one method for most of these
-    fn adc(&mut self, reg: Register) {
-        let value = if reg != Register::HL {
-            self.read_reg(reg)
-        } else if reg == Register::HL {
-            self.adv_cycles(3);
-            self.memory[self.get_pair(Register::HL)]
-        } else {
-            unimplemented!();
-        };
-        let result: u16 = (self.reg.a as u16)
-            .wrapping_add(value as u16)
-            .wrapping_add(self.flags.cf as u16);
+    // Every opcode fetch (M1 cycle) increments the lower 7 bits of the refresh register
+    fn inc_r(&mut self) {
+        self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(1) & 0x7f);
+    }
 
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_add(self.reg.a, value);
-        self.flags.pf = self.overflow(self.reg.a, result as u8);
-        self.flags.nf = false;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.cf = result & 0x0100 != 0;
+    // Address of an 8 bit memory operand. HL is (HL), IX & IY are (IX+d) & (IY+d) where the
+    // signed displacement byte follows the opcode.
+    fn operand_addr(&mut self, reg: Register) -> u16 {
+        match reg {
+            HL => self.get_pair(HL),
+            IX | IY => {
+                let d = self.read8(self.reg.pc.wrapping_add(1)) as i8;
+                self.reg.memptr = self.get_pair(reg).wrapping_add(d as u16);
+                self.reg.memptr
+            }
+            _ => {
+                self.fault(reg);
+                0
+            }
+        }
+    }
 
-        self.reg.a = result as u8;
+    // Reads a register or memory operand (see operand_addr)
+    fn read_operand(&mut self, reg: Register) -> u8 {
+        match reg {
+            HL | IX | IY => {
+                let addr = self.operand_addr(reg);
+                self.read8(addr)
+            }
+            _ => self.read_reg(reg),
+        }
+    }
 
-        self.adv_cycles(4);
-        self.adv_pc(1);
+    fn adc(&mut self, reg: Register) {
+        let value = self.read_operand(reg);
+        self.add8(value, self.flags.cf);
     }
     fn adc_hl(&mut self, reg: Register) {
-        let result = match reg {
-            BC => (self.get_pair(HL) as u32)
-                .wrapping_add(self.get_pair(BC) as u32)
-                .wrapping_add(self.flags.cf as u32),
-            DE => (self.get_pair(HL) as u32)
-                .wrapping_add(self.get_pair(DE) as u32)
-                .wrapping_add(self.flags.cf as u32),
-            HL => (self.get_pair(HL) as u32)
-                .wrapping_add(self.get_pair(HL) as u32)
-                .wrapping_add(self.flags.cf as u32),
-            SP => (self.get_pair(HL) as u32).wrapping_add(self.reg.sp as u32),
-            _ => panic!("Register: {:?} Not allowed for ADC HL", reg),
+        let (hl, value) = (self.get_pair(HL), self.get_pair(reg));
+        let result = hl as u32 + value as u32 + self.flags.cf as u32;
+        self.hl_flags(hl, value, result, false);
+    }
+
+    // SBC HL, rr
+    fn sbc_hl(&mut self, reg: Register) {
+        let (hl, value) = (self.get_pair(HL), self.get_pair(reg));
+        let result = (hl as u32)
+            .wrapping_sub(value as u32)
+            .wrapping_sub(self.flags.cf as u32);
+        self.hl_flags(hl, value, result, true);
+    }
+
+    // Flags for the 16 bit ADC & SBC, H is the carry from bit 11 & X, Y come from the high byte
+    fn hl_flags(&mut self, hl: u16, value: u16, result: u32, subtract: bool) {
+        let r = result as u16;
+        self.write_pair_direct(HL, r);
+        self.reg.memptr = hl.wrapping_add(1);
+
+        self.flags.sf = r & 0x8000 != 0;
+        self.flags.zf = r == 0;
+        self.flags.hf = (hl ^ value ^ r) & 0x1000 != 0;
+        self.flags.pf = if subtract {
+            (hl ^ value) & (hl ^ r) & 0x8000 != 0
+        } else {
+            (hl ^ r) & (value ^ r) & 0x8000 != 0
         };
+        self.flags.nf = subtract;
+        self.flags.yf = (r >> 8) & 0x20 != 0;
+        self.flags.xf = (r >> 8) & 0x08 != 0;
+        self.flags.cf = result > 0xFFFF;
+    }
 
-        self.reg.h = (result >> 8) as u8;
-        self.reg.l = result as u8;
+    // NEG, A = 0 - A
+    fn neg(&mut self) {
+        let value = self.reg.a;
+        self.reg.a = 0;
+        self.reg.a = self.sub8(value, false);
+    }
 
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.pf = self.overflow(result.wrapping_sub(1) as u8, result as u8);
-        self.flags.cf = ((result >> 8) & 0x0100) != 0;
-        self.flags.hf = self.hf_add(self.reg.a, (result >> 8) as u8);
+    // RLD & RRD rotate the low nibble of A & the byte at (HL) as one 12 bit value
+    fn rld(&mut self, right: bool) {
+        let hl = self.get_pair(HL);
+        let (a, m) = (self.reg.a, self.read8(hl));
+        let (a, m) = if right {
+            ((a & 0xF0) | (m & 0x0F), (a << 4) | (m >> 4))
+        } else {
+            ((a & 0xF0) | (m >> 4), (m << 4) | (a & 0x0F))
+        };
+        self.write8(hl, m);
+        self.reg.a = a;
+        self.reg.memptr = hl.wrapping_add(1);
+
+        self.flags.sf = a & 0x80 != 0;
+        self.flags.zf = a == 0;
+        self.flags.yf = a & 0x20 != 0;
+        self.flags.xf = a & 0x08 != 0;
+        self.flags.pf = self.parity(a);
+        self.flags.hf = false;
         self.flags.nf = false;
-        self.flags.yf = (result >> 8) & 0x20 != 0;
-        self.flags.xf = (result >> 8) & 0x08 != 0;
-
-        self.adv_cycles(15);
-        self.adv_pc(2);
     }
 
     // Add Immediate to Accumulator with Carry
     fn adc_im(&mut self) {
-        let value = self.read8(self.reg.pc + 1) as u16;
-
-        // Add immediate with accumulator + carry flag value
-        let reg_a = self.reg.a;
-        let carry = self.flags.cf as u8;
-        let result = (value)
-            .wrapping_add(reg_a as u16)
-            .wrapping_add(carry as u16);
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_add(reg_a, value as u8);
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.nf = false;
-        self.flags.pf = self.overflow(self.reg.a, result as u8);
-        self.flags.cf = result & 0x0100 != 0;
-
-        self.reg.a = result as u8;
+        let value = self.read8(self.reg.pc.wrapping_add(1));
+        self.add8(value, self.flags.cf);
+    }
 
-        self.adv_cycles(7);
-        self.adv_pc(2);
+    fn add(&mut self, reg: Register) {
+        let value = self.read_operand(reg);
+        self.add8(value, false);
     }
 
-    fn add_ex(&mut self, dst: Register, src: Register) {
-        let result = (self.get_pair(dst)).wrapping_add(self.get_pair(src) as u16);
-        self.write_pair_direct(dst, result);
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_add(self.reg.a, self.get_pair(src) as u8);
-        self.flags.pf = self.overflow(self.reg.a, result as u8);
+    // 8 bit addition shared by ADD & ADC
+    fn add8(&mut self, value: u8, carry: bool) {
+        let a = self.reg.a;
+        let result = a as u16 + value as u16 + carry as u16;
+        let r = result as u8;
+
+        self.flags.sf = r & 0x80 != 0;
+        self.flags.zf = r == 0;
+        self.flags.hf = (a ^ value ^ r) & 0x10 != 0;
+        self.flags.pf = if self.i8080() {
+            self.parity(r)
+        } else {
+            (a ^ r) & (value ^ r) & 0x80 != 0
+        };
         self.flags.nf = false;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.cf = result & 0x0100 != 0;
-        self.adv_cycles(15);
-        self.adv_pc(2);
+        self.flags.yf = r & 0x20 != 0;
+        self.flags.xf = r & 0x08 != 0;
+        self.flags.cf = result > 0xFF;
+        self.reg.a = r;
     }
-    fn add(&mut self, reg: Register) {
-        let value = if reg != HL {
-            self.read_reg(reg)
+
+    // 8 bit subtraction shared by SUB, SBC, CP & NEG, returns the result without storing it
+    fn sub8(&mut self, value: u8, carry: bool) -> u8 {
+        let a = self.reg.a;
+        let result = (a as u16)
+            .wrapping_sub(value as u16)
+            .wrapping_sub(carry as u16);
+        let r = result as u8;
+
+        self.flags.sf = r & 0x80 != 0;
+        self.flags.zf = r == 0;
+        // The 8080 adds the complement, AC is set when there is no borrow from bit 4
+        let borrow = (a ^ value ^ r) & 0x10 != 0;
+        self.flags.hf = borrow != self.i8080();
+        self.flags.pf = if self.i8080() {
+            self.parity(r)
         } else {
-            self.adv_cycles(3);
-            self.memory[self.get_pair(HL)]
+            (a ^ value) & (a ^ r) & 0x80 != 0
         };
+        self.flags.nf = true;
+        self.flags.yf = r & 0x20 != 0;
+        self.flags.xf = r & 0x08 != 0;
+        self.flags.cf = result > 0xFF;
+        r
+    }
 
-        let result = (self.reg.a as u16).wrapping_add(value as u16);
-
+    // Flags for AND, OR & XOR (H is only set by AND)
+    fn logic_flags(&mut self, result: u8, hf: bool) {
         self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_add(self.reg.a, value);
-        self.flags.pf = self.overflow(self.reg.a, result as u8);
-        self.flags.nf = false;
+        self.flags.zf = result == 0;
         self.flags.yf = result & 0x20 != 0;
         self.flags.xf = result & 0x08 != 0;
-        self.flags.cf = result & 0x0100 != 0;
-
-        self.reg.a = result as u8;
-
-        self.adv_cycles(4);
-        self.adv_pc(1);
+        self.flags.hf = hf;
+        self.flags.pf = self.parity(result);
+        self.flags.nf = false;
+        self.flags.cf = false;
     }
 
     // Add Immediate to Accumulator
     fn adi(&mut self) {
-        // Read next byte of immediate data (low).
-        let value = self.read8(self.reg.pc + 1) as u16;
-        let result = (value).wrapping_add(self.reg.a as u16);
-
-        // Set CPU flags with new accumulator values
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.pf = self.overflow(self.reg.a, result as u8);
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.nf = false;
-        self.flags.hf = self.hf_add(self.reg.a, value as u8);
-        self.flags.cf = result & 0x0100 != 0;
-
-        self.reg.a = result as u8;
-
-        self.adv_cycles(7);
-        self.adv_pc(2);
+        let value = self.read8(self.reg.pc.wrapping_add(1));
+        self.add8(value, false);
     }
 
     pub fn ana(&mut self, reg: Register) {
-        let value = if reg != Register::HL {
-            self.read_reg(reg)
-        } else {
-            self.adv_cycles(3);
-            self.memory[self.get_pair(Register::HL)]
-        };
         // And value with accumulator
+        let value = self.read_operand(reg);
         let result = self.reg.a & value;
+        self.logic_flags(result, self.and_half_carry(value));
+        self.reg.a = result;
+    }
 
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.nf = false;
-        self.flags.hf = true;
-        self.flags.pf = self.parity(result);
-        self.flags.cf = false;
-
-        self.reg.a = result as u8;
-
-        self.adv_cycles(4);
-        self.adv_pc(1);
+    // AND always sets H on the Z80, the 8080 sets AC to the OR of bit 3 of both operands
+    fn and_half_carry(&self, value: u8) -> bool {
+        !self.i8080() || (self.reg.a | value) & 0x08 != 0
     }
 
     fn ani(&mut self) {
         // The byte of immediate data is ANDed with the contents of the accumulator
-        let value = self.read8(self.reg.pc + 1);
-        let result = self.reg.a as u16 & value as u16;
+        let value = self.read8(self.reg.pc.wrapping_add(1));
+        let result = self.reg.a & value;
+        self.logic_flags(result, self.and_half_carry(value));
+        self.reg.a = result;
+    }
+    // 0xCB Extended Opcode Bit instructions
+    // BIT n, r: X & Y are copies of the tested register, Z & P are set if the bit is 0
+    fn bit(&mut self, bit: u8, reg: Register) {
+        let value = self.read_operand(reg);
+        self.bit_flags(bit, value);
+        // BIT n, (HL) copies X & Y from the high byte of MEMPTR
+        let xy = if reg == HL {
+            (self.reg.memptr >> 8) as u8
+        } else {
+            value
+        };
+        self.flags.yf = xy & 0x20 != 0;
+        self.flags.xf = xy & 0x08 != 0;
+    }
 
+    fn bit_flags(&mut self, bit: u8, value: u8) {
+        let result = value & (1 << bit);
         self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
+        self.flags.zf = result == 0;
+        self.flags.pf = result == 0;
         self.flags.nf = false;
         self.flags.hf = true;
-        self.flags.pf = self.parity(result as u8);
-        self.flags.cf = false;
-
-        self.reg.a = result as u8;
-
-        self.adv_cycles(7);
-        self.adv_pc(2);
     }
-    // 0xCB Extended Opcode Bit instructions
-    fn bit(&mut self, bit: u8, reg: Register) {
-        // Test bit n of register
+
+    // RES n, r & SET n, r
+    fn set_bit(&mut self, bit: u8, reg: Register, set: bool) {
+        let value = if reg == HL {
+            self.read8(self.get_pair(HL))
+        } else {
+            self.read_reg(reg)
+        };
+        let result = if set {
+            value | (1 << bit)
+        } else {
+            value & !(1 << bit)
+        };
         if reg == HL {
-            self.adv_cycles(4);
+            self.write8(self.get_pair(HL), result);
+        } else {
+            self.write_reg(reg, result);
         }
-        let result = self.read_reg(reg) & (1 << bit);
+    }
 
+    // RLC, RRC, RL, RR, SLA, SRA, SLL (undocumented) & SRL
+    // The operation is selected by bits 3-5 of the CB opcode
+    fn rot(&mut self, op: u8, value: u8) -> u8 {
+        let cf = self.flags.cf as u8;
+        let (result, carry) = match op {
+            0 => (value.rotate_left(1), value & 0x80 != 0),
+            1 => (value.rotate_right(1), value & 1 != 0),
+            2 => (value << 1 | cf, value & 0x80 != 0),
+            3 => (value >> 1 | cf << 7, value & 1 != 0),
+            4 => (value << 1, value & 0x80 != 0),
+            5 => (value >> 1 | (value & 0x80), value & 1 != 0),
+            6 => (value << 1 | 1, value & 0x80 != 0),
+            _ => (value >> 1, value & 1 != 0),
+        };
         self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
+        self.flags.zf = result == 0;
         self.flags.yf = result & 0x20 != 0;
         self.flags.xf = result & 0x08 != 0;
+        self.flags.pf = self.parity(result);
+        self.flags.hf = false;
         self.flags.nf = false;
-        self.flags.hf = true;
-        self.flags.pf = self.flags.zf; // TODO: Double check this
-        self.adv_pc(2);
-        self.adv_cycles(8);
+        self.flags.cf = carry;
+        result
+    }
+
+    fn rot_reg(&mut self, op: u8, reg: Register) {
+        if reg == HL {
+            let addr = self.get_pair(HL);
+            let result = self.rot(op, self.read8(addr));
+            self.write8(addr, result);
+        } else {
+            let result = self.rot(op, self.read_reg(reg));
+            self.write_reg(reg, result);
+        }
     }
 
     fn djnz(&mut self) {
         // The b register is decremented, and if not zero the signed value * is added to PC
         // The jump is measured from the start of the last instruction opcode
-        self.adv_cycles(1);
         self.reg.b = self.reg.b.wrapping_sub(1);
         self.jr_cond(self.reg.b != 0);
     }
-    // TODO Clean up JR
-    fn jr(&mut self, offset: i16) {
-        self.adv_pc(2);
-        self.reg.prev_pc = self.reg.pc;
-        self.reg.pc = (self.reg.pc as i16 + offset) as u16;
-        self.adv_cycles(12);
-    }
-    // "Generic" function for conditional JR operations
+    // "Generic" function for JR & the conditional JR operations
+    // The signed offset is relative to the address of the next instruction
     fn jr_cond(&mut self, cond: bool) {
-        // E.g if zero flag == 0 { JR + offset
-        let byte = self.read8(self.reg.pc + 1) as i8;
+        let offset = self.read8(self.reg.pc.wrapping_add(1)) as i8;
         if cond {
-            self.jr(byte as i16);
-        } else {
-            self.adv_cycles(7);
-            self.adv_pc(2);
+            self.jump(self.reg.pc.wrapping_add(2).wrapping_add(offset as u16));
+            self.reg.memptr = self.reg.pc;
         }
     }
-    fn jp(&mut self, addr: u16) {
-        self.reg.prev_pc = self.reg.pc;
-        self.adv_cycles(self.instruction.cycles as usize);
-        self.reg.pc = addr;
-    }
     fn jp_cond(&mut self, cond: bool) {
+        self.reg.memptr = self.read16(self.reg.pc.wrapping_add(1));
         if cond {
-            self.reg.prev_pc = self.reg.pc;
-            self.reg.pc = self.read16(self.reg.pc + 1);
-        } else {
-            self.adv_pc(3);
+            self.jump(self.reg.memptr);
         }
-        self.adv_cycles(10);
     }
 
-    // Jump to address in H:L
-    fn pchl(&mut self) {
-        self.adv_cycles(4);
-        self.reg.prev_pc = self.reg.pc;
-        self.reg.pc = self.get_pair(Register::HL) as u16;
+    // Jump to address in H:L (or IX / IY)
+    fn pchl(&mut self, reg: Register) {
+        self.jump(self.get_pair(reg));
     }
 
-    // 0xEDA0 Extended instruction
-    fn ldi(&mut self) {
-        // YF and XF are copies of bit 1 of n and bit 3 of n respectively.
-        let de = self.read8(self.get_pair(DE));
-        let hl = self.read8(self.get_pair(HL));
-        self.write8(de as u16, hl as u8);
-
-        let n = hl.wrapping_add(self.reg.a);
-
-        self.write_pair_direct(HL, self.get_pair(HL).wrapping_add(1));
-        self.write_pair_direct(DE, self.get_pair(DE).wrapping_add(1));
+    // LDI, LDD, LDIR & LDDR
+    // (DE) = (HL), then HL & DE are stepped & BC is decremented.
+    fn ld_block(&mut self, increment: bool, repeat: bool) {
+        let value = self.read8(self.get_pair(HL));
+        self.write8(self.get_pair(DE), value);
+        let step = if increment { 1 } else { 0xFFFF };
+        self.write_pair_direct(HL, self.get_pair(HL).wrapping_add(step));
+        self.write_pair_direct(DE, self.get_pair(DE).wrapping_add(step));
         self.write_pair_direct(BC, self.get_pair(BC).wrapping_sub(1));
 
+        // YF and XF are copies of bit 1 of n and bit 3 of n respectively, n = (HL) + A
+        let n = value.wrapping_add(self.reg.a);
         self.flags.pf = self.get_pair(BC) != 0;
-        self.flags.nf = false;
+        self.flags.hf = false;
         self.flags.nf = false;
         self.flags.yf = (n & 0x02) != 0;
         self.flags.xf = (n & 0x08) != 0;
-        self.adv_cycles(16);
-        self.adv_pc(2);
+        self.block_repeat(repeat && self.flags.pf);
     }
 
-    // 0xEDB0 Extended instruction
-    fn ldir(&mut self) {
-        // LDIR is basically LDI + BC if BC is not 0 decrease PC by 2.
-        self.ldi();
-        if self.get_pair(BC) != 0 {
-            self.reg.prev_pc = self.reg.pc;
-            self.reg.pc = self.reg.pc.wrapping_sub(2);
-            self.adv_cycles(5);
-        }
-        if self.get_pair(BC) <= 0 {
-            self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(0) as u8 & 0x7f);
+    fn block_repeat(&mut self, repeat: bool) {
+        if repeat {
+            self.repeat();
+            self.reg.memptr = self.reg.pc.wrapping_add(1);
         }
     }
 
     // Extended instructions: ex: LD (**), HL
     // 0xED63, 0xED53 etc..
     // Stores (REGPAIR) into the memory loc pointed to by **
-    // TODO & LOAD INDIRECT BUG?
     fn ld_nn(&mut self, reg: Register) {
-        let ptr = self.read16(self.reg.pc + 1);
+        let ptr = self.read16(self.reg.pc.wrapping_add(2));
         self.write16(ptr, self.get_pair(reg));
-        self.adv_cycles(20);
-        self.adv_pc(4);
+        self.reg.memptr = ptr.wrapping_add(1);
     }
 
     // Extended instructions: ex: LD HL, (**)
     // 0xED6B, 0xED5B etc..
     // Loads the value pointed to by ** into (REGPAIR)
     fn load_indirect(&mut self, reg: Register) {
-        let word = self.read16(self.reg.pc + 1);
+        let word = self.read16(self.reg.pc.wrapping_add(2));
         self.write_pair_direct(reg, self.read16(word));
-        self.adv_cycles(20);
-        self.adv_pc(4);
+        self.reg.memptr = word.wrapping_add(1);
     }
 
     // Load Register Pair Immediate
     // LXI H, 2000H (2000H is stored in HL & acts as as memory pointer)
     fn lxi(&mut self, reg: Register) {
-        self.write_pair_direct(reg, self.read16(self.reg.pc + 1));
-        self.adv_cycles(10);
-        self.adv_pc(3);
+        self.write_pair_direct(reg, self.read16(self.reg.pc.wrapping_add(1)));
     }
 
     // LD (**, A)
     // Store Accumulator direct
     fn sta(&mut self) {
-        let imm = self.read16(self.reg.pc + 1);
+        let imm = self.read16(self.reg.pc.wrapping_add(1));
         self.write8(imm, self.reg.a);
-        self.adv_cycles(13);
-        self.adv_pc(3);
+        self.reg.memptr = (self.reg.a as u16) << 8 | (imm.wrapping_add(1) & 0xFF);
     }
 
-    fn call(&mut self, opcode: u16) {
+    fn call(&mut self) {
         let ret: u16 = self.reg.pc.wrapping_add(3);
-        match opcode {
-            0xCC | 0xCD | 0xC4 | 0xD4 | 0xDC | 0xE4 | 0xEC | 0xF4 | 0xFC | 0x66 => {
-                // High order byte
-                self.memory[self.reg.sp.wrapping_sub(1)] = (ret >> 8) as u8;
-                // Low order byte
-                self.memory[self.reg.sp.wrapping_sub(2)] = ret as u8;
-                // Push return address to stack
-                self.reg.sp = self.reg.sp.wrapping_sub(2);
-            }
-            _ => panic!(format!("Unknown call opcode: {:04x}", opcode)),
-        };
-
-        self.reg.prev_pc = self.reg.pc;
-        self.reg.pc = self.read16(self.reg.pc + 1);
-        self.adv_cycles(17);
+        self.reg.memptr = self.read16(self.reg.pc.wrapping_add(1));
+        // Push return address to stack
+        self.push16(ret);
+        self.jump(self.reg.memptr);
     }
 
     // Conditional calls
-    fn call_cond(&mut self, addr: u16, cond: bool) {
+    fn call_cond(&mut self, cond: bool) {
         if cond {
-            self.call(addr);
+            self.call();
         } else {
-            self.adv_cycles(10);
-            self.adv_pc(3);
+            self.reg.memptr = self.read16(self.reg.pc.wrapping_add(1));
         }
     }
 
+    // CMA, CMC & STC don't touch any other flag on the 8080
     fn cpl(&mut self) {
         self.reg.a ^= 0xFF;
+        if self.i8080() {
+            return;
+        }
         self.flags.hf = true;
         self.flags.nf = true;
         self.flags.yf = self.reg.a & 0x20 != 0;
         self.flags.xf = self.reg.a & 0x08 != 0;
-        self.adv_cycles(4);
-        self.adv_pc(1);
     }
 
     fn ccf(&mut self) {
+        if self.i8080() {
+            self.flags.cf = !self.flags.cf;
+            return;
+        }
         self.flags.hf = self.flags.cf;
         self.flags.cf = !self.flags.cf;
         self.flags.yf = self.reg.a & 0x20 != 0;
         self.flags.xf = self.reg.a & 0x08 != 0;
         self.flags.nf = false;
-        self.adv_cycles(4);
-        self.adv_pc(1);
     }
     fn cmp(&mut self, reg: Register) {
-        let mut value = 0;
-        if reg != HL {
-            value = self.read_reg(reg);
-        } else if reg == HL {
-            self.adv_cycles(3);
-            value = self.memory[self.get_pair(HL)];
-        }
-        let result = (self.reg.a as u16).wrapping_sub(value as u16);
-        let overflow = (self.reg.a as i8).overflowing_sub(value as i8).1;
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_sub(self.reg.a, value as u8);
-        self.flags.nf = true;
+        let value = self.read_operand(reg);
+        self.sub8(value, false);
         // The XF & YF flags use the non compared value
         self.flags.yf = value & 0x20 != 0;
         self.flags.xf = value & 0x08 != 0;
-        self.flags.pf = overflow;
-
-        /*if overflow
-            != (self.carry(7, self.reg.a as u16, !value as u16)
-                != self.carry(8, self.reg.a as u16, !value as u16))
-        {
-            println!("Overflow differs");
-        }*/
-        self.flags.cf = result & 0x0100 != 0;
-
-        self.adv_cycles(4);
-        self.adv_pc(1);
     }
 
     // Compare Immediate with Accumulator
     fn cp(&mut self) {
-        let value = self.read8(self.reg.pc + 1);
-        let result = (self.reg.a as i16).wrapping_sub(value as i16);
-        let overflow = (self.reg.a as i8).overflowing_sub(value as i8).1;
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
+        let value = self.read8(self.reg.pc.wrapping_add(1));
+        self.sub8(value, false);
         self.flags.yf = value & 0x20 != 0;
-        self.flags.hf = self.hf_sub(self.reg.a, value as u8);
         self.flags.xf = value & 0x08 != 0;
-        self.flags.pf = overflow;
-        self.flags.nf = true;
-        self.flags.cf = result & 0x0100 != 0;
-        // self.flags.pf = self.carry_sub(7, a, value, false) != self.carry_sub(8, a, value, false);
-
-        self.adv_cycles(7);
-        self.adv_pc(2);
-    }
-    // Extended instruction
-    fn cpi(&mut self) {
-        // TODO
-        // Compares the value of the memory location pointed to by HL with A.
-        // HL is then incremented and BC is decremented.
-        // S,Z,H from (A - (HL) ) as in CP (HL)
-        // F3 is bit 3 of (A - (HL) - H), H as in F after instruction
-        // F5 is bit 1 of (A - (HL) - H), H as in F after instruction
-        let value = self.read8(self.get_pair(HL));
-        let result = (self.reg.a as u16).wrapping_sub(value as u16);
-        let overflow = (self.reg.a as i8).overflowing_sub(value as i8).1;
-        self.flags.nf = true;
+    }
+    // CPI, CPD, CPIR & CPDR
+    // Compares the value of the memory location pointed to by HL with A.
+    // HL is then stepped and BC is decremented, C is not affected.
+    fn cp_block(&mut self, increment: bool, repeat: bool) {
+        let hl = self.get_pair(HL);
+        let value = self.read8(hl);
+        let a = self.reg.a;
+        let result = a.wrapping_sub(value);
+        let step = if increment { 1 } else { 0xFFFF };
+        self.write_pair_direct(HL, hl.wrapping_add(step));
+        self.write_pair_direct(BC, self.get_pair(BC).wrapping_sub(1));
+        self.reg.memptr = self.reg.memptr.wrapping_add(step);
+
         self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_sub(self.reg.a, value);
-        // self.flags.pf = self.overflow(value, result as u8);
-        self.flags.pf = overflow;
-        self.flags.cf = result & 0x0100 != 0;
-        self.flags.yf = value & 0x20 != 0;
-        self.flags.xf = value & 0x08 != 0;
+        self.flags.zf = result == 0;
+        self.flags.hf = (a ^ value ^ result) & 0x10 != 0;
+        self.flags.pf = self.get_pair(BC) != 0;
+        self.flags.nf = true;
+        // F3 is bit 3 of (A - (HL) - H), F5 is bit 1 of (A - (HL) - H), H as in F after instruction
+        let n = result.wrapping_sub(self.flags.hf as u8);
+        self.flags.yf = n & 0x02 != 0;
+        self.flags.xf = n & 0x08 != 0;
+        self.block_repeat(repeat && self.flags.pf && !self.flags.zf);
     }
 
     pub(crate) fn add_hl(&mut self, reg: Register) {
-        let hl: u16 = self.get_pair(HL);
-        let (result, add) = (
-            (self.get_pair(HL) as u32).wrapping_add(self.get_pair(reg) as u32),
-            self.get_pair(reg),
-        );
-        self.write_pair_direct(HL, result as u16);
-        self.flags.cf = ((result >> 8) & 0x0100) != 0;
-
-        // TODO Figure out why HF_ADD_W doesn't work here
-        // self.flags.hf = self.hf_add_w(hl, add as u16);
-        self.flags.hf = self.carry(12, hl, add as u16);
+        self.add16(HL, reg);
+    }
 
+    // ADD HL, rr / ADD IX, rr / ADD IY, rr
+    // S, Z & P are not affected, H is the carry from bit 11 and X & Y are taken from the high byte
+    fn add16(&mut self, dst: Register, src: Register) {
+        let (value, add) = (self.get_pair(dst), self.get_pair(src));
+        let result = value as u32 + add as u32;
+        self.write_pair_direct(dst, result as u16);
+        self.reg.memptr = value.wrapping_add(1);
+
+        // DAD only affects CY on the 8080
+        if !self.i8080() {
+            self.flags.hf = (value ^ add ^ result as u16) & 0x1000 != 0;
+        }
+        self.flags.cf = result & 0x1_0000 != 0;
         self.flags.nf = false;
         self.flags.yf = (result >> 8) & 0x20 != 0;
         self.flags.xf = (result >> 8) & 0x08 != 0;
-        self.adv_cycles(11);
-        self.adv_pc(1);
     }
 
     // Decrement memory or register
@@ -804,143 +824,105 @@ impl Cpu {
         // If the H register contains 3AH, and the L register contains 7CH
         // and memory location 3A7CH contains 40H, the instruction:
         // DCR M will cause memory location 3A7CH to contain 3FH.
-        let mut result = 0;
-
-        if (reg == HL) || (reg == M) {
-            self.adv_cycles(5);
-            let hl = self.get_pair(HL);
-            self.memory[hl] = self.memory[hl].wrapping_sub(1);
-            result = self.memory[hl]
-        }
-        self.write_reg(reg, self.read_reg(reg).wrapping_sub(1));
-        result = self.read_reg(reg);
-
-        let overflow = (result as i8).wrapping_add(1).overflowing_sub(1).1;
+        let value = match reg {
+            HL | IX | IY => {
+                let addr = self.operand_addr(reg);
+                let value = self.read8(addr);
+                self.write8(addr, value.wrapping_sub(1));
+                value
+            }
+            _ => {
+                let value = self.read_reg(reg);
+                self.write_reg(reg, value.wrapping_sub(1));
+                value
+            }
+        };
+        let result = value.wrapping_sub(1);
 
         self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_sub(result.wrapping_add(1), 1);
-        self.flags.pf = overflow;
+        self.flags.zf = result == 0;
+        // The 8080 sets AC unless the low nibble borrowed (DCR adds 0xFF)
+        self.flags.hf = (value & 0x0F == 0) != self.i8080();
+        self.flags.pf = if self.i8080() {
+            self.parity(result)
+        } else {
+            value == 0x80
+        };
         self.flags.nf = true;
         self.flags.yf = result & 0x20 != 0;
         self.flags.xf = result & 0x08 != 0;
-
-        self.adv_cycles(4);
-        self.adv_pc(1);
     }
 
     // DEC register pair.
     // decrement! macro used for actual 16 bit registers for simplicity
     fn dex(&mut self, pair: Register) {
         self.write_pair_direct(pair, self.get_pair(pair).wrapping_sub(1));
-        if (pair == IX) || (pair == IY) {
-            self.adv_cycles(4);
-            self.adv_pc(1);
-        }
-
-        if (pair == IY) || (pair == IX) {
-            self.adv_cycles(4);
-            self.adv_pc(1);
-        }
-        self.adv_cycles(6);
-        self.adv_pc(1);
     }
 
     // Double precision add
     fn daa(&mut self) {
+        let a = self.reg.a;
         let mut offset = 0;
+        let mut carry = self.flags.cf;
 
-        if self.flags.hf || self.reg.a & 0x0F > 0x09 {
-            offset += 0x06;
+        if self.flags.hf || a & 0x0F > 0x09 {
+            offset |= 0x06;
         }
-        if (self.reg.a > 0x99) || self.flags.cf {
-            offset += 0x60;
-            self.flags.cf = true;
+        if a > 0x99 || carry {
+            offset |= 0x60;
+            carry = true;
         }
-        if self.flags.nf {
-            self.flags.hf = self.flags.hf && (self.reg.a & 0x0F) < 0x06;
-            self.reg.a -= offset;
+        // The 8080 has no N flag & only adjusts after additions
+        let result = if self.flags.nf && !self.i8080() {
+            self.flags.hf = self.flags.hf && (a & 0x0F) < 0x06;
+            a.wrapping_sub(offset)
         } else {
-            self.flags.hf = (self.reg.a & 0x0F) > 0x09;
-            self.reg.a += offset;
-        }
-        let result = (self.reg.a as u16).wrapping_add(offset as u16);
+            self.flags.hf = (a & 0x0F) > 0x09;
+            a.wrapping_add(offset)
+        };
 
         self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.pf = self.parity(result as u8);
-        self.flags.yf = self.reg.a & 0x20 != 0;
-        self.flags.xf = self.reg.a & 0x08 != 0;
-        self.reg.a = result as u8;
-
-        self.adv_cycles(4);
-        self.adv_pc(1);
+        self.flags.zf = result == 0;
+        self.flags.pf = self.parity(result);
+        self.flags.yf = result & 0x20 != 0;
+        self.flags.xf = result & 0x08 != 0;
+        self.flags.cf = carry;
+        self.reg.a = result;
     }
 
     fn set_interrupt_mode(&mut self, mode: u8) {
-        println!("Setting interrupt mode 2");
+        if self.debug {
+            println!("Setting interrupt mode {}", mode);
+        }
         self.int.mode = mode;
-        self.adv_cycles(8);
-        self.adv_pc(2);
     }
     // EI & DI instructions
     fn interrupt(&mut self, value: bool) {
-        self.int.int = value;
-        if value {
-            self.int.irq = true;
-        } else if !value {
-            self.int.iff1 = false;
-            self.int.iff2 = false;
-        }
-        self.adv_cycles(4);
-        self.adv_pc(1);
+        self.int.iff1 = value;
+        self.int.iff2 = value;
+        self.int.ei_delay = value;
+    }
+
+    // RETN & RETI
+    // Both restore IFF1 from IFF2 (RETI is additionally decoded by Z80 peripherals)
+    fn retn(&mut self) {
+        self.int.iff1 = self.int.iff2;
+        self.ret();
     }
 
     // Rotate Accumulator Left Through Carry
+    // The accumulator rotates only affect CY on the 8080, H is reset on the Z80
     fn rla(&mut self) {
         // The contents of the accumulator are rotated one bit position to the left.
         // The high-order bit of the accumulator replaces the carry bit while the carry bit
         // replaces the high-order bit of the accumulator
         let carry = (self.reg.a >> 7) != 0;
-        self.reg.a = (self.reg.a << 1) | ((self.flags.cf as u8) << 7);
+        self.reg.a = (self.reg.a << 1) | self.flags.cf as u8;
         self.flags.nf = false;
-        self.flags.hf = false;
+        self.flags.hf = self.flags.hf && self.i8080();
         self.flags.yf = self.reg.a & 0x20 != 0;
         self.flags.xf = self.reg.a & 0x08 != 0;
         self.flags.cf = carry;
-
-        self.adv_cycles(4);
-        self.adv_pc(1);
-    }
-    fn rrc(&mut self, reg: Register) {
-        self.write_reg(
-            reg,
-            (self.read_reg(reg) >> 1) | ((self.flags.cf as u8) << 7),
-        );
-        let value = self.read_reg(reg);
-
-        self.flags.nf = false;
-        self.flags.hf = false;
-        self.flags.yf = value & 0x20 != 0;
-        self.flags.xf = value & 0x08 != 0;
-        self.flags.cf = value & 0x80 != 0;
-        self.parity(value);
-        self.adv_pc(2);
-        self.adv_cycles(8);
-    }
-    // Extended instruction 0xCB03
-    fn rlc(&mut self, reg: Register) {
-        self.write_reg(reg, (self.read_reg(reg) << 1) | ((self.flags.cf as u8) & 1));
-        let value = self.read_reg(reg);
-
-        self.flags.nf = false;
-        self.flags.hf = false;
-        self.flags.yf = value & 0x20 != 0;
-        self.flags.xf = value & 0x08 != 0;
-        self.flags.cf = value & 0x80 != 0;
-        self.parity(value);
-        self.adv_pc(2);
-        self.adv_cycles(8);
     }
     // Rotate Accumulator Right Through Carry
     fn rra(&mut self) {
@@ -950,9 +932,7 @@ impl Cpu {
         self.flags.yf = self.reg.a & 0x20 != 0;
         self.flags.xf = self.reg.a & 0x08 != 0;
         self.flags.nf = false;
-        self.flags.hf = false;
-        self.adv_cycles(4);
-        self.adv_pc(1);
+        self.flags.hf = self.flags.hf && self.i8080();
     }
 
     // Rotate Accumulator Left
@@ -962,9 +942,7 @@ impl Cpu {
         self.flags.yf = self.reg.a & 0x20 != 0;
         self.flags.xf = self.reg.a & 0x08 != 0;
         self.flags.nf = false;
-        self.flags.hf = false;
-        self.adv_cycles(4);
-        self.adv_pc(1);
+        self.flags.hf = self.flags.hf && self.i8080();
     }
 
     fn rrca(&mut self) {
@@ -973,19 +951,13 @@ impl Cpu {
         self.flags.yf = self.reg.a & 0x20 != 0;
         self.flags.xf = self.reg.a & 0x08 != 0;
         self.flags.nf = false;
-        self.flags.hf = false;
-        self.adv_cycles(4);
-        self.adv_pc(1);
+        self.flags.hf = self.flags.hf && self.i8080();
     }
 
     // Conditional return
     fn ret_cond(&mut self, cond: bool) {
         if cond {
-            self.adv_cycles(1);
             self.ret();
-        } else {
-            self.adv_cycles(5);
-            self.adv_pc(1);
         }
     }
 
@@ -993,281 +965,141 @@ impl Cpu {
     fn mvi(&mut self, reg: Register) {
         // The MVI instruction uses a 8-bit data quantity, as opposed to
         // LXI which uses a 16-bit data quantity.
-        let value = self.read8(self.reg.pc + 1);
-        if reg != HL {
-            self.write_reg(reg, value);
-        } else {
-            self.adv_cycles(3);
-            let hl = self.get_pair(HL);
-            self.memory[hl] = value;
+        match reg {
+            HL | IX | IY => {
+                // For LD (IX+d), * the immediate follows the displacement byte
+                let addr = self.operand_addr(reg);
+                let offset = if reg == HL { 1 } else { 2 };
+                let value = self.read8(self.reg.pc.wrapping_add(offset));
+                self.write8(addr, value);
+            }
+            _ => {
+                let value = self.read8(self.reg.pc.wrapping_add(1));
+                self.write_reg(reg, value);
+            }
         }
-        self.adv_cycles(7);
-        self.adv_pc(2);
     }
 
     // LDA Load Accumulator direct
     fn lda_im(&mut self) {
-        let addr = self.read16(self.reg.pc + 1);
+        let addr = self.read16(self.reg.pc.wrapping_add(1));
         self.reg.a = self.read8(addr);
-        self.adv_cycles(13);
-        self.adv_pc(3);
+        self.reg.memptr = addr.wrapping_add(1);
     }
 
-    fn ld_ixh_ixl(&mut self, reg: Register) {
-        let value = self.read8(self.reg.pc + 1);
-        match reg {
-            IXL | IXH | IYH | IYL => self.write_pair_direct(reg, value as u16),
-            _ => panic!(),
-        }
-        self.adv_pc(self.instruction.bytes as u16);
-        self.adv_cycles(self.instruction.cycles as usize);
-    }
-    // 0xDD Instruction LD IX/IY or IXH IYL etc + *
-    // E.g stores A to the memory location pointed to by IX + *
-    fn ld_dd(&mut self, dst: Register, src: Register) {
-        let b = self.read8(self.reg.pc + 1) as u16;
-        let pair = self.get_pair(dst);
-        match src {
-            A => self.memory[pair + b] = self.read_reg(src),
-            _ => panic!(
-                "DD prefixed LD unknown destination: {:?}, src:{:?}",
-                dst, src
-            ),
-        };
-
-        // TODO LD IXH Only uses 2 bytes and 8 cycles
-        self.adv_cycles(19);
-        self.adv_pc(3);
-    }
     // LD (Load extended registers)
     fn ld_ex(&mut self, reg: Register) {
         // The contents of the designated register pair point to a memory location.
         // This instruction copies the contents of that memory location into the
         // accumulator. The contents of either the register pair or the
         // memory location are not altered.
-        self.reg.a = self.memory[self.get_pair(reg)];
-        self.adv_cycles(7);
-        self.adv_pc(1);
+        let addr = self.get_pair(reg);
+        self.reg.a = self.read8(addr);
+        if reg != HL {
+            self.reg.memptr = addr.wrapping_add(1);
+        }
     }
 
-    fn lhld(&mut self) {
-        // Load the HL register with 16 bits found at addr & addr + 1
-        let imm = self.read16(self.reg.pc + 1);
-        self.write_pair_direct(HL, imm);
-        self.write_pair_direct(HL, self.read16(imm));
-        self.adv_cycles(16);
-        self.adv_pc(3);
+    fn lhld(&mut self, reg: Register) {
+        // Load the HL (or IX / IY) register with 16 bits found at addr & addr + 1
+        let imm = self.read16(self.reg.pc.wrapping_add(1));
+        self.write_pair_direct(reg, self.read16(imm));
+        self.reg.memptr = imm.wrapping_add(1);
     }
 
     pub(crate) fn inc(&mut self, reg: Register) {
-        let result = match reg {
-            A | B | C | D | E | H | L => {
-                self.write_reg(reg, self.read_reg(reg).wrapping_add(1));
-                self.read_reg(reg)
+        let value = match reg {
+            HL | IX | IY => {
+                let addr = self.operand_addr(reg);
+                let value = self.read8(addr);
+                self.write8(addr, value.wrapping_add(1));
+                value
             }
-            HL => {
-                self.adv_cycles(5);
-                let hl = self.get_pair(Register::HL);
-                self.memory[hl] = self.memory[hl].wrapping_add(1);
-                self.memory[hl]
+            _ => {
+                let value = self.read_reg(reg);
+                self.write_reg(reg, value.wrapping_add(1));
+                value
             }
-            _ => unimplemented!("INC not implemented for:{:?}", reg),
         };
-        let overflow = (result as i8).wrapping_sub(1).overflowing_add(1).1;
+        let result = value.wrapping_add(1);
 
         self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_add(result.wrapping_sub(1), 1);
-        self.flags.pf = overflow;
+        self.flags.zf = result == 0;
+        self.flags.hf = value & 0x0F == 0x0F;
+        self.flags.pf = if self.i8080() {
+            self.parity(result)
+        } else {
+            value == 0x7F
+        };
         self.flags.nf = false;
         self.flags.yf = result & 0x20 != 0;
         self.flags.xf = result & 0x08 != 0;
-        self.adv_cycles(4);
-        self.adv_pc(1);
     }
 
     fn inx(&mut self, reg: Register) {
         let value = self.get_pair(reg).wrapping_add(1);
         self.write_pair_direct(reg, value);
-        if (reg == IX) | (reg == IY) {
-            self.adv_cycles(4);
-            self.adv_pc(1);
-        }
-        self.adv_cycles(6);
-        self.adv_pc(1);
     }
 
     fn push(&mut self, reg: Register) {
-        self.reg.sp = self.reg.sp.wrapping_sub(2);
-        self.write16(self.reg.sp, self.get_pair(reg));
-        if (reg == IY) | (reg == IX) {
-            self.adv_pc(1);
-            self.adv_cycles(4);
-        }
-        self.adv_cycles(11);
-        self.adv_pc(1);
+        self.push16(self.get_pair(reg));
     }
 
     // Store the contents of the accumulator addressed by registers B, C
     // or by registers D and E.
     fn stax(&mut self, reg: Register) {
-        self.write8(self.get_pair(reg), self.reg.a);
-        self.adv_cycles(7);
-        self.adv_pc(1);
+        let addr = self.get_pair(reg);
+        self.write8(addr, self.reg.a);
+        self.reg.memptr = (self.reg.a as u16) << 8 | (addr.wrapping_add(1) & 0xFF);
     }
 
     // SBC Subtract Register or Memory from Accumulator with carry flag
     fn sbc(&mut self, reg: Register) {
-        let value = if reg != HL {
-            self.read_reg(reg)
-        } else {
-            self.adv_cycles(3);
-            self.memory[self.get_pair(HL)]
-        };
-
-        let result = (self.reg.a as u16)
-            .wrapping_sub(value as u16)
-            .wrapping_sub(self.flags.cf as u16);
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_sub(self.reg.a, value as u8);
-        self.flags.pf = self.overflow(value, result as u8);
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.cf = result & 0x0100 != 0;
-        self.flags.nf = true;
-        self.reg.a = result as u8;
-
-        self.adv_cycles(4);
-        self.adv_pc(1);
+        let value = self.read_operand(reg);
+        self.reg.a = self.sub8(value, self.flags.cf);
     }
-    // TODO: SBI & SUI can be consolidated to one function
     // Subtract Immediate with Borrow
     fn sbi(&mut self) {
-        let imm = self.read8(self.reg.pc + 1);
-        let value = imm + self.flags.cf as u8;
-        let result = (self.reg.a as u16).wrapping_sub(value as u16);
-        let overflow = (self.reg.a as i8).overflowing_sub(value as i8).1;
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_sub(self.reg.a, value as u8);
-        self.flags.pf = overflow;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.nf = true;
-        self.flags.cf = result & 0x0100 != 0;
-        self.reg.a = result as u8;
-
-        self.adv_cycles(7);
-        self.adv_pc(2);
+        let value = self.read8(self.reg.pc.wrapping_add(1));
+        self.reg.a = self.sub8(value, self.flags.cf);
     }
 
     // SUB Subtract Register or Memory From Accumulator
     fn sub(&mut self, reg: Register) {
-        let value = if reg != HL {
-            self.read_reg(reg)
-        } else {
-            self.adv_cycles(3);
-            self.memory[self.get_pair(HL)]
-        };
-        if (reg == IX) | (reg == IY) {
-            self.adv_pc(1);
-            self.adv_cycles(4);
-        }
-
-        let result = (self.reg.a as u16).wrapping_sub(value as u16);
-        let overflow = (self.reg.a as i8).overflowing_sub(value as i8).1;
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_sub(self.reg.a, value);
-        self.flags.pf = overflow;
-        self.flags.nf = true;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.cf = result & 0x0100 != 0;
-        self.reg.a = result as u8;
-
-        self.adv_cycles(4);
-        self.adv_pc(1);
+        let value = self.read_operand(reg);
+        self.reg.a = self.sub8(value, false);
     }
 
     // SUI Subtract Immediate From Accumulator
     fn sui(&mut self) {
-        let value = self.read8(self.reg.pc + 1);
-        let result = (self.reg.a as u16).wrapping_sub(value as u16);
-        let overflow = (self.reg.a as i8).overflowing_sub(value as i8).1;
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = self.hf_sub(self.reg.a, value as u8);
-        self.flags.pf = self.overflow(value, result as u8);
-        self.flags.pf = overflow;
-        self.flags.nf = true;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.cf = result & 0x0100 != 0;
-        self.reg.a = result as u8;
-
-        self.adv_cycles(7);
-        self.adv_pc(2);
+        let value = self.read8(self.reg.pc.wrapping_add(1));
+        self.reg.a = self.sub8(value, false);
     }
 
     // Set Carry (set carry bit to 1)
     fn scf(&mut self) {
         self.flags.cf = true;
+        if self.i8080() {
+            return;
+        }
         self.flags.nf = false;
         self.flags.hf = false;
         self.flags.yf = self.reg.a & 0x20 != 0;
         self.flags.xf = self.reg.a & 0x08 != 0;
-        self.adv_cycles(4);
-        self.adv_pc(1);
     }
 
     // XRA Logical Exclusive-Or memory with Accumulator (Zero accumulator)
     fn xra(&mut self, reg: Register) {
-        let value = if reg != HL {
-            self.read_reg(reg)
-        } else {
-            self.adv_cycles(3);
-            self.memory[self.get_pair(HL)]
-        };
-
-        let result = self.reg.a as u16 ^ value as u16;
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = false;
-        self.flags.nf = false;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.cf = false;
-        self.flags.pf = self.parity(result as u8);
-        self.reg.a = result as u8;
-
-        self.adv_cycles(4);
-        self.adv_pc(1);
+        let result = self.reg.a ^ self.read_operand(reg);
+        self.logic_flags(result, false);
+        self.reg.a = result;
     }
 
     // XRI Exclusive-Or Immediate with Accumulator
     fn xri(&mut self) {
-        let imm = self.read8(self.reg.pc + 1);
-        let result = self.reg.a ^ imm as u8;
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = false;
-        self.flags.nf = false;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.pf = self.parity(result);
-        self.flags.cf = false;
+        let result = self.reg.a ^ self.read8(self.reg.pc.wrapping_add(1));
+        self.logic_flags(result, false);
         self.reg.a = result;
-
-        self.adv_cycles(7);
-        self.adv_pc(2);
     }
 
     fn ex_af_af(&mut self) {
@@ -1276,8 +1108,6 @@ impl Cpu {
         self.reg.a = a_;
         self.reg.a_ = a;
         self.flags.swap();
-        self.adv_cycles(4);
-        self.adv_pc(1);
     }
     fn exx(&mut self) {
         let b = self.reg.b;
@@ -1300,701 +1130,463 @@ impl Cpu {
         self.reg.e_ = e;
         self.reg.h_ = h;
         self.reg.l_ = l;
-        self.adv_pc(1);
-        self.adv_cycles(4);
     }
     fn ex_de_hl(&mut self) {
         use std::mem;
         mem::swap(&mut self.reg.h, &mut self.reg.d);
         mem::swap(&mut self.reg.l, &mut self.reg.e);
-        self.adv_cycles(4);
-        self.adv_pc(1);
     }
 
-    fn xthl(&mut self) {
-        // Swap H:L with top word on stack
-        let hl = self.get_pair(Register::HL) as u16;
+    fn xthl(&mut self, reg: Register) {
+        // Swap H:L (or IX / IY) with top word on stack
+        let hl = self.get_pair(reg);
         let new_hl = self.read16(self.reg.sp);
-        // Write old HL values to memory
-        self.write16(self.reg.sp, hl);
-        self.write_pair_direct(HL, new_hl);
-        self.adv_cycles(19);
-        self.adv_pc(1);
+        // Write old HL values to memory, high byte first
+        self.write8(self.reg.sp.wrapping_add(1), (hl >> 8) as u8);
+        self.write8(self.reg.sp, hl as u8);
+        self.write_pair_direct(reg, new_hl);
+        self.reg.memptr = new_hl;
     }
 
     fn pop(&mut self, reg: Register) {
         let value = self.read16(self.reg.sp);
         self.write_pair_direct(reg, value);
         self.reg.sp = self.reg.sp.wrapping_add(2);
-
-        if (reg == IX) | (reg == IY) {
-            self.adv_cycles(4);
-            self.adv_pc(1);
-        }
-        self.adv_pc(1);
-        self.adv_cycles(10);
     }
 
     fn ret(&mut self) {
-        let low = self.memory[self.reg.sp];
-        let high = self.memory[self.reg.sp.wrapping_add(1)];
-        let ret: u16 = (high as u16) << 8 | (low as u16);
-        // Set program counter for debug output
-        self.reg.prev_pc = self.reg.pc;
-        self.reg.pc = ret as u16;
+        let ret = self.read16(self.reg.sp);
+        self.jump(ret);
+        self.reg.memptr = ret;
         self.reg.sp = self.reg.sp.wrapping_add(2);
-        self.adv_cycles(10);
     }
 
-    // Extended opcode
+    fn port_in(&mut self, port: u16) -> u8 {
+        let value = self.bus.port_in(port);
+        if !self.watch.is_empty() {
+            self.watch.check(Access::Io, port, value);
+        }
+        if self.debug {
+            println!("In port: {:04x}, value: {:02x}", port, value);
+        }
+        self.io.port = port;
+        self.io.value = value;
+        value
+    }
+
+    fn port_out(&mut self, port: u16, value: u8) {
+        if self.debug {
+            println!("Out port: {:04x}, value: {:02x}", port, value);
+        }
+        if !self.watch.is_empty() {
+            self.watch.check(Access::Io, port, value);
+        }
+        self.io.port = port;
+        self.io.value = value;
+        self.bus.port_out(port, value);
+    }
+
+    // Extended opcode IN r, (C)
+    // The port address is BC, HL is used for the undocumented IN (C) / IN F, (C)
+    // which only affects the flags.
     fn in_c(&mut self, reg: Register) {
-        self.write_reg(reg, self.reg.c);
-        self.flags.zf = self.read_reg(reg) == 0;
+        let value = self.port_in(self.get_pair(BC));
+        self.reg.memptr = self.get_pair(BC).wrapping_add(1);
+        if reg != HL {
+            self.write_reg(reg, value);
+        }
+        self.flags.sf = value & 0x80 != 0;
+        self.flags.zf = value == 0;
+        self.flags.yf = value & 0x20 != 0;
         self.flags.hf = false;
+        self.flags.xf = value & 0x08 != 0;
         self.flags.nf = false;
-        self.flags.pf = self.parity(self.read_reg(reg));
-        self.adv_cycles(12);
-        self.adv_pc(2);
+        self.flags.pf = self.parity(value);
+    }
+
+    // Port address of IN A, (*) & OUT (*), A. The 8080 puts the port number on both halves
+    // of the address bus.
+    fn port_addr(&self) -> u16 {
+        let n = self.read8(self.reg.pc.wrapping_add(1)) as u16;
+        match self.model {
+            CpuModel::Z80 => (self.reg.a as u16) << 8 | n,
+            CpuModel::I8080 => n << 8 | n,
+        }
     }
+
+    // IN A, (*)
+    // The upper half of the port address is the current value of A
     fn in_a(&mut self) {
-        self.io.port = self.read8(self.reg.pc + 1);
-        self.reg.a = 0xFF; // TODO: hack (other emu's do this for zexdoc??)
-                           // self.reg.a = self.io.port;
-        self.adv_cycles(11);
-        self.adv_pc(2);
+        let port = self.port_addr();
+        self.reg.a = self.port_in(port);
+        self.reg.memptr = port.wrapping_add(1);
     }
 
+    // OUT (*), A
     fn out(&mut self, reg: Register) {
-        // Set port:
-        let port = self.read8(self.reg.pc + 1);
-        if self.debug {
-            println!("Out port: {:02x}, value: {:02x}", port, self.read_reg(reg));
-        }
-        self.io.port = self.read_reg(reg);
-        self.adv_cycles(11);
-        self.adv_pc(2);
+        let value = self.read_reg(reg);
+        let port = self.port_addr();
+        self.port_out(port, value);
+        self.reg.memptr = (port & 0xFF00) | (port.wrapping_add(1) & 0xFF);
+    }
+
+    // Extended opcode OUT (C), r
+    // HL is used for the undocumented OUT (C), 0
+    fn out_c(&mut self, reg: Register) {
+        let value = if reg != HL { self.read_reg(reg) } else { 0 };
+        self.port_out(self.get_pair(BC), value);
+        self.reg.memptr = self.get_pair(BC).wrapping_add(1);
+    }
+
+    // INI, IND, INIR & INDR
+    // Reads port BC into (HL), then B is decremented & HL is stepped.
+    fn in_block(&mut self, increment: bool, repeat: bool) {
+        let value = self.port_in(self.get_pair(BC));
+        let step = if increment { 1 } else { 0xFFFF };
+        self.reg.memptr = self.get_pair(BC).wrapping_add(step);
+        let hl = self.get_pair(HL);
+        self.write8(hl, value);
+        let hl = if increment {
+            hl.wrapping_add(1)
+        } else {
+            hl.wrapping_sub(1)
+        };
+        self.write_pair_direct(HL, hl);
+        self.reg.b = self.reg.b.wrapping_sub(1);
+
+        let c = if increment {
+            self.reg.c.wrapping_add(1)
+        } else {
+            self.reg.c.wrapping_sub(1)
+        };
+        self.block_io_flags(value, c);
+        self.block_io_repeat(repeat);
     }
-    // TODO: Consolidate ORA & ORI (pass value directly)
-    fn ora(&mut self, reg: Register) {
-        let value = if reg != HL {
-            self.read_reg(reg)
+
+    // OUTI, OUTD, OTIR & OTDR
+    // B is decremented before the port address is put on the bus.
+    fn out_block(&mut self, increment: bool, repeat: bool) {
+        let hl = self.get_pair(HL);
+        let value = self.read8(hl);
+        self.reg.b = self.reg.b.wrapping_sub(1);
+        self.port_out(self.get_pair(BC), value);
+        let step = if increment { 1 } else { 0xFFFF };
+        self.reg.memptr = self.get_pair(BC).wrapping_add(step);
+        let hl = if increment {
+            hl.wrapping_add(1)
         } else {
-            self.adv_cycles(3);
-            self.memory[self.get_pair(HL)]
+            hl.wrapping_sub(1)
         };
+        self.write_pair_direct(HL, hl);
 
-        let result = self.reg.a as u16 | value as u16;
+        self.block_io_flags(value, self.reg.l);
+        self.block_io_repeat(repeat);
+    }
 
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = false;
-        self.flags.nf = false;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.pf = self.parity(result as u8);
-        self.flags.cf = false;
-        self.reg.a = result as u8;
+    // Flags for the block I/O instructions, see "The Undocumented Z80 Documented" (4.3)
+    fn block_io_flags(&mut self, value: u8, operand: u8) {
+        let k = value as u16 + operand as u16;
+        let b = self.reg.b;
+        self.flags.sf = b & 0x80 != 0;
+        self.flags.zf = b == 0;
+        self.flags.yf = b & 0x20 != 0;
+        self.flags.xf = b & 0x08 != 0;
+        self.flags.nf = value & 0x80 != 0;
+        self.flags.hf = k > 0xFF;
+        self.flags.cf = k > 0xFF;
+        self.flags.pf = self.parity((k as u8 & 0x07) ^ b);
+    }
+
+    fn block_io_repeat(&mut self, repeat: bool) {
+        if repeat && self.reg.b != 0 {
+            self.repeat();
+        }
+    }
 
-        self.adv_cycles(4);
-        self.adv_pc(1);
+    fn ora(&mut self, reg: Register) {
+        let result = self.reg.a | self.read_operand(reg);
+        self.logic_flags(result, false);
+        self.reg.a = result;
     }
 
     // Or Immediate with Accumulator
     fn ori(&mut self) {
-        let result = self.reg.a as u16 | self.read8(self.reg.pc + 1) as u16;
-
-        self.flags.sf = result & 0x80 != 0;
-        self.flags.zf = result & 0xFF == 0;
-        self.flags.hf = false;
-        self.flags.nf = false;
-        self.flags.yf = result & 0x20 != 0;
-        self.flags.xf = result & 0x08 != 0;
-        self.flags.pf = self.parity(result as u8);
-        self.flags.cf = false;
-        self.reg.a = result as u8;
-
-        self.adv_cycles(7);
-        self.adv_pc(2);
+        let result = self.reg.a | self.read8(self.reg.pc.wrapping_add(1));
+        self.logic_flags(result, false);
+        self.reg.a = result;
     }
 
     fn ld(&mut self, dst: Register, src: Register) {
-        // let value =  self.read_reg(src);
-        let mut value: u16 = match src {
-            A | B | C | D | E | H | L | I | R => u16::from(self.read_reg(src)),
-            BC | DE | HL => self.get_pair(src),
-            _ => panic!("Non handled LD source"),
-        };
-
-        let addr = self.get_pair(Register::HL) as u16;
-
-        match dst {
-            A | B | C | D | E | H | L => {
-                if src == HL {
-                    // LD r, (HL)
-                    value = self.read8(addr) as u16;
-                    self.adv_cycles(3);
-                } else if (src == R) | (src == I) {
-                    self.flags.sf = (self.reg.a & 0x80) != 0;
-                    self.flags.zf = (self.reg.a & 0xFF) == 0;
-                    // TODO PF interrupt interrupt handling
-                    self.flags.pf = self.int.iff2;
-                    self.flags.hf = false;
-                    self.flags.nf = false;
-                    self.adv_cycles(5);
-                    self.adv_pc(1);
-                }
-                self.write_reg(dst, value as u8);
-            }
-
-            HL => {
-                // LD (HL, r)
+        match (dst, src) {
+            // LD (HL), r & LD (IX+d), r
+            (HL | IX | IY, _) => {
+                let addr = self.operand_addr(dst);
                 self.write8(addr, self.read_reg(src));
-                self.adv_cycles(3);
             }
-            I | R => {
-                if src == HL {
-                    self.write_reg(src, self.read8(addr));
-                    self.adv_cycles(2);
-                }
-                self.adv_cycles(5);
-                self.adv_pc(1);
-                self.write_reg(dst, value as u8);
+            // LD A, I & LD A, R copy IFF2 into the parity flag
+            (A, I | R) => {
+                let value = self.read_reg(src);
+                self.flags.sf = (value & 0x80) != 0;
+                self.flags.zf = value == 0;
+                self.flags.yf = value & 0x20 != 0;
+                self.flags.xf = value & 0x08 != 0;
+                self.flags.pf = self.int.iff2;
+                self.flags.hf = false;
+                self.flags.nf = false;
+                self.reg.a = value;
+            }
+            (I | R, _) => self.write_reg(dst, self.read_reg(src)),
+            // LD r, r', LD r, (HL) & LD r, (IX+d)
+            _ => {
+                let value = self.read_operand(src);
+                self.write_reg(dst, value);
             }
-            _ => panic!("Unhandled LD register"),
         }
-        self.adv_cycles(4);
-        self.adv_pc(1);
     }
 
     // RESET (used for interrupt jump / calls)
     pub fn rst(&mut self, value: u16) {
-        // Address to return to after interrupt is finished.
-        let ret: u16 = self.reg.pc.wrapping_add(3);
-        self.memory[self.reg.sp.wrapping_sub(1)] = (ret >> 8) as u8;
-        self.memory[self.reg.sp.wrapping_sub(2)] = ret as u8;
-        self.reg.sp = self.reg.sp.wrapping_sub(2);
-        self.reg.prev_pc = self.reg.pc;
-        self.reg.pc = value;
-        self.adv_cycles(11);
+        // Address to return to after the subroutine is finished.
+        self.push16(self.reg.pc.wrapping_add(1));
+        self.jump(value);
+        self.reg.memptr = value;
     }
 
-    fn sphl(&mut self) {
-        self.reg.sp = self.get_pair(HL) as u16;
-        self.adv_cycles(6);
-        self.adv_pc(1);
+    fn sphl(&mut self, reg: Register) {
+        self.reg.sp = self.get_pair(reg);
     }
 
-    // Store H & L direct
-    fn shld(&mut self) {
-        let addr = self.read16(self.reg.pc + 1);
-        let hl = self.get_pair(HL) as u16;
-        self.write16(addr, hl);
-        self.adv_cycles(16);
-        self.adv_pc(3);
+    // Store H & L direct (or IX / IY)
+    fn shld(&mut self, reg: Register) {
+        let addr = self.read16(self.reg.pc.wrapping_add(1));
+        self.write16(addr, self.get_pair(reg));
+        self.reg.memptr = addr.wrapping_add(1);
     }
 
-    pub fn nop(&mut self) {
-        self.adv_pc(1);
-        self.adv_cycles(4);
+    pub fn nop(&mut self) {}
+
+    // Executes a single instruction (or one internal NOP while halted)
+    pub fn step(&mut self) -> Result<StepInfo, CpuError> {
+        let pc = self.reg.pc;
+        let start_cycles = self.cycles;
+        if self.halted {
+            // While halted the CPU keeps executing NOPs (refreshing memory) until an
+            // interrupt is accepted
+            self.idle(4);
+        } else {
+            if self.trace.is_some() {
+                self.write_trace();
+            }
+            let r = self.reg.r;
+            self.fetch();
+            if let Err(e) = self.decode(self.opcode as u8) {
+                if let CpuError::UnknownOpcode { .. } = e {
+                    // Nothing was executed, reported at the start of the instruction
+                    self.reg.pc = pc;
+                    self.reg.r = r;
+                    self.cycles = start_cycles;
+                }
+                return Err(e);
+            }
+        }
+        Ok(StepInfo {
+            pc,
+            opcode: self.opcode,
+            cycles: self.cycles - start_cycles,
+            halted: self.halted,
+        })
+    }
+
+    // A trace that can't be written is closed, the machine keeps running
+    fn write_trace(&mut self) {
+        let record = Record::capture(self);
+        if let Some(Err(e)) = self.trace.as_mut().map(|trace| trace.write(&record)) {
+            log::error!("Execution trace stopped: {}", e);
+            self.trace = None;
+        }
     }
 
-    pub fn execute(&mut self) {
-        self.fetch();
-        self.decode(self.opcode);
+    // Spends (at least) the given amount of T states halted.
+    // One internal NOP is 4 T states and increments R like a regular opcode fetch.
+    pub fn idle(&mut self, t_states: usize) {
+        let nops = t_states.div_ceil(4);
+        self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(nops as u8) & 0x7f);
+        self.adv_cycles(nops * 4);
     }
 
     pub(crate) fn fetch(&mut self) {
         self.opcode = self.read8(self.reg.pc) as u16;
-        self.instruction = Instruction::decode(self.opcode)
-            .expect(format!("Unknown opcode:{:04X}", self.opcode).as_str());
+        self.inc_r();
 
-        if self.instruction.name.to_string().len() < 1 {
-            self.current_instruction = format!("{:w$}", self.current_instruction, w = 12);
-        } else {
-            self.current_instruction = self.instruction.name.to_string();
+        // The instruction metadata & mnemonic is only used for the debug output
+        if self.debug {
+            self.instruction = Instruction::decode(self.opcode).unwrap_or_default();
+
+            if self.instruction.name.is_empty() {
+                self.current_instruction = format!("{:w$}", self.current_instruction, w = 12);
+            } else {
+                self.current_instruction = self.instruction.name.to_string();
+            }
         }
     }
 
-    pub fn decode(&mut self, opcode: u16) {
-        use self::Register::*;
+    // Executes an instruction, an operand fault of its table entry is returned as an error
+    fn decode(&mut self, opcode: u8) -> Result<(), CpuError> {
+        self.fault.set(None);
+        self.decode_opcode(opcode)?;
+        self.fault.take().map_or(Ok(()), Err)
+    }
+
+    fn decode_opcode(&mut self, opcode: u8) -> Result<(), CpuError> {
         if self.debug {
             println!("{:?}", self);
         }
 
-        self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(1)) & 0x7f;
-
+        if self.i8080() {
+            return self.decode_8080(opcode);
+        }
         match opcode {
-            0x00 => self.nop(),
-            0x01 => self.lxi(BC),
-            0x02 => self.stax(BC),
-            0x03 => self.inx(BC),
-            0x04 => self.inc(B),
-            0x05 => self.dec(B),
-            0x06 => self.mvi(B),
-            0x07 => self.rlca(),
-            0x08 => self.ex_af_af(),
-            0x09 => self.add_hl(BC),
-            0x10 => self.djnz(),
-
-            0x0A => self.ld_ex(BC),
-            0x0B => self.dex(BC),
-            0x0C => self.inc(C),
-            0x0D => self.dec(C),
-            0x0E => self.mvi(C),
-            0x0F => self.rrca(),
-
-            0x11 => self.lxi(DE),
-            0x12 => self.stax(DE),
-            0x13 => self.inx(DE),
-            0x14 => self.inc(D),
-            0x15 => self.dec(D),
-            0x16 => self.mvi(D),
-            0x17 => self.rla(),
-            0x18 => self.jr(self.read8(self.reg.pc) as i16),
-            0x19 => self.add_hl(DE),
-
-            0x1A => self.ld_ex(DE),
-            0x1B => self.dex(DE),
-            0x1C => self.inc(E),
-            0x1D => self.dec(E),
-            0x1E => self.mvi(E),
-            0x1F => self.rra(),
-
-            0x20 => self.jr_cond(!self.flags.zf),
-            0x21 => self.lxi(HL),
-            0x22 => self.shld(),
-            0x23 => self.inx(HL),
-            0x24 => self.inc(H),
-            0x25 => self.dec(H),
-            0x26 => self.mvi(H),
-            0x27 => self.daa(),
-            0x28 => self.jr_cond(self.flags.zf),
-            0x29 => self.add_hl(HL),
-
-            0x2A => self.lhld(),
-            0x2B => self.dex(HL),
-            0x2C => self.inc(L),
-            0x2D => self.dec(L),
-            0x2E => self.mvi(L),
-            0x2F => self.cpl(),
-
-            0x30 => self.jr_cond(!self.flags.cf),
-            0x31 => self.lxi(SP),
-            0x32 => self.sta(),
-            0x33 => self.inx(SP),
-            0x34 => self.inc(HL),
-            0x35 => self.dec(M),
-            0x36 => self.mvi(HL),
-            0x37 => self.scf(),
-            0x38 => self.jr_cond(self.flags.cf), // JR C, *
-            0x39 => self.add_hl(SP),
-
-            0x3A => self.lda_im(),
-            0x3B => self.dex(SP),
-            0x3C => self.inc(A),
-            0x3D => self.dec(A),
-            0x3E => self.mvi(A),
-            0x3F => self.ccf(),
-
-            // MOV Instructions 0x40 - 0x7F
-            0x40 => self.ld(B, B),
-            0x41 => self.ld(B, C),
-            0x42 => self.ld(B, D),
-            0x43 => self.ld(B, E),
-            0x44 => self.ld(B, H),
-            0x45 => self.ld(B, L),
-            0x46 => self.ld(B, HL),
-            0x47 => self.ld(B, A),
-
-            0x48 => self.ld(C, B),
-            0x49 => self.ld(C, C),
-            0x4A => self.ld(C, D),
-            0x4B => self.ld(C, E),
-            0x4C => self.ld(C, H),
-            0x4D => self.ld(C, L),
-            0x4E => self.ld(C, HL),
-            0x4F => self.ld(C, A),
-
-            0x50 => self.ld(D, B),
-            0x51 => self.ld(D, C),
-            0x52 => self.ld(D, D),
-            0x53 => self.ld(D, E),
-            0x54 => self.ld(D, H),
-            0x55 => self.ld(D, L),
-            0x56 => self.ld(D, HL),
-            0x57 => self.ld(D, A),
-
-            0x58 => self.ld(E, B),
-            0x59 => self.ld(E, C),
-            0x5A => self.ld(E, D),
-            0x5B => self.ld(E, E),
-            0x5C => self.ld(E, H),
-            0x5D => self.ld(E, L),
-            0x5E => self.ld(E, HL),
-            0x5F => self.ld(E, A),
-
-            0x60 => self.ld(H, B),
-            0x61 => self.ld(H, C),
-            0x62 => self.ld(H, D),
-            0x63 => self.ld(H, E),
-            0x64 => self.ld(H, H),
-            0x65 => self.ld(H, L),
-            0x66 => self.ld(H, HL),
-            0x67 => self.ld(H, A),
-
-            0x68 => self.ld(L, B),
-            0x69 => self.ld(L, C),
-            0x6A => self.ld(L, D),
-            0x6B => self.ld(L, E),
-            0x6C => self.ld(L, H),
-            0x6D => self.ld(L, L),
-            0x6E => self.ld(L, HL),
-            0x6F => self.ld(L, A),
-
-            0x70 => self.ld(HL, B),
-            0x71 => self.ld(HL, C),
-            0x72 => self.ld(HL, D),
-            0x73 => self.ld(HL, E),
-            0x74 => self.ld(HL, H),
-            0x75 => self.ld(HL, L),
-
-            0x76 => self.hlt(),
-            0x77 => self.ld(HL, A),
-
-            0x78 => self.ld(A, B),
-            0x79 => self.ld(A, C),
-            0x7A => self.ld(A, D),
-            0x7B => self.ld(A, E),
-            0x7C => self.ld(A, H),
-            0x7D => self.ld(A, L),
-            0x7E => self.ld_ex(HL),
-            0x7F => self.ld(A, A),
-
-            // ADD Instructions
-            0x80 => self.add(B),
-            0x81 => self.add(C),
-            0x82 => self.add(D),
-            0x83 => self.add(E),
-            0x84 => self.add(H),
-            0x85 => self.add(L),
-            0x86 => self.add(HL),
-            0x87 => self.add(A),
-
-            0x88 => self.adc(B),
-            0x89 => self.adc(C),
-            0x8A => self.adc(D),
-            0x8B => self.adc(E),
-            0x8C => self.adc(H),
-            0x8D => self.adc(L),
-            0x8E => self.adc(HL),
-            0x8F => self.adc(A),
-
-            // SUB Instructions
-            0x90 => self.sub(B),
-            0x91 => self.sub(C),
-            0x92 => self.sub(D),
-            0x93 => self.sub(E),
-            0x94 => self.sub(H),
-            0x95 => self.sub(L),
-            0x96 => self.sub(HL),
-            0x97 => self.sub(A),
-
-            0x98 => self.sbc(B),
-            0x99 => self.sbc(C),
-            0x9A => self.sbc(D),
-            0x9B => self.sbc(E),
-            0x9C => self.sbc(H),
-            0x9D => self.sbc(L),
-            0x9E => self.sbc(HL),
-            0x9F => self.sbc(A),
-
-            // ANA
-            0xA0 => self.ana(B),
-            0xA1 => self.ana(C),
-            0xA2 => self.ana(D),
-            0xA3 => self.ana(E),
-            0xA4 => self.ana(H),
-            0xA5 => self.ana(L),
-            0xA6 => self.ana(HL),
-            0xA7 => self.ana(A),
-
-            // XRA
-            0xA8 => self.xra(B),
-            0xA9 => self.xra(C),
-            0xAA => self.xra(D),
-            0xAB => self.xra(E),
-            0xAC => self.xra(H),
-            0xAD => self.xra(L),
-            0xAE => self.xra(HL),
-            0xAF => self.xra(A),
-
-            // ORA Instructions  0xB(reg)
-            0xB0 => self.ora(B),
-            0xB1 => self.ora(C),
-            0xB2 => self.ora(D),
-            0xB3 => self.ora(E),
-            0xB4 => self.ora(H),
-            0xB5 => self.ora(L),
-            0xB6 => self.ora(HL),
-            0xB7 => self.ora(A),
-
-            // CMP
-            0xB8 => self.cmp(B),
-            0xB9 => self.cmp(C),
-            0xBA => self.cmp(D),
-            0xBB => self.cmp(E),
-            0xBC => self.cmp(H),
-            0xBD => self.cmp(L),
-            0xBE => self.cmp(HL),
-            0xBF => self.cmp(A),
-
-            0xC0 => self.ret_cond(!self.flags.zf),
-            0xC1 => self.pop(BC),
-            0xC2 => self.jp_cond(!self.flags.zf),
-            0xC3 => self.jp_cond(true),
-            0xC4 => self.call_cond(0xC4, !self.flags.zf),
-            0xC5 => self.push(BC),
-            0xC6 => self.adi(),
-            0xC7 => self.rst(0x0000),
-            0xC8 => self.ret_cond(self.flags.zf),
-            0xC9 => self.ret(),
-
-            0xCA => self.jp_cond(self.flags.zf),
             0xCB => {
-                self.opcode = self.read8(self.reg.pc + 1) as u16;
-                self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(1)) & 0x7f;
-                match self.opcode {
-                    0x00 => self.rlc(B),
-                    0x01 => self.rlc(C),
-                    0x02 => self.rlc(D),
-                    0x03 => self.rlc(E),
-                    0x04 => self.rlc(H),
-                    0x05 => self.rlc(L),
-                    0x06 => self.rlc(HL),
-                    0x03 => self.rlc(E),
-                    0x08 => self.rrc(B),
-                    0xC7 => unimplemented!("0xCBC7"),
-                    0x40 => self.bit(0, B),
-                    0x41 => self.bit(0, C),
-                    0x42 => self.bit(0, D),
-                    0x43 => self.bit(0, E),
-                    0x44 => self.bit(0, H),
-                    0x45 => self.bit(0, L),
-                    0x46 => self.bit(0, HL),
-                    0x50 => self.bit(2, B),
-                    0x51 => self.bit(2, C),
-                    0x52 => self.bit(2, D),
-                    0x53 => self.bit(2, E),
-                    0x54 => self.bit(2, H),
-                    0x55 => self.bit(2, L),
-                    0x56 => self.bit(2, HL),
-                    _ => unimplemented!("Unknown opcode:{:04x}", self.opcode),
-                }
+                self.opcode = self.read8(self.reg.pc.wrapping_add(1)) as u16;
+                self.inc_r();
+                let op = self.opcode as usize;
+                self.dispatch(opcodes::CB[op].zip(Self::CB_EXEC[op]), HL, 0)
             }
-            0xCC => self.call_cond(0xCC, self.flags.zf),
-            0xCD => self.call(0xCD),
-            0xCE => self.adc_im(),
-            0xCF => self.rst(0x0008),
-
-            0xD0 => self.ret_cond(!self.flags.cf),
-            0xD1 => self.pop(DE),
-            0xD2 => self.jp_cond(!self.flags.cf),
-            0xD3 => self.out(A),
-            0xD4 => self.call_cond(0xD4, !self.flags.cf),
-            0xD5 => self.push(DE),
-            0xD6 => self.sui(),
-            0xD7 => self.rst(0x0010),
-            0xD8 => self.ret_cond(self.flags.cf),
-            0xD9 => self.exx(),
-            0xDA => self.jp_cond(self.flags.cf),
-            0xDB => self.in_a(),
-            0xDC => self.call_cond(0xDC, self.flags.cf),
-            0xDD => {
-                self.opcode = self.read8(self.reg.pc + 1) as u16;
-                self.reg.r = (self.reg.r & 0x80) | self.reg.r.wrapping_add(1) & 0x7f;
-                self.instruction = Instruction::decode(self.opcode)
-                    .expect(format!("Unknown opcode:{:04x}", self.opcode).as_str());
-
-                match self.opcode {
-                    0x09 => unimplemented!("{:#?}", self.instruction),
-                    0x19 => self.add_ex(IX, DE),
-                    0x21 => {
-                        self.reg.ix = self.read16(self.reg.pc + 2);
-                        self.adv_pc(4);
-                        self.adv_cycles(14);
-                    }
-                    0x22 => {
-                        self.write8(
-                            self.read8(self.reg.pc + 2) as u16,
-                            self.read16(self.reg.ix) as u8,
-                        );
-                        self.adv_pc(4);
-                        self.adv_cycles(20);
-                    }
-                    0x23 => self.inx(IX),
-                    0x24 => unimplemented!("{:04x}", self.opcode),
-                    0x25 => unimplemented!(),
-                    0x26 => {
-                        self.ld_ixh_ixl(IXH);
-                    }
-                    0x29 => unimplemented!("{:04x}", self.opcode),
-                    0x2A => unimplemented!("{:04x}", self.opcode),
-                    0x2B => unimplemented!("{:04x}", self.opcode),
-                    0x2C => unimplemented!("{:04x}", self.opcode),
-                    0x2D => unimplemented!("{:04x}", self.opcode),
-                    0x2E => unimplemented!("{:04x}", self.opcode),
-                    0x34 => unimplemented!("{:04x}", self.opcode),
-                    0x35 => unimplemented!("{:04x}", self.opcode),
-                    0x36 => unimplemented!("{:04x}", self.opcode),
-                    0x39 => unimplemented!("{:04x}", self.opcode),
-                    0x3C => unimplemented!("{:04x}", self.opcode),
-                    0x3D => unimplemented!("{:04x}", self.opcode),
-                    0x3E => unimplemented!("{:04x}", self.opcode),
-                    0xE1 => self.pop(IX),
-                    0xE5 => self.push(IX),
-                    0x7E => {
-                        // byte is the signed displacement byte
-                        let byte = self.read8(self.reg.pc + 2) as i8;
-                        let addr = self.reg.ix.wrapping_add(byte as u16);
-                        self.reg.a = self.read8(addr) as i8 as u8;
-                        self.adv_pc(3);
-                        self.adv_cycles(19);
-                    }
-                    0x77 => self.ld_dd(IX, A),
-                    0xE9 => {
-                        self.opcode = 0xDDE9;
-                        self.instruction.cycles = 8;
-                        self.jp(self.reg.ix);
-                    }
-
-                    _ => panic!(
-                        "Unknown or unimplemented instruction:{:#?}",
-                        self.instruction
-                    ),
-                }
-            }
-            0xDE => self.sbi(),
-            0xDF => self.rst(0x0018),
-            0xE0 => self.ret_cond(!self.flags.pf),
-            0xE1 => self.pop(HL),
-            0xE2 => self.jp_cond(!self.flags.pf),
-            0xE3 => self.xthl(),
-            0xE4 => self.call_cond(0xE4, !self.flags.pf),
-            0xE5 => self.push(HL),
-            0xE6 => self.ani(),
-            0xE7 => self.rst(0x0020),
-            0xE8 => self.ret_cond(self.flags.pf),
-            0xE9 => self.pchl(),
-
-            0xEA => self.jp_cond(self.flags.pf),
-            0xEB => self.ex_de_hl(),
-            0xEC => self.call_cond(0xEC, self.flags.pf),
             0xED => {
-                self.opcode = self.read8(self.reg.pc + 1) as u16;
-                self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(1)) & 0x7f;
-                match self.opcode {
-                    0x08 => self.in_c(C),
-                    0xA0 => self.ldi(),
-                    0xA1 => self.cpi(),
-                    0xB0 => self.ldir(),
-                    0x43 => self.ld_nn(BC),
-                    0x46 => self.set_interrupt_mode(0),
-                    0x47 => self.ld(I, A),
-                    0x50 => self.in_c(D),
-                    0x53 => self.ld_nn(DE),
-                    0x5E => self.set_interrupt_mode(2),
-                    0x56 => self.set_interrupt_mode(1),
-                    0x57 => self.ld(A, I),
-                    0x63 => self.ld_nn(HL),
-                    0x66 => self.set_interrupt_mode(0),
-                    0x76 => self.set_interrupt_mode(1),
-                    0x4A => self.adc_hl(BC),
-                    0x4B => self.load_indirect(BC),
-                    0x4F => self.ld(R, A),
-                    0x5F => self.ld(A, R),
-                    0x5A => self.adc_hl(DE),
-                    0x5B => self.load_indirect(DE),
-                    0x6A => self.adc_hl(HL),
-                    0x6B => self.load_indirect(HL),
-                    0x73 => self.ld_nn(SP),
-                    0x7B => self.load_indirect(SP),
-                    0x7A => self.adc_hl(SP),
-                    0x7E => self.set_interrupt_mode(2),
-                    _ => panic!("{:#?}", Instruction::decode(self.opcode)),
-                }
+                self.opcode = self.read8(self.reg.pc.wrapping_add(1)) as u16;
+                self.inc_r();
+                let op = self.opcode as usize;
+                // Undefined ED opcodes execute as two NOPs
+                self.dispatch(
+                    opcodes::ED[op]
+                        .zip(Self::ED_EXEC[op])
+                        .or(Some((ED_NOP, |_, _| {}))),
+                    HL,
+                    0,
+                )
             }
-
-            0xEE => self.xri(),
-            0xEF => self.rst(0x0028),
-            0xF0 => self.ret_cond(!self.flags.sf),
-            0xF1 => self.pop(AF),
-            0xF2 => self.jp_cond(!self.flags.sf),
-            0xF3 => self.interrupt(false),
-            0xF4 => self.call_cond(0xF4, !self.flags.sf),
-            0xF5 => self.push(AF),
-            0xF6 => self.ori(),
-            0xF7 => self.rst(0x0020),
-            0xF8 => self.ret_cond(self.flags.sf),
-            0xF9 => self.sphl(),
-            0xFA => self.jp_cond(self.flags.sf),
-            0xFB => self.interrupt(true),
-            0xFC => self.call_cond(0xFC, self.flags.sf),
-            0xFD => {
-                self.opcode = self.read8(self.reg.pc + 1) as u16;
-                self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(1)) & 0x7f;
-                match self.opcode {
-                    0x09 => unimplemented!("{:04x}", self.opcode),
-                    0x19 => unimplemented!("{:04x}", self.opcode),
-                    0x21 => {
-                        self.reg.iy = self.read16(self.reg.pc + 2);
-                        self.adv_pc(4);
-                        self.adv_cycles(14);
-                    }
-                    0x22 => {
-                        self.write8(
-                            self.read8(self.reg.pc + 2) as u16,
-                            self.read16(self.reg.iy) as u8,
-                        );
-                        self.adv_pc(4);
-                        self.adv_cycles(20);
-                    }
-                    0x23 => self.inx(IY),
-                    0x24 => unimplemented!(),
-                    0x25 => unimplemented!(),
-                    0x26 => unimplemented!(),
-                    0x29 => unimplemented!(),
-                    0x2A => unimplemented!(),
-                    0x2B => self.dex(IY),
-                    0x2D => unimplemented!(),
-                    0x2C => unimplemented!(),
-                    0x2E => unimplemented!(),
-                    0xE1 => self.pop(IY),
-                    0xE5 => self.push(IY),
-                    0xE9 => {
-                        self.instruction.cycles = 8;
-                        self.jp(self.get_pair(IY))
-                    }
-                    0x66 => {
-                        let byte = self.read8(self.reg.pc + 2);
-                        let addr = self.reg.iy.wrapping_add(byte as u16);
-                        self.reg.h = self.read8(addr) as u8;
-                        self.adv_pc(3);
-                        self.adv_cycles(19);
-                    }
-                    0x7E => {
-                        // byte is the signed displacement byte
-                        let byte = self.read8(self.reg.pc + 2) as i8;
-                        let addr = self.reg.iy.wrapping_add(byte as u16);
-                        self.reg.a = self.read8(addr) as i8 as u8;
-                        self.adv_pc(3);
-                        self.adv_cycles(19);
-                    }
-                    _ => panic!("{:#?}", Instruction::decode(self.opcode)),
+            0xDD | 0xFD => {
+                let ir = if opcode == 0xDD { IX } else { IY };
+                self.adv_pc(1);
+                self.adv_cycles(4);
+                let op = self.read8(self.reg.pc) as usize;
+                if op != 0xCB && opcodes::INDEX[op].is_none() {
+                    // Opcodes without H, L or (HL) operands ignore the prefix. It executes as
+                    // a NOP of its own (so do runs of DD / FD), like EI it keeps interrupts
+                    // off until the next opcode has executed.
+                    self.int.ei_delay = true;
+                    return Ok(());
+                }
+                self.opcode = op as u16;
+                self.inc_r();
+                if op == 0xCB {
+                    // DD CB d op, the opcode follows the displacement. (IX+d) is worked out
+                    // first, its handlers take it from MEMPTR.
+                    self.operand_addr(ir);
+                    self.opcode = self.read8(self.reg.pc.wrapping_add(2)) as u16;
+                    let op = self.opcode as usize;
+                    self.dispatch(opcodes::INDEX_BIT[op].zip(Self::INDEX_BIT_EXEC[op]), ir, 1)
+                } else {
+                    self.dispatch(opcodes::INDEX[op].zip(Self::INDEX_EXEC[op]), ir, 1)
                 }
             }
-            0xFE => self.cp(),
-            0xFF => self.rst(0x0038),
-            _ => println!("Unknown opcode: {:04X}", self.opcode),
+            _ => {
+                let op = opcode as usize;
+                self.dispatch(opcodes::MAIN[op].zip(Self::MAIN_EXEC[op]), HL, 0)
+            }
+        }
+    }
+
+    // 8080 instructions are the unprefixed Z80 opcodes with 8080 timing
+    fn decode_8080(&mut self, opcode: u8) -> Result<(), CpuError> {
+        let op = opcodes::i8080_alias(opcode) as usize;
+        let cycles = I8080_CYCLES[op];
+        let conditional = op & 0xC7 == 0xC0 || op & 0xC7 == 0xC4;
+        let info = opcodes::MAIN[op].map(|info| OpcodeInfo {
+            cycles: if conditional { cycles + 6 } else { cycles },
+            alt_cycles: if conditional { cycles } else { 0 },
+            ..info
+        });
+        self.dispatch(info.zip(Self::MAIN_EXEC[op]), HL, 0)
+    }
+
+    // Executes an opcode table entry & advances PC & the cycle counter from its metadata.
+    // `prefix` bytes (4 T states each) have already been accounted for by the caller.
+    fn dispatch(
+        &mut self,
+        entry: Option<(OpcodeInfo, Handler<B>)>,
+        ir: Register,
+        prefix: u8,
+    ) -> Result<(), CpuError> {
+        let Some((info, handler)) = entry else {
+            // A hole in the opcode tables
+            let pc = self.reg.pc.wrapping_sub(prefix as u16);
+            let len = match (self.bus.read8(pc), self.bus.read8(pc.wrapping_add(1))) {
+                (0xDD | 0xFD, 0xCB) => 4,
+                (0xCB | 0xED | 0xDD | 0xFD, _) => 2,
+                _ => 1,
+            };
+            let bytes = (0..len).map(|i| self.bus.read8(pc.wrapping_add(i))).collect();
+            return Err(CpuError::UnknownOpcode { pc, bytes });
+        };
+        self.branched = false;
+        handler(self, ir);
+        let cycles = if self.branched || info.alt_cycles == 0 {
+            info.cycles
+        } else {
+            info.alt_cycles
+        };
+        self.adv_cycles((cycles - prefix * 4) as usize);
+        if !self.branched {
+            self.adv_pc((info.bytes - prefix) as u16);
+        }
+        Ok(())
+    }
+
+    // Sets PC for jumps, calls, returns & RST. Conditional instructions only call this when
+    // the branch is taken, which selects the taken timing of the opcode.
+    fn jump(&mut self, addr: u16) {
+        self.reg.prev_pc = self.reg.pc;
+        self.reg.pc = addr;
+        self.branched = true;
+    }
+
+    // Repeats a block instruction (LDIR, CPIR, INIR etc.) by leaving PC at its first byte
+    fn repeat(&mut self) {
+        self.branched = true;
+    }
+
+    // DDCB & FDCB rotates & shifts on (IX+d), decode leaves the address in MEMPTR. The result
+    // is also copied into `reg` unless it is HL.
+    fn rot_index(&mut self, op: u8, reg: Register) {
+        let addr = self.reg.memptr;
+        let result = self.rot(op, self.read8(addr));
+        self.write8(addr, result);
+        if reg != HL {
+            self.write_reg(reg, result);
+        }
+    }
+
+    // BIT n, (IX+d), X & Y are copies of the high byte of the indexed address
+    fn bit_index(&mut self, bit: u8) {
+        let addr = self.reg.memptr;
+        self.bit_flags(bit, self.read8(addr));
+        self.flags.yf = (addr >> 8) & 0x20 != 0;
+        self.flags.xf = (addr >> 8) & 0x08 != 0;
+    }
+
+    // RES n, (IX+d) & SET n, (IX+d), the result is also copied into `reg` unless it is HL
+    fn set_bit_index(&mut self, bit: u8, reg: Register, set: bool) {
+        let addr = self.reg.memptr;
+        let value = self.read8(addr);
+        let result = if set {
+            value | (1 << bit)
+        } else {
+            value & !(1 << bit)
+        };
+        self.write8(addr, result);
+        if reg != HL {
+            self.write_reg(reg, result);
         }
     }
 
@@ -2010,136 +1602,159 @@ impl Cpu {
         self.reg.r = 0;
         // Reset flag conditions
         self.flags.set(0xff);
-        self.int.mode = 1;
+        // The 8080 executes the instruction on the data bus like IM 0
+        self.int.mode = if self.i8080() { 0 } else { 1 };
         self.int.iff1 = false;
         self.int.iff2 = false;
+        self.int.ei_delay = false;
+        self.halted = false;
     }
 
-    // TODO interrupt handle
+    // HALT suspends execution until the next interrupt (or NMI) is accepted.
+    // PC is left pointing at the next instruction which becomes the interrupt return address.
     fn hlt(&mut self) {
-        if self.int.irq && self.int.int {
-            ::std::process::exit(1);
+        if self.debug {
+            println!("Halting CPU");
         }
-        eprintln!("Halting CPU");
-        self.adv_cycles(7);
-        ::std::process::exit(1);
+        self.halted = true;
     }
 
-    fn parity(&self, value: u8) -> bool {
-        let mut bits: u8 = 0;
-        for i in 0..8 {
-            bits += (value >> i) & 1;
-        }
-        (bits & 1) == 0
+    // Pushes a word onto the stack, high byte first like the bus does
+    fn push16(&mut self, value: u16) {
+        self.reg.sp = self.reg.sp.wrapping_sub(1);
+        self.write8(self.reg.sp, (value >> 8) as u8);
+        self.reg.sp = self.reg.sp.wrapping_sub(1);
+        self.write8(self.reg.sp, value as u8);
     }
 
-    // Borrowed from github.com/superzazu for debugging purposes
-    // returns if there was a carry between bit "bit_no" and "bit_no - 1" when
-    // executing "a + b + cy"
-    fn carry(&self, bit_no: u8, a: u16, b: u16) -> bool {
-        let result = a.wrapping_add(b);
-        let carry = result ^ a ^ b;
-        return bool::from(carry & (1 << bit_no) != 0);
-    }
-    fn carry_sub(&self, bit_no: u8, a: u16, b: u16) -> bool {
-        let result = a.wrapping_sub(b);
-        let carry = result ^ a ^ b;
-        return bool::from(carry & (1 << bit_no) != 0);
+    fn i8080(&self) -> bool {
+        self.model == CpuModel::I8080
     }
 
-    fn hf_add(&self, a: u8, b: u8) -> bool {
-        // (((a & 0xF) + (b & 0xF)) & 0x10) == 0x10
-        ((((a & 0xF) + (b & 0xF)) & 0x10) & (1 << 4)) != 0
-    }
-    fn hf_add_w(&self, a: u16, b: u16) -> bool {
-        // Check carry of bit 12
-        (((a & 0x0F00) + (b & 0x0F00) & 0x1000) & (1 << 12)) != 0
-    }
-    fn hf_sub_w(a: u16, b: u16) -> bool {
-        // ((((a as i16 & 0x0F00) + (b as i16 & 0x0F00)) & 0x1000) & (1 << 12)) != 0
-        (((a as i16 & 0x0F00) - (b as i16 & 0x0F00)) & 0x1000) < 0
+    fn parity(&self, value: u8) -> bool {
+        value.count_ones() & 1 == 0
     }
 
-    fn hf_sub(&self, a: u8, b: u8) -> bool {
-        (a as i8 & 0x0F) - (b as i8 & 0x0F) < 0
+    // Raises the maskable interrupt (INT) line, the data bus byte is requested from the bus
+    // with `Bus::int_ack` once the interrupt is accepted.
+    pub fn generate_interrupt(&mut self) {
+        self.int.irq = true;
     }
 
-    fn overflow(&mut self, a: u8, result: u8) -> bool {
-        // Overflow should be set if the 2-complement result does not fit the register
-        // Set overflow flag when A and the operand have the same sign
-        // and A and the result have different sign
-        let op = self.read8(self.reg.pc + 1);
-        ((a >> 7) == (op.wrapping_shl(7))) && ((a.wrapping_shr(7)) != (result.wrapping_shr(7)))
-    }
-    pub fn generate_interrupt(&mut self) {
+    // Raises a non maskable interrupt
+    pub fn generate_nmi(&mut self) {
         self.int.nmi_pending = true;
-        if self.io.port == 0 {
-            self.int.data = self.io.port;
-        }
     }
-    pub(crate) fn poll_interrupt(&mut self) {
+
+    // Checked between instructions. Returns true if an interrupt was accepted.
+    pub fn poll_interrupt(&mut self) -> Result<bool, CpuError> {
+        // The device may withdraw INT before the CPU gets to it
+        if self.bus.int_cleared() {
+            self.int.irq = false;
+        }
         // Accepting an NMI
         if self.int.nmi_pending {
             self.int.nmi_pending = false;
             self.int.iff1 = false;
-            self.reg.r = self.reg.r.wrapping_add(1);
+            self.int.ei_delay = false;
+            self.halted = false;
+            self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(1) & 0x7f);
+            self.push16(self.reg.pc);
+            self.reg.prev_pc = self.reg.pc;
+            self.reg.pc = 0x66;
+            self.reg.memptr = 0x66;
             self.adv_cycles(11);
-            self.call(0x66);
-            return;
+            return Ok(true);
         }
-        if (self.int.nmi_pending || self.int.irq) || self.int.iff1 {
-            self.int_pending = false;
-            self.int.iff1 = false;
-            self.int.iff2 = false;
-            self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(0) as u8 & 0x7f);
-
-            // Interrupt Mode 0 is the 8080 compatibility mode
-            // Most commonly the instruction executed on the bus is RST,
-            // but it can be any instruction (technically)
-            // The I register is not used for IM0
-            // TODO investigate interrupt processing
-            match self.int.mode {
-                0 => {
-                    if self.int.data != 0 {
-                        self.adv_cycles(11);
-                        if self.debug {
-                            println!("Servicing interrupt, mode 0");
-                        }
-                        self.decode(self.int.data as u16);
-                    }
+        // The instruction following EI is always executed before an interrupt is accepted
+        if self.int.ei_delay {
+            self.int.ei_delay = false;
+            return Ok(false);
+        }
+        if !(self.int.irq && self.int.iff1) {
+            return Ok(false);
+        }
+        // Unsupported interrupts fail before the device sees the acknowledge
+        let data = self.bus.int_data();
+        if self.int.mode > 2 {
+            return Err(CpuError::Unimplemented {
+                mnemonic: format!("IM {}", self.int.mode),
+            });
+        }
+        if self.int.mode == 0 && data & 0xC7 != 0xC7 {
+            // Operands of multi byte instructions would have to be read from the bus as well
+            let instruction = Instruction::decode(data as u16).unwrap_or_default();
+            if instruction.bytes != 1 {
+                let name = instruction.name.trim();
+                return Err(CpuError::Unimplemented {
+                    mnemonic: format!("IM 0 bus instruction {:02X} {}", data, name),
+                });
+            }
+        }
+        // The interrupting device puts the byte on the data bus during the acknowledge cycle
+        let data = self.bus.int_ack();
+
+        self.int.irq = false;
+        self.int.iff1 = false;
+        self.int.iff2 = false;
+        self.halted = false;
+        self.reg.r = (self.reg.r & 0x80) | (self.reg.r.wrapping_add(1) & 0x7f);
+
+        match self.int.mode {
+            0 => {
+                // Interrupt Mode 0 is the 8080 compatibility mode
+                // Most commonly the instruction executed on the bus is RST,
+                // but it can be any instruction (technically)
+                // The I register is not used for IM0
+                if self.debug {
+                    println!("Servicing interrupt, mode 0, bus: {:02x}", data);
                 }
-                1 => {
-                    // Mode 1, RST38h, regardless of bus value or I reg value.
-                    if self.debug {
-                        println!("Servicing interrupt, mode 1");
+                self.adv_cycles(2);
+                if data & 0xC7 == 0xC7 {
+                    self.push16(self.reg.pc);
+                    self.reg.prev_pc = self.reg.pc;
+                    self.reg.pc = (data & 0x38) as u16;
+                    self.reg.memptr = self.reg.pc;
+                    self.adv_cycles(11);
+                } else {
+                    // Single byte instructions only, PC is not advanced by the bus opcode.
+                    // Jumps & returns (JP (HL), RET etc.) keep the PC they set.
+                    let pc = self.reg.pc;
+                    self.decode(data)?;
+                    if !self.branched {
+                        self.reg.pc = pc;
                     }
-                    self.adv_cycles(13);
-                    self.rst(0x38);
                 }
-                2 => {
-                    // self.reg.i * 256 + busvalue
-                    if self.debug {
-                        println!("Servicing interrupt, mode 2");
-                    }
-                    self.adv_cycles(19);
-                    let addr =
-                        self.read16((self.reg.i).wrapping_shl(8) as u16) | self.int.vector as u16;
-                    let ret: u16 = self.reg.pc.wrapping_add(3);
-                    self.memory[self.reg.sp.wrapping_sub(1)] = (ret >> 8) as u8;
-                    self.memory[self.reg.sp.wrapping_sub(2)] = ret as u8;
-                    self.reg.sp = self.reg.sp.wrapping_sub(2);
-
-                    self.reg.prev_pc = self.reg.pc;
-                    self.reg.pc = addr;
+            }
+            1 => {
+                // Mode 1, RST38h, regardless of bus value or I reg value.
+                if self.debug {
+                    println!("Servicing interrupt, mode 1");
                 }
-                _ => panic!("Unhandled interrupt mode"),
+                self.push16(self.reg.pc);
+                self.reg.prev_pc = self.reg.pc;
+                self.reg.pc = 0x38;
+                self.reg.memptr = self.reg.pc;
+                self.adv_cycles(13);
+            }
+            _ => {
+                // Mode 2: the vector table entry is read from (I * 256 + bus value)
+                if self.debug {
+                    println!("Servicing interrupt, mode 2, bus: {:02x}", data);
+                }
+                let addr = self.read16((self.reg.i as u16) << 8 | data as u16);
+                self.push16(self.reg.pc);
+                self.reg.prev_pc = self.reg.pc;
+                self.reg.pc = addr;
+                self.reg.memptr = self.reg.pc;
+                self.adv_cycles(19);
             }
         }
+        Ok(true)
     }
 
     pub fn try_reset_cycles(&mut self) {
         if self.cycles < 25_600 {
-            return;
         } else {
             self.cycles = 0;
         }
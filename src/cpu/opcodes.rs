@@ -0,0 +1,1058 @@
+// The opcode tables. Every instruction is described exactly once: mnemonic, size in bytes,
+// T states (taken / not taken for conditional instructions) & the handler executing it.
+// The CPU dispatches through the handler tables and `Instruction::decode` (debug output,
+// disassembly) reads the metadata tables, so both always agree.
+//
+// Handlers don't advance PC or the cycle counter, `Cpu::dispatch` does that from the table.
+// Instructions setting PC themselves (jumps, calls, returns & repeating block instructions)
+// use `Cpu::jump` / `Cpu::repeat`, which also selects the taken timing.
+//
+// DD & FD prefixed opcodes share the INDEX & INDEX_BIT tables, mnemonics are written for IX
+// & the handlers receive the index register. Opcodes missing from INDEX execute as if there
+// was no prefix, missing ED opcodes are NOPs.
+use super::Cpu;
+use crate::bus::Bus;
+use crate::instruction_info::{Register, Register::*};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    pub name: &'static str,
+    pub bytes: u8,      // Including prefixes & the displacement byte
+    pub cycles: u8,     // Clock cycles (if branch taken)
+    pub alt_cycles: u8, // If not branch taken, 0 for unconditional instructions
+}
+
+// Undefined ED opcodes
+pub const ED_NOP: OpcodeInfo = OpcodeInfo {
+    name: "NOP*",
+    bytes: 2,
+    cycles: 8,
+    alt_cycles: 0,
+};
+
+// The register argument is HL for unprefixed opcodes and IX or IY for the index tables
+pub(crate) type Handler<B> = fn(&mut Cpu<B>, Register);
+
+// Builds the metadata table `$info` & the handler table `Cpu::$exec` from the same rows
+macro_rules! opcodes {
+    ($info:ident, $exec:ident; $($op:literal => $name:literal, $bytes:literal, $cycles:literal, $alt:literal, $handler:expr;)*) => {
+        pub static $info: [Option<OpcodeInfo>; 256] = {
+            let mut table = [None; 256];
+            $(table[$op] = Some(OpcodeInfo { name: $name, bytes: $bytes, cycles: $cycles, alt_cycles: $alt });)*
+            table
+        };
+
+        impl<B: Bus> Cpu<B> {
+            pub(crate) const $exec: [Option<Handler<B>>; 256] = {
+                let mut table: [Option<Handler<B>>; 256] = [None; 256];
+                $(table[$op] = Some($handler);)*
+                table
+            };
+        }
+    };
+}
+
+// IXH / IYH & IXL / IYL for the undocumented 8 bit index register opcodes
+fn hi(ir: Register) -> Register {
+    if ir == IX {
+        IXH
+    } else {
+        IYH
+    }
+}
+
+fn lo(ir: Register) -> Register {
+    if ir == IX {
+        IXL
+    } else {
+        IYL
+    }
+}
+
+// Unprefixed opcodes
+opcodes! {
+    MAIN, MAIN_EXEC;
+    0x00 => "NOP",                1,  4,  0, |c, _| c.nop();
+    0x01 => "LD BC, **",          3, 10,  0, |c, _| c.lxi(BC);
+    0x02 => "LD (BC), A",         1,  7,  0, |c, _| c.stax(BC);
+    0x03 => "INC BC",             1,  6,  0, |c, _| c.inx(BC);
+    0x04 => "INC B",              1,  4,  0, |c, _| c.inc(B);
+    0x05 => "DEC B",              1,  4,  0, |c, _| c.dec(B);
+    0x06 => "LD B, *",            2,  7,  0, |c, _| c.mvi(B);
+    0x07 => "RLCA",               1,  4,  0, |c, _| c.rlca();
+    0x08 => "EX AF, AF'",         1,  4,  0, |c, _| c.ex_af_af();
+    0x09 => "ADD HL, BC",         1, 11,  0, |c, _| c.add_hl(BC);
+    0x0A => "LD A, (BC)",         1,  7,  0, |c, _| c.ld_ex(BC);
+    0x0B => "DEC BC",             1,  6,  0, |c, _| c.dex(BC);
+    0x0C => "INC C",              1,  4,  0, |c, _| c.inc(C);
+    0x0D => "DEC C",              1,  4,  0, |c, _| c.dec(C);
+    0x0E => "LD C, *",            2,  7,  0, |c, _| c.mvi(C);
+    0x0F => "RRCA",               1,  4,  0, |c, _| c.rrca();
+    0x10 => "DJNZ *",             2, 13,  8, |c, _| c.djnz();
+    0x11 => "LD DE, **",          3, 10,  0, |c, _| c.lxi(DE);
+    0x12 => "LD (DE), A",         1,  7,  0, |c, _| c.stax(DE);
+    0x13 => "INC DE",             1,  6,  0, |c, _| c.inx(DE);
+    0x14 => "INC D",              1,  4,  0, |c, _| c.inc(D);
+    0x15 => "DEC D",              1,  4,  0, |c, _| c.dec(D);
+    0x16 => "LD D, *",            2,  7,  0, |c, _| c.mvi(D);
+    0x17 => "RLA",                1,  4,  0, |c, _| c.rla();
+    0x18 => "JR *",               2, 12,  0, |c, _| c.jr_cond(true);
+    0x19 => "ADD HL, DE",         1, 11,  0, |c, _| c.add_hl(DE);
+    0x1A => "LD A, (DE)",         1,  7,  0, |c, _| c.ld_ex(DE);
+    0x1B => "DEC DE",             1,  6,  0, |c, _| c.dex(DE);
+    0x1C => "INC E",              1,  4,  0, |c, _| c.inc(E);
+    0x1D => "DEC E",              1,  4,  0, |c, _| c.dec(E);
+    0x1E => "LD E, *",            2,  7,  0, |c, _| c.mvi(E);
+    0x1F => "RRA",                1,  4,  0, |c, _| c.rra();
+    0x20 => "JR NZ, *",           2, 12,  7, |c, _| c.jr_cond(!c.flags.zf);
+    0x21 => "LD HL, **",          3, 10,  0, |c, _| c.lxi(HL);
+    0x22 => "LD (**), HL",        3, 16,  0, |c, _| c.shld(HL);
+    0x23 => "INC HL",             1,  6,  0, |c, _| c.inx(HL);
+    0x24 => "INC H",              1,  4,  0, |c, _| c.inc(H);
+    0x25 => "DEC H",              1,  4,  0, |c, _| c.dec(H);
+    0x26 => "LD H, *",            2,  7,  0, |c, _| c.mvi(H);
+    0x27 => "DAA",                1,  4,  0, |c, _| c.daa();
+    0x28 => "JR Z, *",            2, 12,  7, |c, _| c.jr_cond(c.flags.zf);
+    0x29 => "ADD HL, HL",         1, 11,  0, |c, _| c.add_hl(HL);
+    0x2A => "LD HL, (**)",        3, 16,  0, |c, _| c.lhld(HL);
+    0x2B => "DEC HL",             1,  6,  0, |c, _| c.dex(HL);
+    0x2C => "INC L",              1,  4,  0, |c, _| c.inc(L);
+    0x2D => "DEC L",              1,  4,  0, |c, _| c.dec(L);
+    0x2E => "LD L, *",            2,  7,  0, |c, _| c.mvi(L);
+    0x2F => "CPL",                1,  4,  0, |c, _| c.cpl();
+    0x30 => "JR NC, *",           2, 12,  7, |c, _| c.jr_cond(!c.flags.cf);
+    0x31 => "LD SP, **",          3, 10,  0, |c, _| c.lxi(SP);
+    0x32 => "LD (**), A",         3, 13,  0, |c, _| c.sta();
+    0x33 => "INC SP",             1,  6,  0, |c, _| c.inx(SP);
+    0x34 => "INC (HL)",           1, 11,  0, |c, _| c.inc(HL);
+    0x35 => "DEC (HL)",           1, 11,  0, |c, _| c.dec(HL);
+    0x36 => "LD (HL), *",         2, 10,  0, |c, _| c.mvi(HL);
+    0x37 => "SCF",                1,  4,  0, |c, _| c.scf();
+    0x38 => "JR C, *",            2, 12,  7, |c, _| c.jr_cond(c.flags.cf);
+    0x39 => "ADD HL, SP",         1, 11,  0, |c, _| c.add_hl(SP);
+    0x3A => "LD A, (**)",         3, 13,  0, |c, _| c.lda_im();
+    0x3B => "DEC SP",             1,  6,  0, |c, _| c.dex(SP);
+    0x3C => "INC A",              1,  4,  0, |c, _| c.inc(A);
+    0x3D => "DEC A",              1,  4,  0, |c, _| c.dec(A);
+    0x3E => "LD A, *",            2,  7,  0, |c, _| c.mvi(A);
+    0x3F => "CCF",                1,  4,  0, |c, _| c.ccf();
+    0x40 => "LD B, B",            1,  4,  0, |c, _| c.ld(B, B);
+    0x41 => "LD B, C",            1,  4,  0, |c, _| c.ld(B, C);
+    0x42 => "LD B, D",            1,  4,  0, |c, _| c.ld(B, D);
+    0x43 => "LD B, E",            1,  4,  0, |c, _| c.ld(B, E);
+    0x44 => "LD B, H",            1,  4,  0, |c, _| c.ld(B, H);
+    0x45 => "LD B, L",            1,  4,  0, |c, _| c.ld(B, L);
+    0x46 => "LD B, (HL)",         1,  7,  0, |c, _| c.ld(B, HL);
+    0x47 => "LD B, A",            1,  4,  0, |c, _| c.ld(B, A);
+    0x48 => "LD C, B",            1,  4,  0, |c, _| c.ld(C, B);
+    0x49 => "LD C, C",            1,  4,  0, |c, _| c.ld(C, C);
+    0x4A => "LD C, D",            1,  4,  0, |c, _| c.ld(C, D);
+    0x4B => "LD C, E",            1,  4,  0, |c, _| c.ld(C, E);
+    0x4C => "LD C, H",            1,  4,  0, |c, _| c.ld(C, H);
+    0x4D => "LD C, L",            1,  4,  0, |c, _| c.ld(C, L);
+    0x4E => "LD C, (HL)",         1,  7,  0, |c, _| c.ld(C, HL);
+    0x4F => "LD C, A",            1,  4,  0, |c, _| c.ld(C, A);
+    0x50 => "LD D, B",            1,  4,  0, |c, _| c.ld(D, B);
+    0x51 => "LD D, C",            1,  4,  0, |c, _| c.ld(D, C);
+    0x52 => "LD D, D",            1,  4,  0, |c, _| c.ld(D, D);
+    0x53 => "LD D, E",            1,  4,  0, |c, _| c.ld(D, E);
+    0x54 => "LD D, H",            1,  4,  0, |c, _| c.ld(D, H);
+    0x55 => "LD D, L",            1,  4,  0, |c, _| c.ld(D, L);
+    0x56 => "LD D, (HL)",         1,  7,  0, |c, _| c.ld(D, HL);
+    0x57 => "LD D, A",            1,  4,  0, |c, _| c.ld(D, A);
+    0x58 => "LD E, B",            1,  4,  0, |c, _| c.ld(E, B);
+    0x59 => "LD E, C",            1,  4,  0, |c, _| c.ld(E, C);
+    0x5A => "LD E, D",            1,  4,  0, |c, _| c.ld(E, D);
+    0x5B => "LD E, E",            1,  4,  0, |c, _| c.ld(E, E);
+    0x5C => "LD E, H",            1,  4,  0, |c, _| c.ld(E, H);
+    0x5D => "LD E, L",            1,  4,  0, |c, _| c.ld(E, L);
+    0x5E => "LD E, (HL)",         1,  7,  0, |c, _| c.ld(E, HL);
+    0x5F => "LD E, A",            1,  4,  0, |c, _| c.ld(E, A);
+    0x60 => "LD H, B",            1,  4,  0, |c, _| c.ld(H, B);
+    0x61 => "LD H, C",            1,  4,  0, |c, _| c.ld(H, C);
+    0x62 => "LD H, D",            1,  4,  0, |c, _| c.ld(H, D);
+    0x63 => "LD H, E",            1,  4,  0, |c, _| c.ld(H, E);
+    0x64 => "LD H, H",            1,  4,  0, |c, _| c.ld(H, H);
+    0x65 => "LD H, L",            1,  4,  0, |c, _| c.ld(H, L);
+    0x66 => "LD H, (HL)",         1,  7,  0, |c, _| c.ld(H, HL);
+    0x67 => "LD H, A",            1,  4,  0, |c, _| c.ld(H, A);
+    0x68 => "LD L, B",            1,  4,  0, |c, _| c.ld(L, B);
+    0x69 => "LD L, C",            1,  4,  0, |c, _| c.ld(L, C);
+    0x6A => "LD L, D",            1,  4,  0, |c, _| c.ld(L, D);
+    0x6B => "LD L, E",            1,  4,  0, |c, _| c.ld(L, E);
+    0x6C => "LD L, H",            1,  4,  0, |c, _| c.ld(L, H);
+    0x6D => "LD L, L",            1,  4,  0, |c, _| c.ld(L, L);
+    0x6E => "LD L, (HL)",         1,  7,  0, |c, _| c.ld(L, HL);
+    0x6F => "LD L, A",            1,  4,  0, |c, _| c.ld(L, A);
+    0x70 => "LD (HL), B",         1,  7,  0, |c, _| c.ld(HL, B);
+    0x71 => "LD (HL), C",         1,  7,  0, |c, _| c.ld(HL, C);
+    0x72 => "LD (HL), D",         1,  7,  0, |c, _| c.ld(HL, D);
+    0x73 => "LD (HL), E",         1,  7,  0, |c, _| c.ld(HL, E);
+    0x74 => "LD (HL), H",         1,  7,  0, |c, _| c.ld(HL, H);
+    0x75 => "LD (HL), L",         1,  7,  0, |c, _| c.ld(HL, L);
+    0x76 => "HALT",               1,  4,  0, |c, _| c.hlt();
+    0x77 => "LD (HL), A",         1,  7,  0, |c, _| c.ld(HL, A);
+    0x78 => "LD A, B",            1,  4,  0, |c, _| c.ld(A, B);
+    0x79 => "LD A, C",            1,  4,  0, |c, _| c.ld(A, C);
+    0x7A => "LD A, D",            1,  4,  0, |c, _| c.ld(A, D);
+    0x7B => "LD A, E",            1,  4,  0, |c, _| c.ld(A, E);
+    0x7C => "LD A, H",            1,  4,  0, |c, _| c.ld(A, H);
+    0x7D => "LD A, L",            1,  4,  0, |c, _| c.ld(A, L);
+    0x7E => "LD A, (HL)",         1,  7,  0, |c, _| c.ld(A, HL);
+    0x7F => "LD A, A",            1,  4,  0, |c, _| c.ld(A, A);
+    0x80 => "ADD A, B",           1,  4,  0, |c, _| c.add(B);
+    0x81 => "ADD A, C",           1,  4,  0, |c, _| c.add(C);
+    0x82 => "ADD A, D",           1,  4,  0, |c, _| c.add(D);
+    0x83 => "ADD A, E",           1,  4,  0, |c, _| c.add(E);
+    0x84 => "ADD A, H",           1,  4,  0, |c, _| c.add(H);
+    0x85 => "ADD A, L",           1,  4,  0, |c, _| c.add(L);
+    0x86 => "ADD A, (HL)",        1,  7,  0, |c, _| c.add(HL);
+    0x87 => "ADD A, A",           1,  4,  0, |c, _| c.add(A);
+    0x88 => "ADC A, B",           1,  4,  0, |c, _| c.adc(B);
+    0x89 => "ADC A, C",           1,  4,  0, |c, _| c.adc(C);
+    0x8A => "ADC A, D",           1,  4,  0, |c, _| c.adc(D);
+    0x8B => "ADC A, E",           1,  4,  0, |c, _| c.adc(E);
+    0x8C => "ADC A, H",           1,  4,  0, |c, _| c.adc(H);
+    0x8D => "ADC A, L",           1,  4,  0, |c, _| c.adc(L);
+    0x8E => "ADC A, (HL)",        1,  7,  0, |c, _| c.adc(HL);
+    0x8F => "ADC A, A",           1,  4,  0, |c, _| c.adc(A);
+    0x90 => "SUB B",              1,  4,  0, |c, _| c.sub(B);
+    0x91 => "SUB C",              1,  4,  0, |c, _| c.sub(C);
+    0x92 => "SUB D",              1,  4,  0, |c, _| c.sub(D);
+    0x93 => "SUB E",              1,  4,  0, |c, _| c.sub(E);
+    0x94 => "SUB H",              1,  4,  0, |c, _| c.sub(H);
+    0x95 => "SUB L",              1,  4,  0, |c, _| c.sub(L);
+    0x96 => "SUB (HL)",           1,  7,  0, |c, _| c.sub(HL);
+    0x97 => "SUB A",              1,  4,  0, |c, _| c.sub(A);
+    0x98 => "SBC A, B",           1,  4,  0, |c, _| c.sbc(B);
+    0x99 => "SBC A, C",           1,  4,  0, |c, _| c.sbc(C);
+    0x9A => "SBC A, D",           1,  4,  0, |c, _| c.sbc(D);
+    0x9B => "SBC A, E",           1,  4,  0, |c, _| c.sbc(E);
+    0x9C => "SBC A, H",           1,  4,  0, |c, _| c.sbc(H);
+    0x9D => "SBC A, L",           1,  4,  0, |c, _| c.sbc(L);
+    0x9E => "SBC A, (HL)",        1,  7,  0, |c, _| c.sbc(HL);
+    0x9F => "SBC A, A",           1,  4,  0, |c, _| c.sbc(A);
+    0xA0 => "AND B",              1,  4,  0, |c, _| c.ana(B);
+    0xA1 => "AND C",              1,  4,  0, |c, _| c.ana(C);
+    0xA2 => "AND D",              1,  4,  0, |c, _| c.ana(D);
+    0xA3 => "AND E",              1,  4,  0, |c, _| c.ana(E);
+    0xA4 => "AND H",              1,  4,  0, |c, _| c.ana(H);
+    0xA5 => "AND L",              1,  4,  0, |c, _| c.ana(L);
+    0xA6 => "AND (HL)",           1,  7,  0, |c, _| c.ana(HL);
+    0xA7 => "AND A",              1,  4,  0, |c, _| c.ana(A);
+    0xA8 => "XOR B",              1,  4,  0, |c, _| c.xra(B);
+    0xA9 => "XOR C",              1,  4,  0, |c, _| c.xra(C);
+    0xAA => "XOR D",              1,  4,  0, |c, _| c.xra(D);
+    0xAB => "XOR E",              1,  4,  0, |c, _| c.xra(E);
+    0xAC => "XOR H",              1,  4,  0, |c, _| c.xra(H);
+    0xAD => "XOR L",              1,  4,  0, |c, _| c.xra(L);
+    0xAE => "XOR (HL)",           1,  7,  0, |c, _| c.xra(HL);
+    0xAF => "XOR A",              1,  4,  0, |c, _| c.xra(A);
+    0xB0 => "OR B",               1,  4,  0, |c, _| c.ora(B);
+    0xB1 => "OR C",               1,  4,  0, |c, _| c.ora(C);
+    0xB2 => "OR D",               1,  4,  0, |c, _| c.ora(D);
+    0xB3 => "OR E",               1,  4,  0, |c, _| c.ora(E);
+    0xB4 => "OR H",               1,  4,  0, |c, _| c.ora(H);
+    0xB5 => "OR L",               1,  4,  0, |c, _| c.ora(L);
+    0xB6 => "OR (HL)",            1,  7,  0, |c, _| c.ora(HL);
+    0xB7 => "OR A",               1,  4,  0, |c, _| c.ora(A);
+    0xB8 => "CP B",               1,  4,  0, |c, _| c.cmp(B);
+    0xB9 => "CP C",               1,  4,  0, |c, _| c.cmp(C);
+    0xBA => "CP D",               1,  4,  0, |c, _| c.cmp(D);
+    0xBB => "CP E",               1,  4,  0, |c, _| c.cmp(E);
+    0xBC => "CP H",               1,  4,  0, |c, _| c.cmp(H);
+    0xBD => "CP L",               1,  4,  0, |c, _| c.cmp(L);
+    0xBE => "CP (HL)",            1,  7,  0, |c, _| c.cmp(HL);
+    0xBF => "CP A",               1,  4,  0, |c, _| c.cmp(A);
+    0xC0 => "RET NZ",             1, 11,  5, |c, _| c.ret_cond(!c.flags.zf);
+    0xC1 => "POP BC",             1, 10,  0, |c, _| c.pop(BC);
+    0xC2 => "JP NZ, **",          3, 10, 10, |c, _| c.jp_cond(!c.flags.zf);
+    0xC3 => "JP **",              3, 10,  0, |c, _| c.jp_cond(true);
+    0xC4 => "CALL NZ, **",        3, 17, 10, |c, _| c.call_cond(!c.flags.zf);
+    0xC5 => "PUSH BC",            1, 11,  0, |c, _| c.push(BC);
+    0xC6 => "ADD A, *",           2,  7,  0, |c, _| c.adi();
+    0xC7 => "RST 00H",            1, 11,  0, |c, _| c.rst(0x0000);
+    0xC8 => "RET Z",              1, 11,  5, |c, _| c.ret_cond(c.flags.zf);
+    0xC9 => "RET",                1, 10,  0, |c, _| c.ret();
+    0xCA => "JP Z, **",           3, 10, 10, |c, _| c.jp_cond(c.flags.zf);
+    0xCC => "CALL Z, **",         3, 17, 10, |c, _| c.call_cond(c.flags.zf);
+    0xCD => "CALL **",            3, 17,  0, |c, _| c.call();
+    0xCE => "ADC A, *",           2,  7,  0, |c, _| c.adc_im();
+    0xCF => "RST 08H",            1, 11,  0, |c, _| c.rst(0x0008);
+    0xD0 => "RET NC",             1, 11,  5, |c, _| c.ret_cond(!c.flags.cf);
+    0xD1 => "POP DE",             1, 10,  0, |c, _| c.pop(DE);
+    0xD2 => "JP NC, **",          3, 10, 10, |c, _| c.jp_cond(!c.flags.cf);
+    0xD3 => "OUT (*), A",         2, 11,  0, |c, _| c.out(A);
+    0xD4 => "CALL NC, **",        3, 17, 10, |c, _| c.call_cond(!c.flags.cf);
+    0xD5 => "PUSH DE",            1, 11,  0, |c, _| c.push(DE);
+    0xD6 => "SUB *",              2,  7,  0, |c, _| c.sui();
+    0xD7 => "RST 10H",            1, 11,  0, |c, _| c.rst(0x0010);
+    0xD8 => "RET C",              1, 11,  5, |c, _| c.ret_cond(c.flags.cf);
+    0xD9 => "EXX",                1,  4,  0, |c, _| c.exx();
+    0xDA => "JP C, **",           3, 10, 10, |c, _| c.jp_cond(c.flags.cf);
+    0xDB => "IN A, (*)",          2, 11,  0, |c, _| c.in_a();
+    0xDC => "CALL C, **",         3, 17, 10, |c, _| c.call_cond(c.flags.cf);
+    0xDE => "SBC A, *",           2,  7,  0, |c, _| c.sbi();
+    0xDF => "RST 18H",            1, 11,  0, |c, _| c.rst(0x0018);
+    0xE0 => "RET PO",             1, 11,  5, |c, _| c.ret_cond(!c.flags.pf);
+    0xE1 => "POP HL",             1, 10,  0, |c, _| c.pop(HL);
+    0xE2 => "JP PO, **",          3, 10, 10, |c, _| c.jp_cond(!c.flags.pf);
+    0xE3 => "EX (SP), HL",        1, 19,  0, |c, _| c.xthl(HL);
+    0xE4 => "CALL PO, **",        3, 17, 10, |c, _| c.call_cond(!c.flags.pf);
+    0xE5 => "PUSH HL",            1, 11,  0, |c, _| c.push(HL);
+    0xE6 => "AND *",              2,  7,  0, |c, _| c.ani();
+    0xE7 => "RST 20H",            1, 11,  0, |c, _| c.rst(0x0020);
+    0xE8 => "RET PE",             1, 11,  5, |c, _| c.ret_cond(c.flags.pf);
+    0xE9 => "JP (HL)",            1,  4,  0, |c, _| c.pchl(HL);
+    0xEA => "JP PE, **",          3, 10, 10, |c, _| c.jp_cond(c.flags.pf);
+    0xEB => "EX DE, HL",          1,  4,  0, |c, _| c.ex_de_hl();
+    0xEC => "CALL PE, **",        3, 17, 10, |c, _| c.call_cond(c.flags.pf);
+    0xEE => "XOR *",              2,  7,  0, |c, _| c.xri();
+    0xEF => "RST 28H",            1, 11,  0, |c, _| c.rst(0x0028);
+    0xF0 => "RET P",              1, 11,  5, |c, _| c.ret_cond(!c.flags.sf);
+    0xF1 => "POP AF",             1, 10,  0, |c, _| c.pop(AF);
+    0xF2 => "JP P, **",           3, 10, 10, |c, _| c.jp_cond(!c.flags.sf);
+    0xF3 => "DI",                 1,  4,  0, |c, _| c.interrupt(false);
+    0xF4 => "CALL P, **",         3, 17, 10, |c, _| c.call_cond(!c.flags.sf);
+    0xF5 => "PUSH AF",            1, 11,  0, |c, _| c.push(AF);
+    0xF6 => "OR *",               2,  7,  0, |c, _| c.ori();
+    0xF7 => "RST 30H",            1, 11,  0, |c, _| c.rst(0x0030);
+    0xF8 => "RET M",              1, 11,  5, |c, _| c.ret_cond(c.flags.sf);
+    0xF9 => "LD SP, HL",          1,  6,  0, |c, _| c.sphl(HL);
+    0xFA => "JP M, **",           3, 10, 10, |c, _| c.jp_cond(c.flags.sf);
+    0xFB => "EI",                 1,  4,  0, |c, _| c.interrupt(true);
+    0xFC => "CALL M, **",         3, 17, 10, |c, _| c.call_cond(c.flags.sf);
+    0xFE => "CP *",               2,  7,  0, |c, _| c.cp();
+    0xFF => "RST 38H",            1, 11,  0, |c, _| c.rst(0x0038);
+}
+
+// CB prefixed rotates, shifts & bit operations, (HL) takes 7 extra T states
+opcodes! {
+    CB, CB_EXEC;
+    0x00 => "RLC B",              2,  8,  0, |c, _| c.rot_reg(0, B);
+    0x01 => "RLC C",              2,  8,  0, |c, _| c.rot_reg(0, C);
+    0x02 => "RLC D",              2,  8,  0, |c, _| c.rot_reg(0, D);
+    0x03 => "RLC E",              2,  8,  0, |c, _| c.rot_reg(0, E);
+    0x04 => "RLC H",              2,  8,  0, |c, _| c.rot_reg(0, H);
+    0x05 => "RLC L",              2,  8,  0, |c, _| c.rot_reg(0, L);
+    0x06 => "RLC (HL)",           2, 15,  0, |c, _| c.rot_reg(0, HL);
+    0x07 => "RLC A",              2,  8,  0, |c, _| c.rot_reg(0, A);
+    0x08 => "RRC B",              2,  8,  0, |c, _| c.rot_reg(1, B);
+    0x09 => "RRC C",              2,  8,  0, |c, _| c.rot_reg(1, C);
+    0x0A => "RRC D",              2,  8,  0, |c, _| c.rot_reg(1, D);
+    0x0B => "RRC E",              2,  8,  0, |c, _| c.rot_reg(1, E);
+    0x0C => "RRC H",              2,  8,  0, |c, _| c.rot_reg(1, H);
+    0x0D => "RRC L",              2,  8,  0, |c, _| c.rot_reg(1, L);
+    0x0E => "RRC (HL)",           2, 15,  0, |c, _| c.rot_reg(1, HL);
+    0x0F => "RRC A",              2,  8,  0, |c, _| c.rot_reg(1, A);
+    0x10 => "RL B",               2,  8,  0, |c, _| c.rot_reg(2, B);
+    0x11 => "RL C",               2,  8,  0, |c, _| c.rot_reg(2, C);
+    0x12 => "RL D",               2,  8,  0, |c, _| c.rot_reg(2, D);
+    0x13 => "RL E",               2,  8,  0, |c, _| c.rot_reg(2, E);
+    0x14 => "RL H",               2,  8,  0, |c, _| c.rot_reg(2, H);
+    0x15 => "RL L",               2,  8,  0, |c, _| c.rot_reg(2, L);
+    0x16 => "RL (HL)",            2, 15,  0, |c, _| c.rot_reg(2, HL);
+    0x17 => "RL A",               2,  8,  0, |c, _| c.rot_reg(2, A);
+    0x18 => "RR B",               2,  8,  0, |c, _| c.rot_reg(3, B);
+    0x19 => "RR C",               2,  8,  0, |c, _| c.rot_reg(3, C);
+    0x1A => "RR D",               2,  8,  0, |c, _| c.rot_reg(3, D);
+    0x1B => "RR E",               2,  8,  0, |c, _| c.rot_reg(3, E);
+    0x1C => "RR H",               2,  8,  0, |c, _| c.rot_reg(3, H);
+    0x1D => "RR L",               2,  8,  0, |c, _| c.rot_reg(3, L);
+    0x1E => "RR (HL)",            2, 15,  0, |c, _| c.rot_reg(3, HL);
+    0x1F => "RR A",               2,  8,  0, |c, _| c.rot_reg(3, A);
+    0x20 => "SLA B",              2,  8,  0, |c, _| c.rot_reg(4, B);
+    0x21 => "SLA C",              2,  8,  0, |c, _| c.rot_reg(4, C);
+    0x22 => "SLA D",              2,  8,  0, |c, _| c.rot_reg(4, D);
+    0x23 => "SLA E",              2,  8,  0, |c, _| c.rot_reg(4, E);
+    0x24 => "SLA H",              2,  8,  0, |c, _| c.rot_reg(4, H);
+    0x25 => "SLA L",              2,  8,  0, |c, _| c.rot_reg(4, L);
+    0x26 => "SLA (HL)",           2, 15,  0, |c, _| c.rot_reg(4, HL);
+    0x27 => "SLA A",              2,  8,  0, |c, _| c.rot_reg(4, A);
+    0x28 => "SRA B",              2,  8,  0, |c, _| c.rot_reg(5, B);
+    0x29 => "SRA C",              2,  8,  0, |c, _| c.rot_reg(5, C);
+    0x2A => "SRA D",              2,  8,  0, |c, _| c.rot_reg(5, D);
+    0x2B => "SRA E",              2,  8,  0, |c, _| c.rot_reg(5, E);
+    0x2C => "SRA H",              2,  8,  0, |c, _| c.rot_reg(5, H);
+    0x2D => "SRA L",              2,  8,  0, |c, _| c.rot_reg(5, L);
+    0x2E => "SRA (HL)",           2, 15,  0, |c, _| c.rot_reg(5, HL);
+    0x2F => "SRA A",              2,  8,  0, |c, _| c.rot_reg(5, A);
+    0x30 => "SLL B",              2,  8,  0, |c, _| c.rot_reg(6, B);
+    0x31 => "SLL C",              2,  8,  0, |c, _| c.rot_reg(6, C);
+    0x32 => "SLL D",              2,  8,  0, |c, _| c.rot_reg(6, D);
+    0x33 => "SLL E",              2,  8,  0, |c, _| c.rot_reg(6, E);
+    0x34 => "SLL H",              2,  8,  0, |c, _| c.rot_reg(6, H);
+    0x35 => "SLL L",              2,  8,  0, |c, _| c.rot_reg(6, L);
+    0x36 => "SLL (HL)",           2, 15,  0, |c, _| c.rot_reg(6, HL);
+    0x37 => "SLL A",              2,  8,  0, |c, _| c.rot_reg(6, A);
+    0x38 => "SRL B",              2,  8,  0, |c, _| c.rot_reg(7, B);
+    0x39 => "SRL C",              2,  8,  0, |c, _| c.rot_reg(7, C);
+    0x3A => "SRL D",              2,  8,  0, |c, _| c.rot_reg(7, D);
+    0x3B => "SRL E",              2,  8,  0, |c, _| c.rot_reg(7, E);
+    0x3C => "SRL H",              2,  8,  0, |c, _| c.rot_reg(7, H);
+    0x3D => "SRL L",              2,  8,  0, |c, _| c.rot_reg(7, L);
+    0x3E => "SRL (HL)",           2, 15,  0, |c, _| c.rot_reg(7, HL);
+    0x3F => "SRL A",              2,  8,  0, |c, _| c.rot_reg(7, A);
+    0x40 => "BIT 0, B",           2,  8,  0, |c, _| c.bit(0, B);
+    0x41 => "BIT 0, C",           2,  8,  0, |c, _| c.bit(0, C);
+    0x42 => "BIT 0, D",           2,  8,  0, |c, _| c.bit(0, D);
+    0x43 => "BIT 0, E",           2,  8,  0, |c, _| c.bit(0, E);
+    0x44 => "BIT 0, H",           2,  8,  0, |c, _| c.bit(0, H);
+    0x45 => "BIT 0, L",           2,  8,  0, |c, _| c.bit(0, L);
+    0x46 => "BIT 0, (HL)",        2, 12,  0, |c, _| c.bit(0, HL);
+    0x47 => "BIT 0, A",           2,  8,  0, |c, _| c.bit(0, A);
+    0x48 => "BIT 1, B",           2,  8,  0, |c, _| c.bit(1, B);
+    0x49 => "BIT 1, C",           2,  8,  0, |c, _| c.bit(1, C);
+    0x4A => "BIT 1, D",           2,  8,  0, |c, _| c.bit(1, D);
+    0x4B => "BIT 1, E",           2,  8,  0, |c, _| c.bit(1, E);
+    0x4C => "BIT 1, H",           2,  8,  0, |c, _| c.bit(1, H);
+    0x4D => "BIT 1, L",           2,  8,  0, |c, _| c.bit(1, L);
+    0x4E => "BIT 1, (HL)",        2, 12,  0, |c, _| c.bit(1, HL);
+    0x4F => "BIT 1, A",           2,  8,  0, |c, _| c.bit(1, A);
+    0x50 => "BIT 2, B",           2,  8,  0, |c, _| c.bit(2, B);
+    0x51 => "BIT 2, C",           2,  8,  0, |c, _| c.bit(2, C);
+    0x52 => "BIT 2, D",           2,  8,  0, |c, _| c.bit(2, D);
+    0x53 => "BIT 2, E",           2,  8,  0, |c, _| c.bit(2, E);
+    0x54 => "BIT 2, H",           2,  8,  0, |c, _| c.bit(2, H);
+    0x55 => "BIT 2, L",           2,  8,  0, |c, _| c.bit(2, L);
+    0x56 => "BIT 2, (HL)",        2, 12,  0, |c, _| c.bit(2, HL);
+    0x57 => "BIT 2, A",           2,  8,  0, |c, _| c.bit(2, A);
+    0x58 => "BIT 3, B",           2,  8,  0, |c, _| c.bit(3, B);
+    0x59 => "BIT 3, C",           2,  8,  0, |c, _| c.bit(3, C);
+    0x5A => "BIT 3, D",           2,  8,  0, |c, _| c.bit(3, D);
+    0x5B => "BIT 3, E",           2,  8,  0, |c, _| c.bit(3, E);
+    0x5C => "BIT 3, H",           2,  8,  0, |c, _| c.bit(3, H);
+    0x5D => "BIT 3, L",           2,  8,  0, |c, _| c.bit(3, L);
+    0x5E => "BIT 3, (HL)",        2, 12,  0, |c, _| c.bit(3, HL);
+    0x5F => "BIT 3, A",           2,  8,  0, |c, _| c.bit(3, A);
+    0x60 => "BIT 4, B",           2,  8,  0, |c, _| c.bit(4, B);
+    0x61 => "BIT 4, C",           2,  8,  0, |c, _| c.bit(4, C);
+    0x62 => "BIT 4, D",           2,  8,  0, |c, _| c.bit(4, D);
+    0x63 => "BIT 4, E",           2,  8,  0, |c, _| c.bit(4, E);
+    0x64 => "BIT 4, H",           2,  8,  0, |c, _| c.bit(4, H);
+    0x65 => "BIT 4, L",           2,  8,  0, |c, _| c.bit(4, L);
+    0x66 => "BIT 4, (HL)",        2, 12,  0, |c, _| c.bit(4, HL);
+    0x67 => "BIT 4, A",           2,  8,  0, |c, _| c.bit(4, A);
+    0x68 => "BIT 5, B",           2,  8,  0, |c, _| c.bit(5, B);
+    0x69 => "BIT 5, C",           2,  8,  0, |c, _| c.bit(5, C);
+    0x6A => "BIT 5, D",           2,  8,  0, |c, _| c.bit(5, D);
+    0x6B => "BIT 5, E",           2,  8,  0, |c, _| c.bit(5, E);
+    0x6C => "BIT 5, H",           2,  8,  0, |c, _| c.bit(5, H);
+    0x6D => "BIT 5, L",           2,  8,  0, |c, _| c.bit(5, L);
+    0x6E => "BIT 5, (HL)",        2, 12,  0, |c, _| c.bit(5, HL);
+    0x6F => "BIT 5, A",           2,  8,  0, |c, _| c.bit(5, A);
+    0x70 => "BIT 6, B",           2,  8,  0, |c, _| c.bit(6, B);
+    0x71 => "BIT 6, C",           2,  8,  0, |c, _| c.bit(6, C);
+    0x72 => "BIT 6, D",           2,  8,  0, |c, _| c.bit(6, D);
+    0x73 => "BIT 6, E",           2,  8,  0, |c, _| c.bit(6, E);
+    0x74 => "BIT 6, H",           2,  8,  0, |c, _| c.bit(6, H);
+    0x75 => "BIT 6, L",           2,  8,  0, |c, _| c.bit(6, L);
+    0x76 => "BIT 6, (HL)",        2, 12,  0, |c, _| c.bit(6, HL);
+    0x77 => "BIT 6, A",           2,  8,  0, |c, _| c.bit(6, A);
+    0x78 => "BIT 7, B",           2,  8,  0, |c, _| c.bit(7, B);
+    0x79 => "BIT 7, C",           2,  8,  0, |c, _| c.bit(7, C);
+    0x7A => "BIT 7, D",           2,  8,  0, |c, _| c.bit(7, D);
+    0x7B => "BIT 7, E",           2,  8,  0, |c, _| c.bit(7, E);
+    0x7C => "BIT 7, H",           2,  8,  0, |c, _| c.bit(7, H);
+    0x7D => "BIT 7, L",           2,  8,  0, |c, _| c.bit(7, L);
+    0x7E => "BIT 7, (HL)",        2, 12,  0, |c, _| c.bit(7, HL);
+    0x7F => "BIT 7, A",           2,  8,  0, |c, _| c.bit(7, A);
+    0x80 => "RES 0, B",           2,  8,  0, |c, _| c.set_bit(0, B, false);
+    0x81 => "RES 0, C",           2,  8,  0, |c, _| c.set_bit(0, C, false);
+    0x82 => "RES 0, D",           2,  8,  0, |c, _| c.set_bit(0, D, false);
+    0x83 => "RES 0, E",           2,  8,  0, |c, _| c.set_bit(0, E, false);
+    0x84 => "RES 0, H",           2,  8,  0, |c, _| c.set_bit(0, H, false);
+    0x85 => "RES 0, L",           2,  8,  0, |c, _| c.set_bit(0, L, false);
+    0x86 => "RES 0, (HL)",        2, 15,  0, |c, _| c.set_bit(0, HL, false);
+    0x87 => "RES 0, A",           2,  8,  0, |c, _| c.set_bit(0, A, false);
+    0x88 => "RES 1, B",           2,  8,  0, |c, _| c.set_bit(1, B, false);
+    0x89 => "RES 1, C",           2,  8,  0, |c, _| c.set_bit(1, C, false);
+    0x8A => "RES 1, D",           2,  8,  0, |c, _| c.set_bit(1, D, false);
+    0x8B => "RES 1, E",           2,  8,  0, |c, _| c.set_bit(1, E, false);
+    0x8C => "RES 1, H",           2,  8,  0, |c, _| c.set_bit(1, H, false);
+    0x8D => "RES 1, L",           2,  8,  0, |c, _| c.set_bit(1, L, false);
+    0x8E => "RES 1, (HL)",        2, 15,  0, |c, _| c.set_bit(1, HL, false);
+    0x8F => "RES 1, A",           2,  8,  0, |c, _| c.set_bit(1, A, false);
+    0x90 => "RES 2, B",           2,  8,  0, |c, _| c.set_bit(2, B, false);
+    0x91 => "RES 2, C",           2,  8,  0, |c, _| c.set_bit(2, C, false);
+    0x92 => "RES 2, D",           2,  8,  0, |c, _| c.set_bit(2, D, false);
+    0x93 => "RES 2, E",           2,  8,  0, |c, _| c.set_bit(2, E, false);
+    0x94 => "RES 2, H",           2,  8,  0, |c, _| c.set_bit(2, H, false);
+    0x95 => "RES 2, L",           2,  8,  0, |c, _| c.set_bit(2, L, false);
+    0x96 => "RES 2, (HL)",        2, 15,  0, |c, _| c.set_bit(2, HL, false);
+    0x97 => "RES 2, A",           2,  8,  0, |c, _| c.set_bit(2, A, false);
+    0x98 => "RES 3, B",           2,  8,  0, |c, _| c.set_bit(3, B, false);
+    0x99 => "RES 3, C",           2,  8,  0, |c, _| c.set_bit(3, C, false);
+    0x9A => "RES 3, D",           2,  8,  0, |c, _| c.set_bit(3, D, false);
+    0x9B => "RES 3, E",           2,  8,  0, |c, _| c.set_bit(3, E, false);
+    0x9C => "RES 3, H",           2,  8,  0, |c, _| c.set_bit(3, H, false);
+    0x9D => "RES 3, L",           2,  8,  0, |c, _| c.set_bit(3, L, false);
+    0x9E => "RES 3, (HL)",        2, 15,  0, |c, _| c.set_bit(3, HL, false);
+    0x9F => "RES 3, A",           2,  8,  0, |c, _| c.set_bit(3, A, false);
+    0xA0 => "RES 4, B",           2,  8,  0, |c, _| c.set_bit(4, B, false);
+    0xA1 => "RES 4, C",           2,  8,  0, |c, _| c.set_bit(4, C, false);
+    0xA2 => "RES 4, D",           2,  8,  0, |c, _| c.set_bit(4, D, false);
+    0xA3 => "RES 4, E",           2,  8,  0, |c, _| c.set_bit(4, E, false);
+    0xA4 => "RES 4, H",           2,  8,  0, |c, _| c.set_bit(4, H, false);
+    0xA5 => "RES 4, L",           2,  8,  0, |c, _| c.set_bit(4, L, false);
+    0xA6 => "RES 4, (HL)",        2, 15,  0, |c, _| c.set_bit(4, HL, false);
+    0xA7 => "RES 4, A",           2,  8,  0, |c, _| c.set_bit(4, A, false);
+    0xA8 => "RES 5, B",           2,  8,  0, |c, _| c.set_bit(5, B, false);
+    0xA9 => "RES 5, C",           2,  8,  0, |c, _| c.set_bit(5, C, false);
+    0xAA => "RES 5, D",           2,  8,  0, |c, _| c.set_bit(5, D, false);
+    0xAB => "RES 5, E",           2,  8,  0, |c, _| c.set_bit(5, E, false);
+    0xAC => "RES 5, H",           2,  8,  0, |c, _| c.set_bit(5, H, false);
+    0xAD => "RES 5, L",           2,  8,  0, |c, _| c.set_bit(5, L, false);
+    0xAE => "RES 5, (HL)",        2, 15,  0, |c, _| c.set_bit(5, HL, false);
+    0xAF => "RES 5, A",           2,  8,  0, |c, _| c.set_bit(5, A, false);
+    0xB0 => "RES 6, B",           2,  8,  0, |c, _| c.set_bit(6, B, false);
+    0xB1 => "RES 6, C",           2,  8,  0, |c, _| c.set_bit(6, C, false);
+    0xB2 => "RES 6, D",           2,  8,  0, |c, _| c.set_bit(6, D, false);
+    0xB3 => "RES 6, E",           2,  8,  0, |c, _| c.set_bit(6, E, false);
+    0xB4 => "RES 6, H",           2,  8,  0, |c, _| c.set_bit(6, H, false);
+    0xB5 => "RES 6, L",           2,  8,  0, |c, _| c.set_bit(6, L, false);
+    0xB6 => "RES 6, (HL)",        2, 15,  0, |c, _| c.set_bit(6, HL, false);
+    0xB7 => "RES 6, A",           2,  8,  0, |c, _| c.set_bit(6, A, false);
+    0xB8 => "RES 7, B",           2,  8,  0, |c, _| c.set_bit(7, B, false);
+    0xB9 => "RES 7, C",           2,  8,  0, |c, _| c.set_bit(7, C, false);
+    0xBA => "RES 7, D",           2,  8,  0, |c, _| c.set_bit(7, D, false);
+    0xBB => "RES 7, E",           2,  8,  0, |c, _| c.set_bit(7, E, false);
+    0xBC => "RES 7, H",           2,  8,  0, |c, _| c.set_bit(7, H, false);
+    0xBD => "RES 7, L",           2,  8,  0, |c, _| c.set_bit(7, L, false);
+    0xBE => "RES 7, (HL)",        2, 15,  0, |c, _| c.set_bit(7, HL, false);
+    0xBF => "RES 7, A",           2,  8,  0, |c, _| c.set_bit(7, A, false);
+    0xC0 => "SET 0, B",           2,  8,  0, |c, _| c.set_bit(0, B, true);
+    0xC1 => "SET 0, C",           2,  8,  0, |c, _| c.set_bit(0, C, true);
+    0xC2 => "SET 0, D",           2,  8,  0, |c, _| c.set_bit(0, D, true);
+    0xC3 => "SET 0, E",           2,  8,  0, |c, _| c.set_bit(0, E, true);
+    0xC4 => "SET 0, H",           2,  8,  0, |c, _| c.set_bit(0, H, true);
+    0xC5 => "SET 0, L",           2,  8,  0, |c, _| c.set_bit(0, L, true);
+    0xC6 => "SET 0, (HL)",        2, 15,  0, |c, _| c.set_bit(0, HL, true);
+    0xC7 => "SET 0, A",           2,  8,  0, |c, _| c.set_bit(0, A, true);
+    0xC8 => "SET 1, B",           2,  8,  0, |c, _| c.set_bit(1, B, true);
+    0xC9 => "SET 1, C",           2,  8,  0, |c, _| c.set_bit(1, C, true);
+    0xCA => "SET 1, D",           2,  8,  0, |c, _| c.set_bit(1, D, true);
+    0xCB => "SET 1, E",           2,  8,  0, |c, _| c.set_bit(1, E, true);
+    0xCC => "SET 1, H",           2,  8,  0, |c, _| c.set_bit(1, H, true);
+    0xCD => "SET 1, L",           2,  8,  0, |c, _| c.set_bit(1, L, true);
+    0xCE => "SET 1, (HL)",        2, 15,  0, |c, _| c.set_bit(1, HL, true);
+    0xCF => "SET 1, A",           2,  8,  0, |c, _| c.set_bit(1, A, true);
+    0xD0 => "SET 2, B",           2,  8,  0, |c, _| c.set_bit(2, B, true);
+    0xD1 => "SET 2, C",           2,  8,  0, |c, _| c.set_bit(2, C, true);
+    0xD2 => "SET 2, D",           2,  8,  0, |c, _| c.set_bit(2, D, true);
+    0xD3 => "SET 2, E",           2,  8,  0, |c, _| c.set_bit(2, E, true);
+    0xD4 => "SET 2, H",           2,  8,  0, |c, _| c.set_bit(2, H, true);
+    0xD5 => "SET 2, L",           2,  8,  0, |c, _| c.set_bit(2, L, true);
+    0xD6 => "SET 2, (HL)",        2, 15,  0, |c, _| c.set_bit(2, HL, true);
+    0xD7 => "SET 2, A",           2,  8,  0, |c, _| c.set_bit(2, A, true);
+    0xD8 => "SET 3, B",           2,  8,  0, |c, _| c.set_bit(3, B, true);
+    0xD9 => "SET 3, C",           2,  8,  0, |c, _| c.set_bit(3, C, true);
+    0xDA => "SET 3, D",           2,  8,  0, |c, _| c.set_bit(3, D, true);
+    0xDB => "SET 3, E",           2,  8,  0, |c, _| c.set_bit(3, E, true);
+    0xDC => "SET 3, H",           2,  8,  0, |c, _| c.set_bit(3, H, true);
+    0xDD => "SET 3, L",           2,  8,  0, |c, _| c.set_bit(3, L, true);
+    0xDE => "SET 3, (HL)",        2, 15,  0, |c, _| c.set_bit(3, HL, true);
+    0xDF => "SET 3, A",           2,  8,  0, |c, _| c.set_bit(3, A, true);
+    0xE0 => "SET 4, B",           2,  8,  0, |c, _| c.set_bit(4, B, true);
+    0xE1 => "SET 4, C",           2,  8,  0, |c, _| c.set_bit(4, C, true);
+    0xE2 => "SET 4, D",           2,  8,  0, |c, _| c.set_bit(4, D, true);
+    0xE3 => "SET 4, E",           2,  8,  0, |c, _| c.set_bit(4, E, true);
+    0xE4 => "SET 4, H",           2,  8,  0, |c, _| c.set_bit(4, H, true);
+    0xE5 => "SET 4, L",           2,  8,  0, |c, _| c.set_bit(4, L, true);
+    0xE6 => "SET 4, (HL)",        2, 15,  0, |c, _| c.set_bit(4, HL, true);
+    0xE7 => "SET 4, A",           2,  8,  0, |c, _| c.set_bit(4, A, true);
+    0xE8 => "SET 5, B",           2,  8,  0, |c, _| c.set_bit(5, B, true);
+    0xE9 => "SET 5, C",           2,  8,  0, |c, _| c.set_bit(5, C, true);
+    0xEA => "SET 5, D",           2,  8,  0, |c, _| c.set_bit(5, D, true);
+    0xEB => "SET 5, E",           2,  8,  0, |c, _| c.set_bit(5, E, true);
+    0xEC => "SET 5, H",           2,  8,  0, |c, _| c.set_bit(5, H, true);
+    0xED => "SET 5, L",           2,  8,  0, |c, _| c.set_bit(5, L, true);
+    0xEE => "SET 5, (HL)",        2, 15,  0, |c, _| c.set_bit(5, HL, true);
+    0xEF => "SET 5, A",           2,  8,  0, |c, _| c.set_bit(5, A, true);
+    0xF0 => "SET 6, B",           2,  8,  0, |c, _| c.set_bit(6, B, true);
+    0xF1 => "SET 6, C",           2,  8,  0, |c, _| c.set_bit(6, C, true);
+    0xF2 => "SET 6, D",           2,  8,  0, |c, _| c.set_bit(6, D, true);
+    0xF3 => "SET 6, E",           2,  8,  0, |c, _| c.set_bit(6, E, true);
+    0xF4 => "SET 6, H",           2,  8,  0, |c, _| c.set_bit(6, H, true);
+    0xF5 => "SET 6, L",           2,  8,  0, |c, _| c.set_bit(6, L, true);
+    0xF6 => "SET 6, (HL)",        2, 15,  0, |c, _| c.set_bit(6, HL, true);
+    0xF7 => "SET 6, A",           2,  8,  0, |c, _| c.set_bit(6, A, true);
+    0xF8 => "SET 7, B",           2,  8,  0, |c, _| c.set_bit(7, B, true);
+    0xF9 => "SET 7, C",           2,  8,  0, |c, _| c.set_bit(7, C, true);
+    0xFA => "SET 7, D",           2,  8,  0, |c, _| c.set_bit(7, D, true);
+    0xFB => "SET 7, E",           2,  8,  0, |c, _| c.set_bit(7, E, true);
+    0xFC => "SET 7, H",           2,  8,  0, |c, _| c.set_bit(7, H, true);
+    0xFD => "SET 7, L",           2,  8,  0, |c, _| c.set_bit(7, L, true);
+    0xFE => "SET 7, (HL)",        2, 15,  0, |c, _| c.set_bit(7, HL, true);
+    0xFF => "SET 7, A",           2,  8,  0, |c, _| c.set_bit(7, A, true);
+}
+
+// ED prefixed opcodes
+opcodes! {
+    ED, ED_EXEC;
+    0x40 => "IN B, (C)",          2, 12,  0, |c, _| c.in_c(B);
+    0x41 => "OUT (C), B",         2, 12,  0, |c, _| c.out_c(B);
+    0x42 => "SBC HL, BC",         2, 15,  0, |c, _| c.sbc_hl(BC);
+    0x43 => "LD (**), BC",        4, 20,  0, |c, _| c.ld_nn(BC);
+    0x44 => "NEG",                2,  8,  0, |c, _| c.neg();
+    0x45 => "RETN",               2, 14,  0, |c, _| c.retn();
+    0x46 => "IM 0",               2,  8,  0, |c, _| c.set_interrupt_mode(0);
+    0x47 => "LD I, A",            2,  9,  0, |c, _| c.ld(I, A);
+    0x48 => "IN C, (C)",          2, 12,  0, |c, _| c.in_c(C);
+    0x49 => "OUT (C), C",         2, 12,  0, |c, _| c.out_c(C);
+    0x4A => "ADC HL, BC",         2, 15,  0, |c, _| c.adc_hl(BC);
+    0x4B => "LD BC, (**)",        4, 20,  0, |c, _| c.load_indirect(BC);
+    0x4C => "NEG",                2,  8,  0, |c, _| c.neg();
+    0x4D => "RETI",               2, 14,  0, |c, _| c.retn();
+    0x4E => "IM 0",               2,  8,  0, |c, _| c.set_interrupt_mode(0);
+    0x4F => "LD R, A",            2,  9,  0, |c, _| c.ld(R, A);
+    0x50 => "IN D, (C)",          2, 12,  0, |c, _| c.in_c(D);
+    0x51 => "OUT (C), D",         2, 12,  0, |c, _| c.out_c(D);
+    0x52 => "SBC HL, DE",         2, 15,  0, |c, _| c.sbc_hl(DE);
+    0x53 => "LD (**), DE",        4, 20,  0, |c, _| c.ld_nn(DE);
+    0x54 => "NEG",                2,  8,  0, |c, _| c.neg();
+    0x55 => "RETN",               2, 14,  0, |c, _| c.retn();
+    0x56 => "IM 1",               2,  8,  0, |c, _| c.set_interrupt_mode(1);
+    0x57 => "LD A, I",            2,  9,  0, |c, _| c.ld(A, I);
+    0x58 => "IN E, (C)",          2, 12,  0, |c, _| c.in_c(E);
+    0x59 => "OUT (C), E",         2, 12,  0, |c, _| c.out_c(E);
+    0x5A => "ADC HL, DE",         2, 15,  0, |c, _| c.adc_hl(DE);
+    0x5B => "LD DE, (**)",        4, 20,  0, |c, _| c.load_indirect(DE);
+    0x5C => "NEG",                2,  8,  0, |c, _| c.neg();
+    0x5D => "RETI",               2, 14,  0, |c, _| c.retn();
+    0x5E => "IM 2",               2,  8,  0, |c, _| c.set_interrupt_mode(2);
+    0x5F => "LD A, R",            2,  9,  0, |c, _| c.ld(A, R);
+    0x60 => "IN H, (C)",          2, 12,  0, |c, _| c.in_c(H);
+    0x61 => "OUT (C), H",         2, 12,  0, |c, _| c.out_c(H);
+    0x62 => "SBC HL, HL",         2, 15,  0, |c, _| c.sbc_hl(HL);
+    0x63 => "LD (**), HL",        4, 20,  0, |c, _| c.ld_nn(HL);
+    0x64 => "NEG",                2,  8,  0, |c, _| c.neg();
+    0x65 => "RETN",               2, 14,  0, |c, _| c.retn();
+    0x66 => "IM 0",               2,  8,  0, |c, _| c.set_interrupt_mode(0);
+    0x67 => "RRD",                2, 18,  0, |c, _| c.rld(true);
+    0x68 => "IN L, (C)",          2, 12,  0, |c, _| c.in_c(L);
+    0x69 => "OUT (C), L",         2, 12,  0, |c, _| c.out_c(L);
+    0x6A => "ADC HL, HL",         2, 15,  0, |c, _| c.adc_hl(HL);
+    0x6B => "LD HL, (**)",        4, 20,  0, |c, _| c.load_indirect(HL);
+    0x6C => "NEG",                2,  8,  0, |c, _| c.neg();
+    0x6D => "RETI",               2, 14,  0, |c, _| c.retn();
+    0x6E => "IM 0",               2,  8,  0, |c, _| c.set_interrupt_mode(0);
+    0x6F => "RLD",                2, 18,  0, |c, _| c.rld(false);
+    0x70 => "IN (C)",             2, 12,  0, |c, _| c.in_c(HL);
+    0x71 => "OUT (C), 0",         2, 12,  0, |c, _| c.out_c(HL);
+    0x72 => "SBC HL, SP",         2, 15,  0, |c, _| c.sbc_hl(SP);
+    0x73 => "LD (**), SP",        4, 20,  0, |c, _| c.ld_nn(SP);
+    0x74 => "NEG",                2,  8,  0, |c, _| c.neg();
+    0x75 => "RETN",               2, 14,  0, |c, _| c.retn();
+    0x76 => "IM 1",               2,  8,  0, |c, _| c.set_interrupt_mode(1);
+    0x78 => "IN A, (C)",          2, 12,  0, |c, _| c.in_c(A);
+    0x79 => "OUT (C), A",         2, 12,  0, |c, _| c.out_c(A);
+    0x7A => "ADC HL, SP",         2, 15,  0, |c, _| c.adc_hl(SP);
+    0x7B => "LD SP, (**)",        4, 20,  0, |c, _| c.load_indirect(SP);
+    0x7C => "NEG",                2,  8,  0, |c, _| c.neg();
+    0x7D => "RETI",               2, 14,  0, |c, _| c.retn();
+    0x7E => "IM 2",               2,  8,  0, |c, _| c.set_interrupt_mode(2);
+    0xA0 => "LDI",                2, 16,  0, |c, _| c.ld_block(true, false);
+    0xA1 => "CPI",                2, 16,  0, |c, _| c.cp_block(true, false);
+    0xA2 => "INI",                2, 16,  0, |c, _| c.in_block(true, false);
+    0xA3 => "OUTI",               2, 16,  0, |c, _| c.out_block(true, false);
+    0xA8 => "LDD",                2, 16,  0, |c, _| c.ld_block(false, false);
+    0xA9 => "CPD",                2, 16,  0, |c, _| c.cp_block(false, false);
+    0xAA => "IND",                2, 16,  0, |c, _| c.in_block(false, false);
+    0xAB => "OUTD",               2, 16,  0, |c, _| c.out_block(false, false);
+    0xB0 => "LDIR",               2, 21, 16, |c, _| c.ld_block(true, true);
+    0xB1 => "CPIR",               2, 21, 16, |c, _| c.cp_block(true, true);
+    0xB2 => "INIR",               2, 21, 16, |c, _| c.in_block(true, true);
+    0xB3 => "OTIR",               2, 21, 16, |c, _| c.out_block(true, true);
+    0xB8 => "LDDR",               2, 21, 16, |c, _| c.ld_block(false, true);
+    0xB9 => "CPDR",               2, 21, 16, |c, _| c.cp_block(false, true);
+    0xBA => "INDR",               2, 21, 16, |c, _| c.in_block(false, true);
+    0xBB => "OTDR",               2, 21, 16, |c, _| c.out_block(false, true);
+}
+
+// DD & FD prefixed opcodes using IX / IY in place of HL, H & L
+opcodes! {
+    INDEX, INDEX_EXEC;
+    0x09 => "ADD IX, BC",         2, 15,  0, |c, ir| c.add16(ir, BC);
+    0x19 => "ADD IX, DE",         2, 15,  0, |c, ir| c.add16(ir, DE);
+    0x21 => "LD IX, **",          4, 14,  0, |c, ir| c.lxi(ir);
+    0x22 => "LD (**), IX",        4, 20,  0, |c, ir| c.shld(ir);
+    0x23 => "INC IX",             2, 10,  0, |c, ir| c.inx(ir);
+    0x24 => "INC IXH",            2,  8,  0, |c, ir| c.inc(hi(ir));
+    0x25 => "DEC IXH",            2,  8,  0, |c, ir| c.dec(hi(ir));
+    0x26 => "LD IXH, *",          3, 11,  0, |c, ir| c.mvi(hi(ir));
+    0x29 => "ADD IX, IX",         2, 15,  0, |c, ir| c.add16(ir, ir);
+    0x2A => "LD IX, (**)",        4, 20,  0, |c, ir| c.lhld(ir);
+    0x2B => "DEC IX",             2, 10,  0, |c, ir| c.dex(ir);
+    0x2C => "INC IXL",            2,  8,  0, |c, ir| c.inc(lo(ir));
+    0x2D => "DEC IXL",            2,  8,  0, |c, ir| c.dec(lo(ir));
+    0x2E => "LD IXL, *",          3, 11,  0, |c, ir| c.mvi(lo(ir));
+    0x34 => "INC (IX+*)",         3, 23,  0, |c, ir| c.inc(ir);
+    0x35 => "DEC (IX+*)",         3, 23,  0, |c, ir| c.dec(ir);
+    0x36 => "LD (IX+*), *",       4, 19,  0, |c, ir| c.mvi(ir);
+    0x39 => "ADD IX, SP",         2, 15,  0, |c, ir| c.add16(ir, SP);
+    0x44 => "LD B, IXH",          2,  8,  0, |c, ir| c.ld(B, hi(ir));
+    0x45 => "LD B, IXL",          2,  8,  0, |c, ir| c.ld(B, lo(ir));
+    0x46 => "LD B, (IX+*)",       3, 19,  0, |c, ir| c.ld(B, ir);
+    0x4C => "LD C, IXH",          2,  8,  0, |c, ir| c.ld(C, hi(ir));
+    0x4D => "LD C, IXL",          2,  8,  0, |c, ir| c.ld(C, lo(ir));
+    0x4E => "LD C, (IX+*)",       3, 19,  0, |c, ir| c.ld(C, ir);
+    0x54 => "LD D, IXH",          2,  8,  0, |c, ir| c.ld(D, hi(ir));
+    0x55 => "LD D, IXL",          2,  8,  0, |c, ir| c.ld(D, lo(ir));
+    0x56 => "LD D, (IX+*)",       3, 19,  0, |c, ir| c.ld(D, ir);
+    0x5C => "LD E, IXH",          2,  8,  0, |c, ir| c.ld(E, hi(ir));
+    0x5D => "LD E, IXL",          2,  8,  0, |c, ir| c.ld(E, lo(ir));
+    0x5E => "LD E, (IX+*)",       3, 19,  0, |c, ir| c.ld(E, ir);
+    0x60 => "LD IXH, B",          2,  8,  0, |c, ir| c.ld(hi(ir), B);
+    0x61 => "LD IXH, C",          2,  8,  0, |c, ir| c.ld(hi(ir), C);
+    0x62 => "LD IXH, D",          2,  8,  0, |c, ir| c.ld(hi(ir), D);
+    0x63 => "LD IXH, E",          2,  8,  0, |c, ir| c.ld(hi(ir), E);
+    0x64 => "LD IXH, IXH",        2,  8,  0, |c, ir| c.ld(hi(ir), hi(ir));
+    0x65 => "LD IXH, IXL",        2,  8,  0, |c, ir| c.ld(hi(ir), lo(ir));
+    0x66 => "LD H, (IX+*)",       3, 19,  0, |c, ir| c.ld(H, ir);
+    0x67 => "LD IXH, A",          2,  8,  0, |c, ir| c.ld(hi(ir), A);
+    0x68 => "LD IXL, B",          2,  8,  0, |c, ir| c.ld(lo(ir), B);
+    0x69 => "LD IXL, C",          2,  8,  0, |c, ir| c.ld(lo(ir), C);
+    0x6A => "LD IXL, D",          2,  8,  0, |c, ir| c.ld(lo(ir), D);
+    0x6B => "LD IXL, E",          2,  8,  0, |c, ir| c.ld(lo(ir), E);
+    0x6C => "LD IXL, IXH",        2,  8,  0, |c, ir| c.ld(lo(ir), hi(ir));
+    0x6D => "LD IXL, IXL",        2,  8,  0, |c, ir| c.ld(lo(ir), lo(ir));
+    0x6E => "LD L, (IX+*)",       3, 19,  0, |c, ir| c.ld(L, ir);
+    0x6F => "LD IXL, A",          2,  8,  0, |c, ir| c.ld(lo(ir), A);
+    0x70 => "LD (IX+*), B",       3, 19,  0, |c, ir| c.ld(ir, B);
+    0x71 => "LD (IX+*), C",       3, 19,  0, |c, ir| c.ld(ir, C);
+    0x72 => "LD (IX+*), D",       3, 19,  0, |c, ir| c.ld(ir, D);
+    0x73 => "LD (IX+*), E",       3, 19,  0, |c, ir| c.ld(ir, E);
+    0x74 => "LD (IX+*), H",       3, 19,  0, |c, ir| c.ld(ir, H);
+    0x75 => "LD (IX+*), L",       3, 19,  0, |c, ir| c.ld(ir, L);
+    0x77 => "LD (IX+*), A",       3, 19,  0, |c, ir| c.ld(ir, A);
+    0x7C => "LD A, IXH",          2,  8,  0, |c, ir| c.ld(A, hi(ir));
+    0x7D => "LD A, IXL",          2,  8,  0, |c, ir| c.ld(A, lo(ir));
+    0x7E => "LD A, (IX+*)",       3, 19,  0, |c, ir| c.ld(A, ir);
+    0x84 => "ADD A, IXH",         2,  8,  0, |c, ir| c.add(hi(ir));
+    0x85 => "ADD A, IXL",         2,  8,  0, |c, ir| c.add(lo(ir));
+    0x86 => "ADD A, (IX+*)",      3, 19,  0, |c, ir| c.add(ir);
+    0x8C => "ADC A, IXH",         2,  8,  0, |c, ir| c.adc(hi(ir));
+    0x8D => "ADC A, IXL",         2,  8,  0, |c, ir| c.adc(lo(ir));
+    0x8E => "ADC A, (IX+*)",      3, 19,  0, |c, ir| c.adc(ir);
+    0x94 => "SUB IXH",            2,  8,  0, |c, ir| c.sub(hi(ir));
+    0x95 => "SUB IXL",            2,  8,  0, |c, ir| c.sub(lo(ir));
+    0x96 => "SUB (IX+*)",         3, 19,  0, |c, ir| c.sub(ir);
+    0x9C => "SBC A, IXH",         2,  8,  0, |c, ir| c.sbc(hi(ir));
+    0x9D => "SBC A, IXL",         2,  8,  0, |c, ir| c.sbc(lo(ir));
+    0x9E => "SBC A, (IX+*)",      3, 19,  0, |c, ir| c.sbc(ir);
+    0xA4 => "AND IXH",            2,  8,  0, |c, ir| c.ana(hi(ir));
+    0xA5 => "AND IXL",            2,  8,  0, |c, ir| c.ana(lo(ir));
+    0xA6 => "AND (IX+*)",         3, 19,  0, |c, ir| c.ana(ir);
+    0xAC => "XOR IXH",            2,  8,  0, |c, ir| c.xra(hi(ir));
+    0xAD => "XOR IXL",            2,  8,  0, |c, ir| c.xra(lo(ir));
+    0xAE => "XOR (IX+*)",         3, 19,  0, |c, ir| c.xra(ir);
+    0xB4 => "OR IXH",             2,  8,  0, |c, ir| c.ora(hi(ir));
+    0xB5 => "OR IXL",             2,  8,  0, |c, ir| c.ora(lo(ir));
+    0xB6 => "OR (IX+*)",          3, 19,  0, |c, ir| c.ora(ir);
+    0xBC => "CP IXH",             2,  8,  0, |c, ir| c.cmp(hi(ir));
+    0xBD => "CP IXL",             2,  8,  0, |c, ir| c.cmp(lo(ir));
+    0xBE => "CP (IX+*)",          3, 19,  0, |c, ir| c.cmp(ir);
+    0xE1 => "POP IX",             2, 14,  0, |c, ir| c.pop(ir);
+    0xE3 => "EX (SP), IX",        2, 23,  0, |c, ir| c.xthl(ir);
+    0xE5 => "PUSH IX",            2, 15,  0, |c, ir| c.push(ir);
+    0xE9 => "JP (IX)",            2,  8,  0, |c, ir| c.pchl(ir);
+    0xF9 => "LD SP, IX",          2, 10,  0, |c, ir| c.sphl(ir);
+}
+
+// DDCB & FDCB prefixed opcodes: DD CB d op. The rotates, RES & SET also copy the result into
+// the register encoded in bits 0-2 of the opcode (undocumented), unless it is (HL).
+opcodes! {
+    INDEX_BIT, INDEX_BIT_EXEC;
+    0x00 => "RLC (IX+*), B",      4, 23,  0, |c, _| c.rot_index(0, B);
+    0x01 => "RLC (IX+*), C",      4, 23,  0, |c, _| c.rot_index(0, C);
+    0x02 => "RLC (IX+*), D",      4, 23,  0, |c, _| c.rot_index(0, D);
+    0x03 => "RLC (IX+*), E",      4, 23,  0, |c, _| c.rot_index(0, E);
+    0x04 => "RLC (IX+*), H",      4, 23,  0, |c, _| c.rot_index(0, H);
+    0x05 => "RLC (IX+*), L",      4, 23,  0, |c, _| c.rot_index(0, L);
+    0x06 => "RLC (IX+*)",         4, 23,  0, |c, _| c.rot_index(0, HL);
+    0x07 => "RLC (IX+*), A",      4, 23,  0, |c, _| c.rot_index(0, A);
+    0x08 => "RRC (IX+*), B",      4, 23,  0, |c, _| c.rot_index(1, B);
+    0x09 => "RRC (IX+*), C",      4, 23,  0, |c, _| c.rot_index(1, C);
+    0x0A => "RRC (IX+*), D",      4, 23,  0, |c, _| c.rot_index(1, D);
+    0x0B => "RRC (IX+*), E",      4, 23,  0, |c, _| c.rot_index(1, E);
+    0x0C => "RRC (IX+*), H",      4, 23,  0, |c, _| c.rot_index(1, H);
+    0x0D => "RRC (IX+*), L",      4, 23,  0, |c, _| c.rot_index(1, L);
+    0x0E => "RRC (IX+*)",         4, 23,  0, |c, _| c.rot_index(1, HL);
+    0x0F => "RRC (IX+*), A",      4, 23,  0, |c, _| c.rot_index(1, A);
+    0x10 => "RL (IX+*), B",       4, 23,  0, |c, _| c.rot_index(2, B);
+    0x11 => "RL (IX+*), C",       4, 23,  0, |c, _| c.rot_index(2, C);
+    0x12 => "RL (IX+*), D",       4, 23,  0, |c, _| c.rot_index(2, D);
+    0x13 => "RL (IX+*), E",       4, 23,  0, |c, _| c.rot_index(2, E);
+    0x14 => "RL (IX+*), H",       4, 23,  0, |c, _| c.rot_index(2, H);
+    0x15 => "RL (IX+*), L",       4, 23,  0, |c, _| c.rot_index(2, L);
+    0x16 => "RL (IX+*)",          4, 23,  0, |c, _| c.rot_index(2, HL);
+    0x17 => "RL (IX+*), A",       4, 23,  0, |c, _| c.rot_index(2, A);
+    0x18 => "RR (IX+*), B",       4, 23,  0, |c, _| c.rot_index(3, B);
+    0x19 => "RR (IX+*), C",       4, 23,  0, |c, _| c.rot_index(3, C);
+    0x1A => "RR (IX+*), D",       4, 23,  0, |c, _| c.rot_index(3, D);
+    0x1B => "RR (IX+*), E",       4, 23,  0, |c, _| c.rot_index(3, E);
+    0x1C => "RR (IX+*), H",       4, 23,  0, |c, _| c.rot_index(3, H);
+    0x1D => "RR (IX+*), L",       4, 23,  0, |c, _| c.rot_index(3, L);
+    0x1E => "RR (IX+*)",          4, 23,  0, |c, _| c.rot_index(3, HL);
+    0x1F => "RR (IX+*), A",       4, 23,  0, |c, _| c.rot_index(3, A);
+    0x20 => "SLA (IX+*), B",      4, 23,  0, |c, _| c.rot_index(4, B);
+    0x21 => "SLA (IX+*), C",      4, 23,  0, |c, _| c.rot_index(4, C);
+    0x22 => "SLA (IX+*), D",      4, 23,  0, |c, _| c.rot_index(4, D);
+    0x23 => "SLA (IX+*), E",      4, 23,  0, |c, _| c.rot_index(4, E);
+    0x24 => "SLA (IX+*), H",      4, 23,  0, |c, _| c.rot_index(4, H);
+    0x25 => "SLA (IX+*), L",      4, 23,  0, |c, _| c.rot_index(4, L);
+    0x26 => "SLA (IX+*)",         4, 23,  0, |c, _| c.rot_index(4, HL);
+    0x27 => "SLA (IX+*), A",      4, 23,  0, |c, _| c.rot_index(4, A);
+    0x28 => "SRA (IX+*), B",      4, 23,  0, |c, _| c.rot_index(5, B);
+    0x29 => "SRA (IX+*), C",      4, 23,  0, |c, _| c.rot_index(5, C);
+    0x2A => "SRA (IX+*), D",      4, 23,  0, |c, _| c.rot_index(5, D);
+    0x2B => "SRA (IX+*), E",      4, 23,  0, |c, _| c.rot_index(5, E);
+    0x2C => "SRA (IX+*), H",      4, 23,  0, |c, _| c.rot_index(5, H);
+    0x2D => "SRA (IX+*), L",      4, 23,  0, |c, _| c.rot_index(5, L);
+    0x2E => "SRA (IX+*)",         4, 23,  0, |c, _| c.rot_index(5, HL);
+    0x2F => "SRA (IX+*), A",      4, 23,  0, |c, _| c.rot_index(5, A);
+    0x30 => "SLL (IX+*), B",      4, 23,  0, |c, _| c.rot_index(6, B);
+    0x31 => "SLL (IX+*), C",      4, 23,  0, |c, _| c.rot_index(6, C);
+    0x32 => "SLL (IX+*), D",      4, 23,  0, |c, _| c.rot_index(6, D);
+    0x33 => "SLL (IX+*), E",      4, 23,  0, |c, _| c.rot_index(6, E);
+    0x34 => "SLL (IX+*), H",      4, 23,  0, |c, _| c.rot_index(6, H);
+    0x35 => "SLL (IX+*), L",      4, 23,  0, |c, _| c.rot_index(6, L);
+    0x36 => "SLL (IX+*)",         4, 23,  0, |c, _| c.rot_index(6, HL);
+    0x37 => "SLL (IX+*), A",      4, 23,  0, |c, _| c.rot_index(6, A);
+    0x38 => "SRL (IX+*), B",      4, 23,  0, |c, _| c.rot_index(7, B);
+    0x39 => "SRL (IX+*), C",      4, 23,  0, |c, _| c.rot_index(7, C);
+    0x3A => "SRL (IX+*), D",      4, 23,  0, |c, _| c.rot_index(7, D);
+    0x3B => "SRL (IX+*), E",      4, 23,  0, |c, _| c.rot_index(7, E);
+    0x3C => "SRL (IX+*), H",      4, 23,  0, |c, _| c.rot_index(7, H);
+    0x3D => "SRL (IX+*), L",      4, 23,  0, |c, _| c.rot_index(7, L);
+    0x3E => "SRL (IX+*)",         4, 23,  0, |c, _| c.rot_index(7, HL);
+    0x3F => "SRL (IX+*), A",      4, 23,  0, |c, _| c.rot_index(7, A);
+    0x40 => "BIT 0, (IX+*)",      4, 20,  0, |c, _| c.bit_index(0);
+    0x41 => "BIT 0, (IX+*)",      4, 20,  0, |c, _| c.bit_index(0);
+    0x42 => "BIT 0, (IX+*)",      4, 20,  0, |c, _| c.bit_index(0);
+    0x43 => "BIT 0, (IX+*)",      4, 20,  0, |c, _| c.bit_index(0);
+    0x44 => "BIT 0, (IX+*)",      4, 20,  0, |c, _| c.bit_index(0);
+    0x45 => "BIT 0, (IX+*)",      4, 20,  0, |c, _| c.bit_index(0);
+    0x46 => "BIT 0, (IX+*)",      4, 20,  0, |c, _| c.bit_index(0);
+    0x47 => "BIT 0, (IX+*)",      4, 20,  0, |c, _| c.bit_index(0);
+    0x48 => "BIT 1, (IX+*)",      4, 20,  0, |c, _| c.bit_index(1);
+    0x49 => "BIT 1, (IX+*)",      4, 20,  0, |c, _| c.bit_index(1);
+    0x4A => "BIT 1, (IX+*)",      4, 20,  0, |c, _| c.bit_index(1);
+    0x4B => "BIT 1, (IX+*)",      4, 20,  0, |c, _| c.bit_index(1);
+    0x4C => "BIT 1, (IX+*)",      4, 20,  0, |c, _| c.bit_index(1);
+    0x4D => "BIT 1, (IX+*)",      4, 20,  0, |c, _| c.bit_index(1);
+    0x4E => "BIT 1, (IX+*)",      4, 20,  0, |c, _| c.bit_index(1);
+    0x4F => "BIT 1, (IX+*)",      4, 20,  0, |c, _| c.bit_index(1);
+    0x50 => "BIT 2, (IX+*)",      4, 20,  0, |c, _| c.bit_index(2);
+    0x51 => "BIT 2, (IX+*)",      4, 20,  0, |c, _| c.bit_index(2);
+    0x52 => "BIT 2, (IX+*)",      4, 20,  0, |c, _| c.bit_index(2);
+    0x53 => "BIT 2, (IX+*)",      4, 20,  0, |c, _| c.bit_index(2);
+    0x54 => "BIT 2, (IX+*)",      4, 20,  0, |c, _| c.bit_index(2);
+    0x55 => "BIT 2, (IX+*)",      4, 20,  0, |c, _| c.bit_index(2);
+    0x56 => "BIT 2, (IX+*)",      4, 20,  0, |c, _| c.bit_index(2);
+    0x57 => "BIT 2, (IX+*)",      4, 20,  0, |c, _| c.bit_index(2);
+    0x58 => "BIT 3, (IX+*)",      4, 20,  0, |c, _| c.bit_index(3);
+    0x59 => "BIT 3, (IX+*)",      4, 20,  0, |c, _| c.bit_index(3);
+    0x5A => "BIT 3, (IX+*)",      4, 20,  0, |c, _| c.bit_index(3);
+    0x5B => "BIT 3, (IX+*)",      4, 20,  0, |c, _| c.bit_index(3);
+    0x5C => "BIT 3, (IX+*)",      4, 20,  0, |c, _| c.bit_index(3);
+    0x5D => "BIT 3, (IX+*)",      4, 20,  0, |c, _| c.bit_index(3);
+    0x5E => "BIT 3, (IX+*)",      4, 20,  0, |c, _| c.bit_index(3);
+    0x5F => "BIT 3, (IX+*)",      4, 20,  0, |c, _| c.bit_index(3);
+    0x60 => "BIT 4, (IX+*)",      4, 20,  0, |c, _| c.bit_index(4);
+    0x61 => "BIT 4, (IX+*)",      4, 20,  0, |c, _| c.bit_index(4);
+    0x62 => "BIT 4, (IX+*)",      4, 20,  0, |c, _| c.bit_index(4);
+    0x63 => "BIT 4, (IX+*)",      4, 20,  0, |c, _| c.bit_index(4);
+    0x64 => "BIT 4, (IX+*)",      4, 20,  0, |c, _| c.bit_index(4);
+    0x65 => "BIT 4, (IX+*)",      4, 20,  0, |c, _| c.bit_index(4);
+    0x66 => "BIT 4, (IX+*)",      4, 20,  0, |c, _| c.bit_index(4);
+    0x67 => "BIT 4, (IX+*)",      4, 20,  0, |c, _| c.bit_index(4);
+    0x68 => "BIT 5, (IX+*)",      4, 20,  0, |c, _| c.bit_index(5);
+    0x69 => "BIT 5, (IX+*)",      4, 20,  0, |c, _| c.bit_index(5);
+    0x6A => "BIT 5, (IX+*)",      4, 20,  0, |c, _| c.bit_index(5);
+    0x6B => "BIT 5, (IX+*)",      4, 20,  0, |c, _| c.bit_index(5);
+    0x6C => "BIT 5, (IX+*)",      4, 20,  0, |c, _| c.bit_index(5);
+    0x6D => "BIT 5, (IX+*)",      4, 20,  0, |c, _| c.bit_index(5);
+    0x6E => "BIT 5, (IX+*)",      4, 20,  0, |c, _| c.bit_index(5);
+    0x6F => "BIT 5, (IX+*)",      4, 20,  0, |c, _| c.bit_index(5);
+    0x70 => "BIT 6, (IX+*)",      4, 20,  0, |c, _| c.bit_index(6);
+    0x71 => "BIT 6, (IX+*)",      4, 20,  0, |c, _| c.bit_index(6);
+    0x72 => "BIT 6, (IX+*)",      4, 20,  0, |c, _| c.bit_index(6);
+    0x73 => "BIT 6, (IX+*)",      4, 20,  0, |c, _| c.bit_index(6);
+    0x74 => "BIT 6, (IX+*)",      4, 20,  0, |c, _| c.bit_index(6);
+    0x75 => "BIT 6, (IX+*)",      4, 20,  0, |c, _| c.bit_index(6);
+    0x76 => "BIT 6, (IX+*)",      4, 20,  0, |c, _| c.bit_index(6);
+    0x77 => "BIT 6, (IX+*)",      4, 20,  0, |c, _| c.bit_index(6);
+    0x78 => "BIT 7, (IX+*)",      4, 20,  0, |c, _| c.bit_index(7);
+    0x79 => "BIT 7, (IX+*)",      4, 20,  0, |c, _| c.bit_index(7);
+    0x7A => "BIT 7, (IX+*)",      4, 20,  0, |c, _| c.bit_index(7);
+    0x7B => "BIT 7, (IX+*)",      4, 20,  0, |c, _| c.bit_index(7);
+    0x7C => "BIT 7, (IX+*)",      4, 20,  0, |c, _| c.bit_index(7);
+    0x7D => "BIT 7, (IX+*)",      4, 20,  0, |c, _| c.bit_index(7);
+    0x7E => "BIT 7, (IX+*)",      4, 20,  0, |c, _| c.bit_index(7);
+    0x7F => "BIT 7, (IX+*)",      4, 20,  0, |c, _| c.bit_index(7);
+    0x80 => "RES 0, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(0, B, false);
+    0x81 => "RES 0, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(0, C, false);
+    0x82 => "RES 0, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(0, D, false);
+    0x83 => "RES 0, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(0, E, false);
+    0x84 => "RES 0, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(0, H, false);
+    0x85 => "RES 0, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(0, L, false);
+    0x86 => "RES 0, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(0, HL, false);
+    0x87 => "RES 0, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(0, A, false);
+    0x88 => "RES 1, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(1, B, false);
+    0x89 => "RES 1, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(1, C, false);
+    0x8A => "RES 1, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(1, D, false);
+    0x8B => "RES 1, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(1, E, false);
+    0x8C => "RES 1, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(1, H, false);
+    0x8D => "RES 1, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(1, L, false);
+    0x8E => "RES 1, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(1, HL, false);
+    0x8F => "RES 1, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(1, A, false);
+    0x90 => "RES 2, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(2, B, false);
+    0x91 => "RES 2, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(2, C, false);
+    0x92 => "RES 2, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(2, D, false);
+    0x93 => "RES 2, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(2, E, false);
+    0x94 => "RES 2, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(2, H, false);
+    0x95 => "RES 2, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(2, L, false);
+    0x96 => "RES 2, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(2, HL, false);
+    0x97 => "RES 2, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(2, A, false);
+    0x98 => "RES 3, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(3, B, false);
+    0x99 => "RES 3, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(3, C, false);
+    0x9A => "RES 3, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(3, D, false);
+    0x9B => "RES 3, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(3, E, false);
+    0x9C => "RES 3, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(3, H, false);
+    0x9D => "RES 3, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(3, L, false);
+    0x9E => "RES 3, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(3, HL, false);
+    0x9F => "RES 3, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(3, A, false);
+    0xA0 => "RES 4, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(4, B, false);
+    0xA1 => "RES 4, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(4, C, false);
+    0xA2 => "RES 4, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(4, D, false);
+    0xA3 => "RES 4, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(4, E, false);
+    0xA4 => "RES 4, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(4, H, false);
+    0xA5 => "RES 4, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(4, L, false);
+    0xA6 => "RES 4, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(4, HL, false);
+    0xA7 => "RES 4, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(4, A, false);
+    0xA8 => "RES 5, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(5, B, false);
+    0xA9 => "RES 5, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(5, C, false);
+    0xAA => "RES 5, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(5, D, false);
+    0xAB => "RES 5, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(5, E, false);
+    0xAC => "RES 5, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(5, H, false);
+    0xAD => "RES 5, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(5, L, false);
+    0xAE => "RES 5, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(5, HL, false);
+    0xAF => "RES 5, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(5, A, false);
+    0xB0 => "RES 6, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(6, B, false);
+    0xB1 => "RES 6, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(6, C, false);
+    0xB2 => "RES 6, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(6, D, false);
+    0xB3 => "RES 6, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(6, E, false);
+    0xB4 => "RES 6, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(6, H, false);
+    0xB5 => "RES 6, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(6, L, false);
+    0xB6 => "RES 6, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(6, HL, false);
+    0xB7 => "RES 6, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(6, A, false);
+    0xB8 => "RES 7, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(7, B, false);
+    0xB9 => "RES 7, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(7, C, false);
+    0xBA => "RES 7, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(7, D, false);
+    0xBB => "RES 7, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(7, E, false);
+    0xBC => "RES 7, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(7, H, false);
+    0xBD => "RES 7, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(7, L, false);
+    0xBE => "RES 7, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(7, HL, false);
+    0xBF => "RES 7, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(7, A, false);
+    0xC0 => "SET 0, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(0, B, true);
+    0xC1 => "SET 0, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(0, C, true);
+    0xC2 => "SET 0, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(0, D, true);
+    0xC3 => "SET 0, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(0, E, true);
+    0xC4 => "SET 0, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(0, H, true);
+    0xC5 => "SET 0, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(0, L, true);
+    0xC6 => "SET 0, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(0, HL, true);
+    0xC7 => "SET 0, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(0, A, true);
+    0xC8 => "SET 1, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(1, B, true);
+    0xC9 => "SET 1, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(1, C, true);
+    0xCA => "SET 1, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(1, D, true);
+    0xCB => "SET 1, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(1, E, true);
+    0xCC => "SET 1, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(1, H, true);
+    0xCD => "SET 1, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(1, L, true);
+    0xCE => "SET 1, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(1, HL, true);
+    0xCF => "SET 1, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(1, A, true);
+    0xD0 => "SET 2, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(2, B, true);
+    0xD1 => "SET 2, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(2, C, true);
+    0xD2 => "SET 2, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(2, D, true);
+    0xD3 => "SET 2, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(2, E, true);
+    0xD4 => "SET 2, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(2, H, true);
+    0xD5 => "SET 2, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(2, L, true);
+    0xD6 => "SET 2, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(2, HL, true);
+    0xD7 => "SET 2, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(2, A, true);
+    0xD8 => "SET 3, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(3, B, true);
+    0xD9 => "SET 3, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(3, C, true);
+    0xDA => "SET 3, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(3, D, true);
+    0xDB => "SET 3, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(3, E, true);
+    0xDC => "SET 3, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(3, H, true);
+    0xDD => "SET 3, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(3, L, true);
+    0xDE => "SET 3, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(3, HL, true);
+    0xDF => "SET 3, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(3, A, true);
+    0xE0 => "SET 4, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(4, B, true);
+    0xE1 => "SET 4, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(4, C, true);
+    0xE2 => "SET 4, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(4, D, true);
+    0xE3 => "SET 4, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(4, E, true);
+    0xE4 => "SET 4, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(4, H, true);
+    0xE5 => "SET 4, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(4, L, true);
+    0xE6 => "SET 4, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(4, HL, true);
+    0xE7 => "SET 4, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(4, A, true);
+    0xE8 => "SET 5, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(5, B, true);
+    0xE9 => "SET 5, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(5, C, true);
+    0xEA => "SET 5, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(5, D, true);
+    0xEB => "SET 5, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(5, E, true);
+    0xEC => "SET 5, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(5, H, true);
+    0xED => "SET 5, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(5, L, true);
+    0xEE => "SET 5, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(5, HL, true);
+    0xEF => "SET 5, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(5, A, true);
+    0xF0 => "SET 6, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(6, B, true);
+    0xF1 => "SET 6, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(6, C, true);
+    0xF2 => "SET 6, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(6, D, true);
+    0xF3 => "SET 6, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(6, E, true);
+    0xF4 => "SET 6, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(6, H, true);
+    0xF5 => "SET 6, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(6, L, true);
+    0xF6 => "SET 6, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(6, HL, true);
+    0xF7 => "SET 6, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(6, A, true);
+    0xF8 => "SET 7, (IX+*), B",   4, 23,  0, |c, _| c.set_bit_index(7, B, true);
+    0xF9 => "SET 7, (IX+*), C",   4, 23,  0, |c, _| c.set_bit_index(7, C, true);
+    0xFA => "SET 7, (IX+*), D",   4, 23,  0, |c, _| c.set_bit_index(7, D, true);
+    0xFB => "SET 7, (IX+*), E",   4, 23,  0, |c, _| c.set_bit_index(7, E, true);
+    0xFC => "SET 7, (IX+*), H",   4, 23,  0, |c, _| c.set_bit_index(7, H, true);
+    0xFD => "SET 7, (IX+*), L",   4, 23,  0, |c, _| c.set_bit_index(7, L, true);
+    0xFE => "SET 7, (IX+*)",      4, 23,  0, |c, _| c.set_bit_index(7, HL, true);
+    0xFF => "SET 7, (IX+*), A",   4, 23,  0, |c, _| c.set_bit_index(7, A, true);
+}
+
+// Intel 8080 timing of the unprefixed opcodes. Conditional CALL & RET take 6 more T states
+// when taken, opcodes the Z80 redefined have the timing of their 8080 alias (see `i8080_alias`).
+#[rustfmt::skip]
+pub const I8080_CYCLES: [u8; 256] = [
+//  0   1   2   3   4   5   6   7   8   9   A   B   C   D   E   F
+    4, 10,  7,  5,  5,  5,  7,  4,  4, 10,  7,  5,  5,  5,  7,  4, // 0
+    4, 10,  7,  5,  5,  5,  7,  4,  4, 10,  7,  5,  5,  5,  7,  4, // 1
+    4, 10, 16,  5,  5,  5,  7,  4,  4, 10, 16,  5,  5,  5,  7,  4, // 2
+    4, 10, 13,  5, 10, 10, 10,  4,  4, 10, 13,  5,  5,  5,  7,  4, // 3
+    5,  5,  5,  5,  5,  5,  7,  5,  5,  5,  5,  5,  5,  5,  7,  5, // 4
+    5,  5,  5,  5,  5,  5,  7,  5,  5,  5,  5,  5,  5,  5,  7,  5, // 5
+    5,  5,  5,  5,  5,  5,  7,  5,  5,  5,  5,  5,  5,  5,  7,  5, // 6
+    7,  7,  7,  7,  7,  7,  7,  7,  5,  5,  5,  5,  5,  5,  7,  5, // 7
+    4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4, // 8
+    4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4, // 9
+    4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4, // A
+    4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4, // B
+    5, 10, 10, 10, 11, 11,  7, 11,  5, 10, 10, 10, 11, 17,  7, 11, // C
+    5, 10, 10, 10, 11, 11,  7, 11,  5, 10, 10, 10, 11, 17,  7, 11, // D
+    5, 10, 10, 18, 11, 11,  7, 11,  5,  5, 10,  4, 11, 17,  7, 11, // E
+    5, 10, 10,  4, 11, 11,  7, 11,  5,  5, 10,  4, 11, 17,  7, 11, // F
+];
+
+// The 8080 opcode executed for the opcodes the Z80 uses for its own instructions & prefixes.
+// EX AF, AF', DJNZ & JR are NOPs, the CB prefix is JP, EXX is RET & DD, ED & FD are CALL.
+pub fn i8080_alias(op: u8) -> u8 {
+    match op {
+        0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => 0x00,
+        0xCB => 0xC3,
+        0xD9 => 0xC9,
+        0xDD | 0xED | 0xFD => 0xCD,
+        op => op,
+    }
+}
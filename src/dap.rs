@@ -0,0 +1,487 @@
+// Debug Adapter Protocol server, for debugging from VS Code & other DAP clients. Messages
+// are JSON bodies after a "Content-Length" header, `stdio` speaks them over stdin / stdout
+// the way editors launch debug adapters.
+//
+// There are no sources, everything is addressed by memory: instruction breakpoints, the
+// call stack (`Debugger::frames`, built from CALL / RST / RET & interrupts), `readMemory`
+// & `disassemble`. Registers & flags are the variables of every frame, `evaluate` in the
+// debug console runs debugger commands (see `Debugger::command`). The machine is held from
+// the first `poll` until `configurationDone`, a `stopOnEntry` launch keeps it stopped.
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::debugger::{
+    parse_number, register, set_register, Breakpoint, Condition, Debugger, Stop, REGISTER_NAMES,
+};
+use crate::disassembler::{disassemble, Disassembly};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+
+const THREAD: u64 = 1;
+const REGISTERS: u64 = 1; // Variable references of the scopes
+const FLAGS: u64 = 2;
+
+pub struct DapServer {
+    messages: Receiver<Value>,
+    output: Box<dyn Write>,
+    seq: u64,
+    events: Vec<(&'static str, Value)>, // Sent after the current response
+    attached: bool,
+    closed: bool,  // Disconnected or the end of the input
+    running: bool, // Resumed by the client, waits for the stopped event
+    stop_on_entry: bool,
+    breakpoints: Vec<u16>, // Addresses of the instruction breakpoints
+}
+
+// Body of the next message, None at the end of the input
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        } else if line.is_empty() && length.is_some() {
+            break;
+        }
+    }
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn base64(data: &[u8]) -> String {
+    const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(DIGITS[(bits >> (18 - 6 * i) & 0x3F) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+fn stop_body(reason: &str, description: &str) -> Value {
+    json!({
+        "reason": reason,
+        "description": description,
+        "threadId": THREAD,
+        "allThreadsStopped": true,
+    })
+}
+
+fn hex(value: u16, bits: u32) -> String {
+    match bits {
+        8 => format!("0x{:02X}", value),
+        _ => format!("0x{:04X}", value),
+    }
+}
+
+// "memoryReference" / "instructionReference" plus an optional offset
+fn address(args: &Value, reference: &str) -> Result<u16, String> {
+    let text = args[reference].as_str().unwrap_or("");
+    let addr = parse_number(text).ok_or(format!("Bad address {:?}", text))?;
+    Ok(addr.wrapping_add(args["offset"].as_i64().unwrap_or(0) as u16))
+}
+
+impl DapServer {
+    pub fn stdio() -> Self {
+        Self::new(io::stdin(), io::stdout())
+    }
+
+    // Messages are read on a separate thread so the machine keeps running
+    pub fn new<R: Read + Send + 'static, W: Write + 'static>(input: R, output: W) -> Self {
+        let (sender, messages) = channel();
+        thread::spawn(move || {
+            let mut input = BufReader::new(input);
+            while let Ok(Some(body)) = read_message(&mut input) {
+                match serde_json::from_slice(&body) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => log::warn!("Bad DAP message: {}", e),
+                }
+            }
+        });
+        DapServer {
+            messages,
+            output: Box::new(output),
+            seq: 0,
+            events: Vec::new(),
+            attached: false,
+            closed: false,
+            running: false,
+            stop_on_entry: false,
+            breakpoints: Vec::new(),
+        }
+    }
+
+    pub fn connected(&self) -> bool {
+        !self.closed
+    }
+
+    pub fn poll<B: Bus>(&mut self, debugger: &mut Debugger, cpu: &mut Cpu<B>) -> io::Result<()> {
+        if !self.attached {
+            self.attached = true;
+            debugger.enabled = true;
+            debugger.pause(cpu);
+            debugger.take_stop();
+        }
+        while !self.closed {
+            match self.messages.try_recv() {
+                Ok(message) => self.message(debugger, cpu, &message)?,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.close(debugger, cpu),
+            }
+        }
+
+        if self.running && debugger.is_stopped() && !self.closed {
+            self.running = false;
+            let stop = debugger.take_stop().unwrap_or(Stop::Pause);
+            let reason = match stop {
+                Stop::Breakpoint(_) => "instruction breakpoint",
+                Stop::Watchpoint(..) => "data breakpoint",
+                Stop::Step | Stop::Frame(_) => "step",
+                Stop::Pause => "pause",
+            };
+            let report = debugger.report(cpu, &stop);
+            let description = report.lines().next().unwrap_or("");
+            self.stopped(reason, description)?;
+        }
+        Ok(())
+    }
+
+    // Tells the client the program is done, e.g. when a CP/M program warm boots
+    pub fn terminated(&mut self) -> io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.send(json!({"type": "event", "event": "terminated"}))
+    }
+
+    fn close<B: Bus>(&mut self, debugger: &mut Debugger, cpu: &mut Cpu<B>) {
+        self.closed = true;
+        self.running = false;
+        if debugger.is_stopped() {
+            debugger.resume_running(cpu);
+        }
+    }
+
+    fn message<B: Bus>(
+        &mut self,
+        debugger: &mut Debugger,
+        cpu: &mut Cpu<B>,
+        message: &Value,
+    ) -> io::Result<()> {
+        if message["type"] != "request" {
+            return Ok(());
+        }
+        let command = message["command"].as_str().unwrap_or("");
+        let result = self.request(debugger, cpu, command, &message["arguments"]);
+        let mut response = json!({
+            "type": "response",
+            "request_seq": message["seq"],
+            "command": command,
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(text) => response["message"] = Value::from(text),
+        }
+        self.send(response)?;
+        for (event, body) in std::mem::take(&mut self.events) {
+            self.send(json!({"type": "event", "event": event, "body": body}))?;
+        }
+        Ok(())
+    }
+
+    // Body of the response, the error message if the request failed
+    fn request<B: Bus>(
+        &mut self,
+        debugger: &mut Debugger,
+        cpu: &mut Cpu<B>,
+        command: &str,
+        args: &Value,
+    ) -> Result<Value, String> {
+        match command {
+            "initialize" => {
+                self.events.push(("initialized", json!({})));
+                Ok(json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsInstructionBreakpoints": true,
+                    "supportsDisassembleRequest": true,
+                    "supportsReadMemoryRequest": true,
+                    "supportsSetVariable": true,
+                    "supportsConditionalBreakpoints": true,
+                    "supportsHitConditionalBreakpoints": true,
+                    "supportsSteppingGranularity": true,
+                }))
+            }
+            "launch" | "attach" => {
+                self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                Ok(Value::Null)
+            }
+            "configurationDone" => {
+                match self.stop_on_entry {
+                    true => self.events.push(("stopped", stop_body("entry", ""))),
+                    false => debugger.resume_running(cpu),
+                }
+                Ok(Value::Null)
+            }
+            "setBreakpoints" => {
+                // Source breakpoints, there are no sources
+                let count = args["breakpoints"].as_array().map_or(0, |list| list.len());
+                let breakpoint = json!({
+                    "verified": false,
+                    "message": "Only instruction breakpoints are supported",
+                });
+                Ok(json!({ "breakpoints": vec![breakpoint; count] }))
+            }
+            "setExceptionBreakpoints" => Ok(json!({})),
+            "setInstructionBreakpoints" => Ok(self.set_breakpoints(debugger, args)),
+            "threads" => Ok(json!({"threads": [{"id": THREAD, "name": "Z80"}]})),
+            "stackTrace" => Ok(self.stack_trace(debugger, cpu, args)),
+            "scopes" => Ok(json!({"scopes": [
+                {"name": "Registers", "variablesReference": REGISTERS, "expensive": false},
+                {"name": "Flags", "variablesReference": FLAGS, "expensive": false},
+            ]})),
+            "variables" => Ok(json!({ "variables": variables(cpu, args) })),
+            "setVariable" => {
+                let name = args["name"].as_str().unwrap_or("");
+                let text = args["value"].as_str().unwrap_or("");
+                let value = parse_number(text).ok_or(format!("Bad value {:?}", text))?;
+                if args["variablesReference"] != REGISTERS || !set_register(cpu, name, value) {
+                    return Err(format!("{} can't be set", name));
+                }
+                let bits = if name.len() == 1 { 8 } else { 16 };
+                Ok(json!({ "value": hex(register(cpu, name).unwrap(), bits) }))
+            }
+            "readMemory" => {
+                let addr = address(args, "memoryReference")?;
+                let count = args["count"].as_u64().unwrap_or(0).min(0x1_0000) as u16;
+                let data: Vec<u8> = (0..count)
+                    .map(|i| cpu.bus.read8(addr.wrapping_add(i)))
+                    .collect();
+                Ok(json!({"address": hex(addr, 16), "data": base64(&data)}))
+            }
+            "disassemble" => {
+                let listing = listing(cpu, address(args, "memoryReference")?, args);
+                let instructions: Vec<Value> = listing
+                    .iter()
+                    .map(|line| {
+                        let bytes: Vec<String> =
+                            line.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                        json!({
+                            "address": hex(line.addr, 16),
+                            "instructionBytes": bytes.join(" "),
+                            "instruction": line.text,
+                        })
+                    })
+                    .collect();
+                Ok(json!({ "instructions": instructions }))
+            }
+            "continue" | "next" | "stepIn" | "stepOut" => {
+                match command {
+                    "continue" => debugger.resume_running(cpu),
+                    "next" => debugger.step_over(cpu),
+                    "stepIn" => debugger.step_into(cpu),
+                    _ => debugger.step_out(cpu)?,
+                }
+                self.running = true;
+                Ok(json!({"allThreadsContinued": true}))
+            }
+            "pause" => {
+                debugger.pause(cpu);
+                self.running = true;
+                Ok(Value::Null)
+            }
+            "evaluate" => {
+                let expression = args["expression"].as_str().unwrap_or("").trim();
+                if let Some(value) = register(cpu, expression) {
+                    let bits = if expression.len() == 1 { 8 } else { 16 };
+                    return Ok(json!({"result": hex(value, bits), "variablesReference": 0}));
+                }
+                if args["context"] != "repl" {
+                    return Err(format!("Unknown register {}", expression));
+                }
+                let output = debugger.command(cpu, expression);
+                // Commands like "c" resume the machine
+                self.running |= !debugger.is_stopped();
+                Ok(json!({"result": output.trim_end(), "variablesReference": 0}))
+            }
+            "disconnect" => {
+                self.close(debugger, cpu);
+                Ok(Value::Null)
+            }
+            _ => Err(format!("Unsupported request {}", command)),
+        }
+    }
+
+    // Replaces the instruction breakpoints, other debugger breakpoints are kept
+    fn set_breakpoints(&mut self, debugger: &mut Debugger, args: &Value) -> Value {
+        let ours = std::mem::take(&mut self.breakpoints);
+        debugger.breakpoints.retain(|bp| !ours.contains(&bp.addr));
+        let mut results = Vec::new();
+        for request in args["breakpoints"].as_array().into_iter().flatten() {
+            let addr = match address(request, "instructionReference") {
+                Ok(addr) => addr,
+                Err(e) => {
+                    results.push(json!({"verified": false, "message": e}));
+                    continue;
+                }
+            };
+            let condition = match request["condition"].as_str().map(Condition::parse) {
+                Some(Err(e)) => {
+                    results.push(json!({"verified": false, "message": e}));
+                    continue;
+                }
+                condition => condition.and_then(Result::ok),
+            };
+            let after = request["hitCondition"]
+                .as_str()
+                .and_then(|n| n.parse().ok());
+            debugger.breakpoints.push(Breakpoint {
+                addr,
+                condition,
+                after: after.unwrap_or(1),
+                hits: 0,
+            });
+            self.breakpoints.push(addr);
+            results.push(json!({
+                "verified": true,
+                "instructionReference": hex(addr, 16),
+            }));
+        }
+        json!({ "breakpoints": results })
+    }
+
+    // Frame 0 is at PC, the callers are at the return addresses of the debugger frames
+    fn stack_trace<B: Bus>(&self, debugger: &Debugger, cpu: &Cpu<B>, args: &Value) -> Value {
+        let frames = &debugger.frames;
+        let mut stack = Vec::new();
+        for i in 0..=frames.len() {
+            let pc = match i {
+                0 => cpu.reg.pc,
+                _ => {
+                    let sp = frames[frames.len() - i].sp;
+                    u16::from_le_bytes([cpu.bus.read8(sp), cpu.bus.read8(sp.wrapping_add(1))])
+                }
+            };
+            // The routine the frame is in
+            let name = match frames.len().checked_sub(i + 1).map(|i| frames[i]) {
+                Some(frame) if frame.interrupt => format!("interrupt {:04X}", frame.target),
+                Some(frame) => format!("{:04X}", frame.target),
+                None => String::from("(top)"),
+            };
+            stack.push(json!({
+                "id": i,
+                "name": name,
+                "line": 0,
+                "column": 0,
+                "instructionPointerReference": hex(pc, 16),
+            }));
+        }
+        let total = stack.len();
+        let start = (args["startFrame"].as_u64().unwrap_or(0) as usize).min(total);
+        let levels = match args["levels"].as_u64().unwrap_or(0) as usize {
+            0 => total,
+            levels => levels,
+        };
+        let stack: Vec<Value> = stack.into_iter().skip(start).take(levels).collect();
+        json!({"stackFrames": stack, "totalFrames": total})
+    }
+
+    fn stopped(&mut self, reason: &str, description: &str) -> io::Result<()> {
+        let body = stop_body(reason, description);
+        self.send(json!({"type": "event", "event": "stopped", "body": body}))
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = Value::from(self.seq);
+        let body = message.to_string();
+        write!(
+            self.output,
+            "Content-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+        self.output.flush()
+    }
+}
+
+fn variables<B: Bus>(cpu: &Cpu<B>, args: &Value) -> Vec<Value> {
+    let variable =
+        |name: &str, value: String| json!({"name": name, "value": value, "variablesReference": 0});
+    match args["variablesReference"].as_u64() {
+        Some(REGISTERS) => REGISTER_NAMES
+            .iter()
+            .map(|name| {
+                let value = register(cpu, name).unwrap();
+                let mut entry = variable(name, hex(value, if name.len() == 1 { 8 } else { 16 }));
+                // Register pairs can be opened in the memory view
+                if name.len() > 1 && !name.starts_with("AF") {
+                    entry["memoryReference"] = Value::from(hex(value, 16));
+                }
+                entry
+            })
+            .collect(),
+        Some(FLAGS) => {
+            let flags = cpu.flags.get();
+            let mut list: Vec<Value> = ["S", "Z", "Y", "H", "X", "P/V", "N", "C"]
+                .iter()
+                .enumerate()
+                .map(|(i, name)| variable(name, (flags >> (7 - i) & 1).to_string()))
+                .collect();
+            let int = &cpu.int;
+            list.push(variable("IFF1", (int.iff1 as u8).to_string()));
+            list.push(variable("IFF2", (int.iff2 as u8).to_string()));
+            list.push(variable("IM", int.mode.to_string()));
+            list
+        }
+        _ => Vec::new(),
+    }
+}
+
+// "instructionCount" instructions around `addr`, starting "instructionOffset" instructions
+// away from it. Instructions are 1 to 4 bytes, the ones before `addr` are found by
+// disassembling from 4 bytes per instruction back.
+fn listing<B: Bus>(cpu: &Cpu<B>, addr: u16, args: &Value) -> Vec<Disassembly> {
+    let count = args["instructionCount"].as_u64().unwrap_or(0).min(0x1_0000) as usize;
+    let offset = args["instructionOffset"].as_i64().unwrap_or(0);
+    let back = (-offset).clamp(0, 0x3FFF) as usize;
+
+    let mut before = Vec::new();
+    let mut pos = addr.wrapping_sub(back as u16 * 4);
+    while (1..=back * 4).contains(&(addr.wrapping_sub(pos) as usize)) {
+        let line = disassemble(&cpu.bus, pos);
+        pos = pos.wrapping_add(line.len as u16);
+        before.push(line);
+    }
+    let mut listing = before.split_off(before.len().saturating_sub(back));
+
+    let mut pos = addr;
+    for _ in 0..offset.max(0) {
+        pos = pos.wrapping_add(disassemble(&cpu.bus, pos).len as u16);
+    }
+    while listing.len() < count {
+        let line = disassemble(&cpu.bus, pos);
+        pos = pos.wrapping_add(line.len as u16);
+        listing.push(line);
+    }
+    listing.truncate(count);
+    listing
+}
@@ -0,0 +1,758 @@
+// Interactive debugger: PC breakpoints (with hit counts & conditions on registers and
+// memory), read / write / IO watchpoints, step into, over & out, running until an outer
+// stack frame is reached or to a video frame & register / memory editing.
+//
+// The machine loop (`Interconnect::execute_cpu`) calls `check` before & `after_step` after
+// every instruction while `enabled` is set, `end_frame` after every video frame. Commands
+// only change the run state, stepping goes through the regular machine loop with its timing
+// & interrupts. `command` takes the lines of the REPL, `Repl` reads them from stdin without
+// blocking the emulator.
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::disassembler::disassemble;
+use std::cell::Cell;
+use std::fmt::Write;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+// Deepest call stack tracked, older frames are dropped
+const MAX_FRAMES: usize = 1024;
+
+pub const REGISTER_NAMES: [&str; 22] = [
+    "A", "F", "B", "C", "D", "E", "H", "L", "I", "R", "AF", "BC", "DE", "HL", "IX", "IY", "SP",
+    "PC", "AF'", "BC'", "DE'", "HL'",
+];
+
+// 8 or 16 bit register by name (case insensitive), None for unknown names
+pub fn register<B: Bus>(cpu: &Cpu<B>, name: &str) -> Option<u16> {
+    let reg = &cpu.reg;
+    let pair = |high: u8, low: u8| (high as u16) << 8 | low as u16;
+    Some(match name.to_ascii_uppercase().as_str() {
+        "A" => reg.a as u16,
+        "F" => cpu.flags.get() as u16,
+        "B" => reg.b as u16,
+        "C" => reg.c as u16,
+        "D" => reg.d as u16,
+        "E" => reg.e as u16,
+        "H" => reg.h as u16,
+        "L" => reg.l as u16,
+        "I" => reg.i as u16,
+        "R" => reg.r as u16,
+        "AF" => pair(reg.a, cpu.flags.get()),
+        "BC" => pair(reg.b, reg.c),
+        "DE" => pair(reg.d, reg.e),
+        "HL" => pair(reg.h, reg.l),
+        "IX" => reg.ix,
+        "IY" => reg.iy,
+        "SP" => reg.sp,
+        "PC" => reg.pc,
+        "AF'" => pair(reg.a_, cpu.flags.get_shadow()),
+        "BC'" => pair(reg.b_, reg.c_),
+        "DE'" => pair(reg.d_, reg.e_),
+        "HL'" => pair(reg.h_, reg.l_),
+        _ => return None,
+    })
+}
+
+// False for unknown names, 8 bit registers take the low byte of `value`
+pub fn set_register<B: Bus>(cpu: &mut Cpu<B>, name: &str, value: u16) -> bool {
+    let (high, low) = ((value >> 8) as u8, value as u8);
+    let reg = &mut cpu.reg;
+    match name.to_ascii_uppercase().as_str() {
+        "A" => reg.a = low,
+        "F" => cpu.flags.set(low),
+        "B" => reg.b = low,
+        "C" => reg.c = low,
+        "D" => reg.d = low,
+        "E" => reg.e = low,
+        "H" => reg.h = low,
+        "L" => reg.l = low,
+        "I" => reg.i = low,
+        "R" => reg.r = low,
+        "AF" => {
+            reg.a = high;
+            cpu.flags.set(low);
+        }
+        "BC" => (reg.b, reg.c) = (high, low),
+        "DE" => (reg.d, reg.e) = (high, low),
+        "HL" => (reg.h, reg.l) = (high, low),
+        "IX" => reg.ix = value,
+        "IY" => reg.iy = value,
+        "SP" => reg.sp = value,
+        "PC" => reg.pc = value,
+        "AF'" => {
+            reg.a_ = high;
+            cpu.flags.set_shadow(low);
+        }
+        "BC'" => (reg.b_, reg.c_) = (high, low),
+        "DE'" => (reg.d_, reg.e_) = (high, low),
+        "HL'" => (reg.h_, reg.l_) = (high, low),
+        _ => return false,
+    }
+    true
+}
+
+// Hex by default, "$" / "0x" prefixes & a "h" suffix are accepted as well
+pub fn parse_number(text: &str) -> Option<u16> {
+    let text = text.trim();
+    let digits = text
+        .strip_prefix('$')
+        .or_else(|| text.strip_prefix("0x"))
+        .or_else(|| text.strip_suffix(['h', 'H']))
+        .unwrap_or(text);
+    u16::from_str_radix(digits, 16).ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Register(&'static str),
+    Memory(u16), // Byte at the address
+}
+
+// Comparison terms joined by "&&", e.g. "A==3F && (4E00)!=0 && HL>=4000"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    text: String,
+    terms: Vec<(Operand, &'static str, u16)>,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut terms = Vec::new();
+        for term in text.split("&&") {
+            let (at, op) = ["==", "!=", "<=", ">=", "<", ">"]
+                .iter()
+                .find_map(|op| term.find(op).map(|at| (at, *op)))
+                .ok_or_else(|| format!("No comparison in \"{}\"", term.trim()))?;
+            let (lhs, rhs) = (term[..at].trim(), term[at + op.len()..].trim());
+            let operand = match lhs.strip_prefix('(').and_then(|m| m.strip_suffix(')')) {
+                Some(addr) => Operand::Memory(parse_number(addr).ok_or("Bad address")?),
+                None => Operand::Register(
+                    REGISTER_NAMES
+                        .iter()
+                        .find(|name| name.eq_ignore_ascii_case(lhs))
+                        .ok_or_else(|| format!("Unknown register {}", lhs))?,
+                ),
+            };
+            let value = parse_number(rhs).ok_or_else(|| format!("Bad value {}", rhs))?;
+            terms.push((operand, op, value));
+        }
+        Ok(Condition {
+            text: text.trim().to_string(),
+            terms,
+        })
+    }
+
+    pub fn eval<B: Bus>(&self, cpu: &Cpu<B>) -> bool {
+        self.terms.iter().all(|&(operand, op, value)| {
+            let lhs = match operand {
+                Operand::Register(name) => register(cpu, name).unwrap_or(0),
+                Operand::Memory(addr) => cpu.bus.read8(addr) as u16,
+            };
+            match op {
+                "==" => lhs == value,
+                "!=" => lhs != value,
+                "<=" => lhs <= value,
+                ">=" => lhs >= value,
+                "<" => lhs < value,
+                _ => lhs > value,
+            }
+        })
+    }
+}
+
+pub struct Breakpoint {
+    pub addr: u16,
+    pub condition: Option<Condition>,
+    pub after: u32, // Execution stops from this hit on
+    pub hits: u32,  // Times PC reached `addr` with the condition met
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read, // Memory reads, including opcode fetches
+    Write,
+    Io, // Port reads & writes, matched on the low byte of the port
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub access: Access,
+    pub start: u16,
+    pub end: u16, // Inclusive
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub index: usize,
+    pub addr: u16,
+    pub value: u8,
+}
+
+// Checked by the CPU on every memory & port access while the list isn't empty. Only the
+// first hit of an instruction is kept.
+#[derive(Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    hit: Cell<Option<WatchHit>>,
+}
+
+impl Watchpoints {
+    pub fn add(&mut self, watchpoint: Watchpoint) -> usize {
+        self.list.push(watchpoint);
+        self.list.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> bool {
+        let found = index < self.list.len();
+        if found {
+            self.list.remove(index);
+        }
+        found
+    }
+
+    pub fn list(&self) -> &[Watchpoint] {
+        &self.list
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn check(&self, access: Access, addr: u16, value: u8) {
+        if self.hit.get().is_some() {
+            return;
+        }
+        let addr = match access {
+            Access::Io => addr & 0xFF,
+            _ => addr,
+        };
+        let index = self
+            .list
+            .iter()
+            .position(|w| w.access == access && (w.start..=w.end).contains(&addr));
+        if let Some(index) = index {
+            self.hit.set(Some(WatchHit { index, addr, value }));
+        }
+    }
+
+    pub fn take_hit(&self) -> Option<WatchHit> {
+        self.hit.take()
+    }
+}
+
+// Call stack entry, pushed by CALL / RST & accepted interrupts, popped once SP moves above
+// the return address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub call: u16,   // Address of the CALL / RST or the interrupted instruction
+    pub target: u16, // Entry point of the routine
+    pub sp: u16,     // Where the return address is stored
+    pub interrupt: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint(usize),
+    Watchpoint(WatchHit, u16), // & the PC of the accessing instruction
+    Step,
+    Pause,
+    Frame(u32), // Video frame N has been completed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Run {
+    Running,
+    Stopped,
+    Step,
+    Over { pc: u16, sp: u16 }, // Until PC returns to `pc` in the same (or an outer) frame
+    Out { sp: u16 },           // Until the return address at `sp` has been popped
+    Frame(u32),                // Until video frame N has been completed
+}
+
+pub struct Debugger {
+    pub enabled: bool,
+    pub breakpoints: Vec<Breakpoint>,
+    pub frames: Vec<Frame>, // Innermost last
+    pub frame: Option<u32>, // Video frames completed, None for machines without video
+    run: Run,
+    skip: Option<u16>, // Breakpoints at this PC are ignored once when resuming
+    stop: Option<Stop>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            enabled: false,
+            breakpoints: Vec::new(),
+            frames: Vec::new(),
+            frame: None,
+            run: Run::Running,
+            skip: None,
+            stop: None,
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.run == Run::Stopped
+    }
+
+    // Reason of the last stop, once
+    pub fn take_stop(&mut self) -> Option<Stop> {
+        self.stop.take()
+    }
+
+    fn halt<B: Bus>(&mut self, cpu: &mut Cpu<B>, stop: Stop) {
+        self.run = Run::Stopped;
+        self.stop = Some(stop);
+        cpu.breakpoint = true;
+    }
+
+    fn resume<B: Bus>(&mut self, cpu: &mut Cpu<B>, run: Run) {
+        self.run = run;
+        self.skip = Some(cpu.reg.pc);
+        cpu.breakpoint = false;
+    }
+
+    // Before every instruction, true if execution has to stop
+    pub fn check<B: Bus>(&mut self, cpu: &mut Cpu<B>) -> bool {
+        if self.run == Run::Stopped {
+            return true;
+        }
+        let pc = cpu.reg.pc;
+        if self.skip.take() == Some(pc) || cpu.halted {
+            return false;
+        }
+        let hit = self.breakpoints.iter_mut().position(|bp| {
+            if bp.addr != pc || !bp.condition.as_ref().is_none_or(|c| c.eval(cpu)) {
+                return false;
+            }
+            bp.hits += 1;
+            bp.hits >= bp.after
+        });
+        if let Some(index) = hit {
+            self.halt(cpu, Stop::Breakpoint(index));
+        }
+        hit.is_some()
+    }
+
+    // After every instruction, `pc` & `sp` as they were before it
+    pub fn after_step<B: Bus>(&mut self, cpu: &mut Cpu<B>, pc: u16, sp: u16) {
+        let op = cpu.bus.read8(pc);
+        let call = op == 0xCD || op & 0xC7 == 0xC4 || op & 0xC7 == 0xC7;
+        if call && cpu.reg.sp == sp.wrapping_sub(2) {
+            self.push_frame(cpu, pc, false);
+        } else {
+            while self.frames.last().is_some_and(|f| cpu.reg.sp > f.sp) {
+                self.frames.pop();
+            }
+        }
+
+        if let Some(hit) = cpu.watch.take_hit() {
+            return self.halt(cpu, Stop::Watchpoint(hit, pc));
+        }
+        let done = match self.run {
+            Run::Step => true,
+            Run::Over { pc, sp } => cpu.reg.pc == pc && cpu.reg.sp >= sp,
+            Run::Out { sp } => cpu.reg.sp > sp,
+            Run::Running | Run::Stopped | Run::Frame(_) => false,
+        };
+        if done {
+            self.halt(cpu, Stop::Step);
+        }
+    }
+
+    // After every video frame, `frame` is the number of frames completed
+    pub fn end_frame<B: Bus>(&mut self, cpu: &mut Cpu<B>, frame: u32) {
+        self.frame = Some(frame);
+        if let Run::Frame(target) = self.run {
+            if frame >= target {
+                self.halt(cpu, Stop::Frame(frame));
+            }
+        }
+    }
+
+    // After an accepted interrupt, `pc` is the address execution will return to
+    pub fn after_interrupt<B: Bus>(&mut self, cpu: &mut Cpu<B>, pc: u16) {
+        self.push_frame(cpu, pc, true);
+    }
+
+    fn push_frame<B: Bus>(&mut self, cpu: &Cpu<B>, call: u16, interrupt: bool) {
+        if self.frames.len() == MAX_FRAMES {
+            self.frames.remove(0);
+        }
+        self.frames.push(Frame {
+            call,
+            target: cpu.reg.pc,
+            sp: cpu.reg.sp,
+            interrupt,
+        });
+    }
+
+    pub fn pause<B: Bus>(&mut self, cpu: &mut Cpu<B>) {
+        if self.run != Run::Stopped {
+            self.halt(cpu, Stop::Pause);
+        }
+    }
+
+    pub fn resume_running<B: Bus>(&mut self, cpu: &mut Cpu<B>) {
+        self.resume(cpu, Run::Running);
+    }
+
+    pub fn step_into<B: Bus>(&mut self, cpu: &mut Cpu<B>) {
+        self.resume(cpu, Run::Step);
+    }
+
+    // CALL, RST & repeating block instructions run until the next instruction is reached
+    pub fn step_over<B: Bus>(&mut self, cpu: &mut Cpu<B>) {
+        let instruction = disassemble(&cpu.bus, cpu.reg.pc);
+        let op = instruction.bytes[0];
+        let call = op == 0xCD || op & 0xC7 == 0xC4 || op & 0xC7 == 0xC7;
+        let repeat = op == 0xED && instruction.bytes[1] & 0xF4 == 0xB0;
+        let run = match call || repeat {
+            true => Run::Over {
+                pc: cpu.reg.pc.wrapping_add(instruction.len as u16),
+                sp: cpu.reg.sp,
+            },
+            false => Run::Step,
+        };
+        self.resume(cpu, run);
+    }
+
+    pub fn step_out<B: Bus>(&mut self, cpu: &mut Cpu<B>) -> Result<(), String> {
+        self.finish_to(cpu, 1)
+    }
+
+    // Runs until the `depth` innermost stack frames have returned (frame `depth` in
+    // `backtrace`)
+    pub fn finish_to<B: Bus>(&mut self, cpu: &mut Cpu<B>, depth: usize) -> Result<(), String> {
+        if depth == 0 || depth > self.frames.len() {
+            return Err(format!("No frame {}", depth));
+        }
+        let sp = self.frames[self.frames.len() - depth].sp;
+        self.resume(cpu, Run::Out { sp });
+        Ok(())
+    }
+
+    // Runs until video frame `frame` has been completed, by default the current one
+    pub fn run_to_frame<B: Bus>(
+        &mut self,
+        cpu: &mut Cpu<B>,
+        frame: Option<u32>,
+    ) -> Result<(), String> {
+        let current = self.frame.ok_or("No video frames on this machine")?;
+        let target = frame.unwrap_or(current + 1);
+        if target <= current {
+            return Err(format!("Frame {} is already done", target));
+        }
+        self.resume(cpu, Run::Frame(target));
+        Ok(())
+    }
+
+    // Frame 0 is the current PC
+    pub fn backtrace<B: Bus>(&self, cpu: &Cpu<B>) -> String {
+        let mut out = format!("#0  {:04X}\n", cpu.reg.pc);
+        for (i, frame) in self.frames.iter().rev().enumerate() {
+            let ret = u16::from_le_bytes([
+                cpu.bus.read8(frame.sp),
+                cpu.bus.read8(frame.sp.wrapping_add(1)),
+            ]);
+            let kind = if frame.interrupt { "interrupt" } else { "call" };
+            writeln!(
+                out,
+                "#{}  {:04X}  {} {:04X} from {:04X}",
+                i + 1,
+                ret,
+                kind,
+                frame.target,
+                frame.call
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    pub fn registers<B: Bus>(&self, cpu: &Cpu<B>) -> String {
+        let mut out = String::new();
+        for (i, name) in REGISTER_NAMES[10..].iter().enumerate() {
+            let separator = if i == 7 { '\n' } else { ' ' };
+            write!(
+                out,
+                "{}={:04X}{}",
+                name,
+                register(cpu, name).unwrap(),
+                separator
+            )
+            .unwrap();
+        }
+        let flags: String = "SZYHXPNC"
+            .chars()
+            .enumerate()
+            .map(|(i, c)| match cpu.flags.get() & (0x80 >> i) {
+                0 => '-',
+                _ => c,
+            })
+            .collect();
+        let int = &cpu.int;
+        writeln!(
+            out,
+            "I={:02X} R={:02X} IFF1={} IFF2={} IM={} {}",
+            cpu.reg.i, cpu.reg.r, int.iff1 as u8, int.iff2 as u8, int.mode, flags
+        )
+        .unwrap();
+        out
+    }
+
+    // Description of a stop with the next instruction
+    pub fn report<B: Bus>(&self, cpu: &Cpu<B>, stop: &Stop) -> String {
+        let reason = match stop {
+            Stop::Breakpoint(index) => {
+                let bp = &self.breakpoints[*index];
+                format!("Breakpoint {} at {:04X}, hit {}", index, bp.addr, bp.hits)
+            }
+            Stop::Watchpoint(hit, pc) => format!(
+                "Watchpoint {}: {:?} {:04X} = {:02X} by the instruction at {:04X}",
+                hit.index,
+                cpu.watch.list()[hit.index].access,
+                hit.addr,
+                hit.value,
+                pc
+            ),
+            Stop::Step => String::from("Stopped"),
+            Stop::Pause => String::from("Paused"),
+            Stop::Frame(frame) => format!("Frame {} done", frame),
+        };
+        format!("{}\n{}\n", reason, disassemble(&cpu.bus, cpu.reg.pc))
+    }
+
+    pub fn list(&self, cpu: &Cpu<impl Bus>) -> String {
+        let mut out = String::new();
+        for (i, bp) in self.breakpoints.iter().enumerate() {
+            write!(out, "Breakpoint {} at {:04X}", i, bp.addr).unwrap();
+            if bp.after > 1 {
+                write!(out, " from hit {}", bp.after).unwrap();
+            }
+            if let Some(condition) = &bp.condition {
+                write!(out, " if {}", condition.text).unwrap();
+            }
+            writeln!(out, ", {} hits", bp.hits).unwrap();
+        }
+        for (i, w) in cpu.watch.list().iter().enumerate() {
+            let line = format!(
+                "Watchpoint {} {:?} {:04X}-{:04X}",
+                i, w.access, w.start, w.end
+            );
+            writeln!(out, "{}", line).unwrap();
+        }
+        out
+    }
+
+    // Executes a REPL command line & returns its output
+    pub fn command<B: Bus>(&mut self, cpu: &mut Cpu<B>, line: &str) -> String {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let arg = |i: usize| words.get(i).and_then(|word| parse_number(word));
+        let count =
+            |i: usize, default: usize| words.get(i).map_or(Some(default), |word| word.parse().ok());
+        let result: Result<String, String> = match words.as_slice() {
+            [] => Ok(String::new()),
+            ["help" | "h" | "?", ..] => Ok(String::from(HELP)),
+            ["b" | "break", addr, rest @ ..] => parse_number(addr)
+                .ok_or_else(|| format!("Bad address {}", addr))
+                .and_then(|addr| self.add_breakpoint(addr, rest)),
+            ["d" | "delete", _] => match count(1, 0) {
+                Some(i) if i < self.breakpoints.len() => {
+                    self.breakpoints.remove(i);
+                    Ok(String::new())
+                }
+                _ => Err(String::from("No such breakpoint")),
+            },
+            ["w" | "watch", kind, range] => {
+                let access = match *kind {
+                    "r" => Some(Access::Read),
+                    "w" => Some(Access::Write),
+                    "io" => Some(Access::Io),
+                    _ => None,
+                };
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                match (access, parse_number(start), parse_number(end)) {
+                    (Some(access), Some(start), Some(end)) if start <= end => {
+                        let index = cpu.watch.add(Watchpoint { access, start, end });
+                        Ok(format!("Watchpoint {}\n", index))
+                    }
+                    _ => Err(String::from("Usage: w r|w|io START[-END]")),
+                }
+            }
+            ["dw", _] => match count(1, 0) {
+                Some(i) if cpu.watch.remove(i) => Ok(String::new()),
+                _ => Err(String::from("No such watchpoint")),
+            },
+            ["info" | "l"] => Ok(self.list(cpu)),
+            ["c" | "continue"] => {
+                self.resume_running(cpu);
+                Ok(String::new())
+            }
+            ["s" | "step"] => {
+                self.step_into(cpu);
+                Ok(String::new())
+            }
+            ["n" | "next"] => {
+                self.step_over(cpu);
+                Ok(String::new())
+            }
+            ["finish" | "out"] => self.step_out(cpu).map(|_| String::new()),
+            ["up", _] => match count(1, 0) {
+                Some(depth) => self.finish_to(cpu, depth).map(|_| String::new()),
+                None => Err(String::from("Usage: up N")),
+            },
+            ["frame", ..] => match words.get(1).map(|word| word.parse().ok()) {
+                Some(None) => Err(String::from("Usage: frame [N]")),
+                frame => self
+                    .run_to_frame(cpu, frame.flatten())
+                    .map(|_| String::new()),
+            },
+            ["pause"] => {
+                self.pause(cpu);
+                Ok(String::new())
+            }
+            ["bt" | "backtrace"] => Ok(self.backtrace(cpu)),
+            ["r" | "regs"] => Ok(self.registers(cpu)),
+            ["set", name, value] => match parse_number(value) {
+                Some(value) if set_register(cpu, name, value) => Ok(self.registers(cpu)),
+                _ => Err(format!(
+                    "Usage: set REG VALUE ({})",
+                    REGISTER_NAMES.join(" ")
+                )),
+            },
+            ["x", _, ..] => match (arg(1), count(2, 16)) {
+                (Some(addr), Some(len)) => Ok(dump(cpu, addr, len)),
+                _ => Err(String::from("Usage: x ADDR [COUNT]")),
+            },
+            ["poke", _, bytes @ ..] if !bytes.is_empty() => {
+                let bytes: Option<Vec<u16>> = bytes.iter().map(|b| parse_number(b)).collect();
+                match (arg(1), bytes) {
+                    (Some(addr), Some(bytes)) => {
+                        for (i, &byte) in bytes.iter().enumerate() {
+                            cpu.bus.write8(addr.wrapping_add(i as u16), byte as u8);
+                        }
+                        Ok(dump(cpu, addr, bytes.len()))
+                    }
+                    _ => Err(String::from("Usage: poke ADDR BYTE...")),
+                }
+            }
+            ["dis", ..] => match (
+                words.get(1).map_or(Some(cpu.reg.pc), |_| arg(1)),
+                count(2, 8),
+            ) {
+                (Some(mut addr), Some(count)) => {
+                    let mut out = String::new();
+                    for _ in 0..count {
+                        let instruction = disassemble(&cpu.bus, addr);
+                        addr = addr.wrapping_add(instruction.len as u16);
+                        writeln!(out, "{}", instruction).unwrap();
+                    }
+                    Ok(out)
+                }
+                _ => Err(String::from("Usage: dis [ADDR] [COUNT]")),
+            },
+            _ => Err(format!("Unknown command \"{}\", try help", line.trim())),
+        };
+        result.unwrap_or_else(|e| e + "\n")
+    }
+
+    // "b ADDR [hits N] [if CONDITION]"
+    fn add_breakpoint(&mut self, addr: u16, rest: &[&str]) -> Result<String, String> {
+        let (after, rest) = match rest {
+            ["hits", n, rest @ ..] => (n.parse().map_err(|_| "Bad hit count")?, rest),
+            _ => (1, rest),
+        };
+        let condition = match rest {
+            [] => None,
+            ["if", condition @ ..] => Some(Condition::parse(&condition.join(" "))?),
+            _ => return Err(String::from("Usage: b ADDR [hits N] [if CONDITION]")),
+        };
+        self.breakpoints.push(Breakpoint {
+            addr,
+            condition,
+            after,
+            hits: 0,
+        });
+        Ok(format!("Breakpoint {}\n", self.breakpoints.len() - 1))
+    }
+
+    // Runs the commands entered since the last call, returns their output & any new stop
+    pub fn poll_repl<B: Bus>(&mut self, cpu: &mut Cpu<B>, repl: &Repl) -> String {
+        let mut out = String::new();
+        while let Some(line) = repl.poll() {
+            out.push_str(&self.command(cpu, &line));
+        }
+        if let Some(stop) = self.take_stop() {
+            out.push_str(&self.report(cpu, &stop));
+        }
+        out
+    }
+}
+
+fn dump<B: Bus>(cpu: &Cpu<B>, addr: u16, len: usize) -> String {
+    let mut out = String::new();
+    for line in 0..len.div_ceil(16) {
+        let start = addr.wrapping_add(line as u16 * 16);
+        write!(out, "{:04X} ", start).unwrap();
+        for i in 0..(len - line * 16).min(16) {
+            write!(out, " {:02X}", cpu.bus.read8(start.wrapping_add(i as u16))).unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
+
+const HELP: &str = "\
+b ADDR [hits N] [if COND]  breakpoint, COND like A==3F && (4E00)!=0
+d N                        delete breakpoint N
+w r|w|io START[-END]       read / write / port watchpoint
+dw N                       delete watchpoint N
+info                       list breakpoints & watchpoints
+c                          continue
+s, n, finish               step into, over & out
+up N                       run until frame N of the backtrace is reached
+frame [N]                  run to the end of this video frame (or of frame N)
+pause                      stop a running machine
+bt                         backtrace
+r                          registers
+set REG VALUE              edit a register
+x ADDR [COUNT]             memory dump
+poke ADDR BYTE...          edit memory
+dis [ADDR] [COUNT]         disassemble
+Addresses & values are hex, counts decimal.
+";
+
+// Debugger commands from stdin, read on a separate thread so the machine keeps running
+pub struct Repl {
+    lines: Receiver<String>,
+}
+
+impl Repl {
+    pub fn stdin() -> Self {
+        let (sender, lines) = channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Repl { lines }
+    }
+
+    // Next line entered, None if there is nothing new
+    pub fn poll(&self) -> Option<String> {
+        self.lines.try_recv().ok()
+    }
+}
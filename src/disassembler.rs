@@ -0,0 +1,114 @@
+// Disassembler working on any `Bus`, driven by the same opcode tables as the CPU.
+// Placeholders in the table mnemonics are resolved from the bytes following the opcode:
+// `**` is a 16 bit immediate or address, `(IX+*)` a signed displacement, `*` after JR/DJNZ
+// the relative jump target & any other `*` an 8 bit immediate.
+use crate::bus::Bus;
+use crate::cpu::opcodes::{OpcodeInfo, CB, ED, ED_NOP, INDEX, INDEX_BIT, MAIN};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub addr: u16,
+    pub len: u8,
+    pub bytes: Vec<u8>,
+    pub text: String, // e.g. "JR NZ,$0A3C" or "LD (IX+$05),$FF"
+}
+
+// Listing line: address, raw bytes & the instruction
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(f, "{:04X}  {:<12}{}", self.addr, bytes.join(" "), self.text)
+    }
+}
+
+pub fn disassemble<B: Bus>(bus: &B, addr: u16) -> Disassembly {
+    let byte = |offset: u16| bus.read8(addr.wrapping_add(offset));
+
+    // Table entry, index register name & offset of the first operand byte
+    let (info, ir, operand) = match byte(0) {
+        0xCB => (CB[byte(1) as usize], "HL", 2),
+        0xED => (ED[byte(1) as usize].or(Some(ED_NOP)), "HL", 2),
+        prefix @ (0xDD | 0xFD) => {
+            let ir = if prefix == 0xDD { "IX" } else { "IY" };
+            match byte(1) {
+                0xCB => (INDEX_BIT[byte(3) as usize], ir, 2),
+                op => (INDEX[op as usize], ir, 2),
+            }
+        }
+        op => (MAIN[op as usize], "HL", 1),
+    };
+
+    // A DD/FD prefix in front of an opcode without index form is ignored by the CPU,
+    // list it on its own so the following instruction decodes normally
+    let info = info.unwrap_or(OpcodeInfo {
+        name: "NOP*",
+        bytes: 1,
+        cycles: 4,
+        alt_cycles: 0,
+    });
+
+    let bytes: Vec<u8> = (0..info.bytes as u16).map(byte).collect();
+    let name = info.name.replace("IX", ir);
+    let mut text = String::new();
+    let mut rest = name.as_str();
+    let mut pos = operand;
+
+    while let Some(i) = rest.find('*') {
+        text.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        // Undocumented instructions are marked with a trailing `*`, e.g. "NOP*"
+        if text.ends_with(|c: char| c.is_ascii_alphanumeric()) {
+            text.push('*');
+            rest = &rest[1..];
+            continue;
+        }
+        if rest.starts_with("**") {
+            let word = (byte(pos + 1) as u16) << 8 | byte(pos) as u16;
+            text.push_str(&format!("${:04X}", word));
+            pos += 2;
+            rest = &rest[2..];
+            continue;
+        }
+
+        let n = byte(pos);
+        if text.ends_with('+') {
+            let d = n as i8;
+            text.pop();
+            let sign = if d < 0 { '-' } else { '+' };
+            text.push_str(&format!("{}${:02X}", sign, d.unsigned_abs()));
+        } else if name.starts_with("JR") || name.starts_with("DJNZ") {
+            let target = addr
+                .wrapping_add(info.bytes as u16)
+                .wrapping_add(n as i8 as u16);
+            text.push_str(&format!("${:04X}", target));
+        } else {
+            text.push_str(&format!("${:02X}", n));
+        }
+        pos += 1;
+        rest = &rest[1..];
+    }
+    text.push_str(rest);
+
+    Disassembly {
+        addr,
+        len: info.bytes,
+        bytes,
+        text: text.replace(", ", ","),
+    }
+}
+
+// Lists every instruction starting between `start` & `end` (inclusive), the last one
+// may extend past `end`
+pub fn disassemble_range<B: Bus>(bus: &B, start: u16, end: u16) -> Vec<Disassembly> {
+    let mut listing = Vec::new();
+    let mut addr = start as u32;
+
+    while addr <= end as u32 {
+        let line = disassemble(bus, addr as u16);
+        addr += line.len as u32;
+        listing.push(line);
+    }
+    listing
+}
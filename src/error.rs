@@ -0,0 +1,86 @@
+use std::fmt;
+
+// Errors reported by `Cpu::step` & `Cpu::poll_interrupt` instead of panicking. Unknown
+// opcodes & unsupported interrupts are reported before anything happens: PC, R & the T
+// states are left at the start of the instruction and the interrupt isn't acknowledged.
+// Operand faults (`Unimplemented` from a table entry) are only noticed while the handler
+// runs, registers, memory & ports may be partially updated and PC is past the instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpuError {
+    // Opcode bytes (including any prefixes) the decoder has no entry for
+    UnknownOpcode { pc: u16, bytes: Vec<u8> },
+    // Valid instructions or CPU features the emulator does not support (yet), also opcode
+    // table entries with operands their handler doesn't take
+    Unimplemented { mnemonic: String },
+    // System calls of a harness (BDOS / BIOS) made with arguments they can't work with
+    BadCall { call: String, reason: String },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::UnknownOpcode { pc, bytes } => {
+                write!(f, "Unknown opcode at {:04X}:", pc)?;
+                for byte in bytes {
+                    write!(f, " {:02X}", byte)?;
+                }
+                Ok(())
+            }
+            CpuError::Unimplemented { mnemonic } => write!(f, "Unimplemented: {}", mnemonic),
+            CpuError::BadCall { call, reason } => write!(f, "{}: {}", call, reason),
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
+// Errors reported by `assembler::assemble`. `line` is the 1 based source line, for macro
+// expansions the line invoking the macro.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// Errors reported when loading a save state (`savestate::load`). A failed load leaves the
+// machine as it was.
+#[derive(Debug)]
+pub enum StateError {
+    Io(std::io::Error),
+    NotAState,
+    // Written by another version of the emulator, the layout differs
+    Version { found: u16, expected: u16 },
+    // The file ended early or has a section of the wrong size
+    Corrupt(String),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::Io(e) => write!(f, "{}", e),
+            StateError::NotAState => write!(f, "Not a save state"),
+            StateError::Version { found, expected } => write!(
+                f,
+                "Save state version {} is incompatible with this build (version {})",
+                found, expected
+            ),
+            StateError::Corrupt(message) => write!(f, "Corrupt save state: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl From<std::io::Error> for StateError {
+    fn from(e: std::io::Error) -> Self {
+        StateError::Io(e)
+    }
+}
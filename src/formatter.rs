@@ -1,4 +1,6 @@
+use crate::bus::Bus;
 use crate::cpu::{Cpu, Registers};
+use crate::disassembler::disassemble;
 use crate::memory::MemoryRW;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter, Result};
@@ -16,9 +18,8 @@ impl Display for Registers {
     }
 }
 
-impl Debug for Cpu {
+impl<B: Bus> Debug for Cpu<B> {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
-        fmt.align();
         write!(fmt, "PC: {:>04X}, ", self.reg.pc)?;
         write!(fmt, "AF: {:>02X}{:02X}, ", self.reg.a, self.flags.get())?;
         write!(fmt, "BC: {:>02X}{:02X}, ", self.reg.b, self.reg.c)?;
@@ -27,8 +28,8 @@ impl Debug for Cpu {
         write!(fmt, "SP: {:>04X}, ", self.reg.sp)?;
         write!(fmt, "IX: {:>04X}, ", self.reg.ix)?;
         write!(fmt, "IY: {:>04X}, ", self.reg.iy)?;
-        write!(fmt, "I: {:02X}, ", self.int.int as u8)?;
-        write!(fmt, "R: {:02X}\t", self.reg.r as u8)?;
+        write!(fmt, "I: {:02X}, ", self.reg.i)?;
+        write!(fmt, "R: {:02X}\t", { self.reg.r })?;
         write!(
             fmt,
             "({:02X} {:02X} {:02X} {:02X}), ",
@@ -40,10 +41,10 @@ impl Debug for Cpu {
         write!(fmt, "cyc: {}", self.cycles)
     }
 }
-impl Display for Cpu {
+impl<B: Bus> Display for Cpu<B> {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
-        fmt.align();
-        write!(fmt, "{:w$}", &self.current_instruction, w = 12)?;
+        let instruction = disassemble(&self.bus, self.reg.pc);
+        write!(fmt, "{:w$}", instruction.text, w = 20)?;
         write!(
             fmt,
             "({:02X} {:02X} {:02X} {:02X})\t",
@@ -67,12 +68,12 @@ impl Display for Cpu {
         write!(fmt, "P:{} ", self.flags.pf as u8)?;
         write!(fmt, "C:{} ", self.flags.cf as u8)?;
         write!(fmt, "H:{} ", self.flags.hf as u8)?;
-        write!(fmt, "I:{}", self.int.int as u8)
+        write!(fmt, "I:{}", self.int.iff1 as u8)
     }
 }
 
 /*// TODO Refactor the above to fit this style
-impl Debug for Cpu {
+impl<B: Bus> Debug for Cpu<B> {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
         writeln!(
             fmt,
@@ -0,0 +1,337 @@
+// GDB remote serial protocol stub, for attaching gdb-multiarch (`set architecture z80`,
+// `target remote localhost:PORT`) or z88dk-gdb to a running machine.
+//
+// The machine loop calls `poll`, which accepts a client, answers its packets & sends the
+// stop reply once the debugger stops after `c` / `s`. Execution control goes through the
+// `Debugger`: Z0 / z0 are its PC breakpoints, Z2 / Z3 write & read watchpoints. Attaching
+// stops the machine, detaching lets it run again.
+//
+// Registers (`g` / `G`) use the layout of gdb's z80 target, 16 bit little endian each:
+// AF BC DE HL SP PC IX IY AF' BC' DE' HL' IR
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::debugger::{register, set_register, Access, Breakpoint, Debugger, Stop, Watchpoint};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+const REGISTERS: [&str; 12] = [
+    "AF", "BC", "DE", "HL", "SP", "PC", "IX", "IY", "AF'", "BC'", "DE'", "HL'",
+];
+
+pub struct GdbStub {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    input: Vec<u8>, // Received bytes not handled yet
+    running: bool,  // `c` or `s` waits for the stop reply
+}
+
+fn checksum(data: &str) -> u8 {
+    data.bytes().fold(0, |sum, b| sum.wrapping_add(b))
+}
+
+fn hex_bytes(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// "ADDR,LEN" of m, M & Z packets
+fn addr_len(text: &str) -> Option<(u16, u16)> {
+    let (addr, len) = text.split_once(',')?;
+    let addr = u16::from_str_radix(addr, 16).ok()?;
+    Some((addr, u16::from_str_radix(len, 16).ok()?))
+}
+
+impl GdbStub {
+    // Port 0 picks a free port, see `local_addr`
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(GdbStub {
+            listener,
+            client: None,
+            input: Vec::new(),
+            running: false,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    pub fn poll<B: Bus>(&mut self, debugger: &mut Debugger, cpu: &mut Cpu<B>) -> io::Result<()> {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.client = Some(stream);
+                    self.input.clear();
+                    self.running = false;
+                    debugger.enabled = true;
+                    debugger.pause(cpu);
+                    debugger.take_stop();
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut buf = [0; 1024];
+        loop {
+            let read = match self.client.as_mut() {
+                Some(client) => client.read(&mut buf),
+                None => return Ok(()),
+            };
+            match read {
+                Ok(0) => {
+                    self.disconnect(debugger, cpu);
+                    return Ok(());
+                }
+                Ok(n) => self.input.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        self.handle_input(debugger, cpu)?;
+
+        if self.running && debugger.is_stopped() {
+            self.running = false;
+            let reply = match debugger.take_stop() {
+                Some(Stop::Watchpoint(hit, _)) => {
+                    let kind = match cpu.watch.list()[hit.index].access {
+                        Access::Read => "rwatch",
+                        _ => "watch",
+                    };
+                    format!("T05{}:{:04x};", kind, hit.addr)
+                }
+                Some(Stop::Pause) => String::from("S02"),
+                _ => String::from("S05"),
+            };
+            self.send(&reply)?;
+        }
+        Ok(())
+    }
+
+    fn disconnect<B: Bus>(&mut self, debugger: &mut Debugger, cpu: &mut Cpu<B>) {
+        self.client = None;
+        self.running = false;
+        if debugger.is_stopped() {
+            debugger.resume_running(cpu);
+        }
+    }
+
+    fn handle_input<B: Bus>(
+        &mut self,
+        debugger: &mut Debugger,
+        cpu: &mut Cpu<B>,
+    ) -> io::Result<()> {
+        while let Some(&first) = self.input.first() {
+            match first {
+                b'$' => {
+                    let end = match self.input.iter().position(|&b| b == b'#') {
+                        Some(end) if self.input.len() >= end + 3 => end,
+                        _ => return Ok(()), // Incomplete
+                    };
+                    let packet = String::from_utf8_lossy(&self.input[1..end]).into_owned();
+                    let sum = std::str::from_utf8(&self.input[end + 1..end + 3])
+                        .ok()
+                        .and_then(|sum| u8::from_str_radix(sum, 16).ok());
+                    self.input.drain(..end + 3);
+                    if sum != Some(checksum(&packet)) {
+                        self.write(b"-")?;
+                        continue;
+                    }
+                    self.write(b"+")?;
+                    if let Some(response) = self.packet(debugger, cpu, &packet) {
+                        self.send(&response)?;
+                    }
+                    if self.client.is_none() {
+                        return Ok(());
+                    }
+                }
+                0x03 => {
+                    // Ctrl-C
+                    self.input.remove(0);
+                    debugger.pause(cpu);
+                }
+                _ => {
+                    // Acks & noise
+                    self.input.remove(0);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Response to a packet, None when it is sent later (the stop reply of `c` & `s`)
+    fn packet<B: Bus>(
+        &mut self,
+        debugger: &mut Debugger,
+        cpu: &mut Cpu<B>,
+        packet: &str,
+    ) -> Option<String> {
+        let ok = |done: bool| Some(String::from(if done { "OK" } else { "E01" }));
+        let (command, args) = packet.split_at(packet.len().min(1));
+        match command {
+            "?" => Some(String::from("S05")),
+            "g" => {
+                let mut out = String::new();
+                for name in REGISTERS.iter() {
+                    let value = register(cpu, name).unwrap_or(0);
+                    out += &format!("{:02x}{:02x}", value as u8, value >> 8);
+                }
+                Some(out + &format!("{:02x}{:02x}", cpu.reg.r, cpu.reg.i))
+            }
+            "G" => match hex_bytes(args) {
+                Some(bytes) if bytes.len() >= REGISTERS.len() * 2 + 2 => {
+                    for (i, name) in REGISTERS.iter().enumerate() {
+                        let value = u16::from_le_bytes([bytes[i * 2], bytes[i * 2 + 1]]);
+                        set_register(cpu, name, value);
+                    }
+                    cpu.reg.r = bytes[24];
+                    cpu.reg.i = bytes[25];
+                    ok(true)
+                }
+                _ => ok(false),
+            },
+            "m" => match addr_len(args) {
+                Some((addr, len)) => Some(
+                    (0..len)
+                        .map(|i| format!("{:02x}", cpu.bus.read8(addr.wrapping_add(i))))
+                        .collect(),
+                ),
+                None => ok(false),
+            },
+            "M" => {
+                let parsed = args
+                    .split_once(':')
+                    .map(|(target, data)| (addr_len(target), hex_bytes(data)));
+                match parsed {
+                    Some((Some((addr, len)), Some(bytes))) if bytes.len() == len as usize => {
+                        for (i, &byte) in bytes.iter().enumerate() {
+                            cpu.bus.write8(addr.wrapping_add(i as u16), byte);
+                        }
+                        ok(true)
+                    }
+                    _ => ok(false),
+                }
+            }
+            "c" | "s" => {
+                if !args.is_empty() {
+                    match u16::from_str_radix(args, 16) {
+                        Ok(addr) => cpu.reg.pc = addr,
+                        Err(_) => return ok(false),
+                    }
+                }
+                match command {
+                    "c" => debugger.resume_running(cpu),
+                    _ => debugger.step_into(cpu),
+                }
+                self.running = true;
+                None
+            }
+            "Z" | "z" => {
+                let parsed = args
+                    .split_once(',')
+                    .and_then(|(kind, target)| Some((kind, addr_len(target)?)));
+                let (kind, (addr, len)) = match parsed {
+                    Some(parsed) => parsed,
+                    None => return ok(false),
+                };
+                let insert = command == "Z";
+                let access = match kind {
+                    "0" | "1" => None,
+                    "2" => Some(Access::Write),
+                    "3" => Some(Access::Read),
+                    _ => return Some(String::new()),
+                };
+                let done = match (access, insert) {
+                    (None, true) => {
+                        debugger.breakpoints.push(Breakpoint {
+                            addr,
+                            condition: None,
+                            after: 1,
+                            hits: 0,
+                        });
+                        true
+                    }
+                    (None, false) => {
+                        let found = debugger
+                            .breakpoints
+                            .iter()
+                            .position(|bp| bp.addr == addr && bp.condition.is_none());
+                        found.map(|i| debugger.breakpoints.remove(i)).is_some()
+                    }
+                    (Some(access), insert) => {
+                        let end = addr.wrapping_add(len.max(1) - 1);
+                        let watchpoint = Watchpoint {
+                            access,
+                            start: addr,
+                            end,
+                        };
+                        if insert {
+                            cpu.watch.add(watchpoint);
+                            true
+                        } else {
+                            let list = cpu.watch.list();
+                            let found = list.iter().position(|w| *w == watchpoint);
+                            found.is_some_and(|i| cpu.watch.remove(i))
+                        }
+                    }
+                };
+                ok(done)
+            }
+            "D" => {
+                // Detach, the reply goes out before the connection is closed
+                self.send("OK").ok();
+                self.disconnect(debugger, cpu);
+                None
+            }
+            "k" => {
+                self.disconnect(debugger, cpu);
+                None
+            }
+            "H" => ok(true),
+            "q" if args.starts_with("Supported") => Some(String::from("PacketSize=1000")),
+            "q" if args == "Attached" => Some(String::from("1")),
+            "q" if args == "C" => Some(String::from("QC1")),
+            "q" if args == "fThreadInfo" => Some(String::from("m1")),
+            "q" if args == "sThreadInfo" => Some(String::from("l")),
+            _ => Some(String::new()), // Not supported
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum(data));
+        self.write(packet.as_bytes())
+    }
+
+    // The socket doesn't block, a full send buffer is waited out
+    fn write(&mut self, mut data: &[u8]) -> io::Result<()> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+        while !data.is_empty() {
+            match client.write(data) {
+                Ok(n) => data = &data[n..],
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(1))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,45 +1,116 @@
-use super::cpu::Cpu;
+use super::bus::Bus;
+use super::cpu::{Cpu, StepInfo};
+use super::debugger::Debugger;
+use super::error::CpuError;
 use crate::pacman::keypad::Keypad;
 
-pub struct Interconnect {
-    pub cpu: Cpu,
+// Video timing: 264 lines of 384 pixel clocks at 6.144 MHz, the CPU gets every other one
+// (3.072 MHz) for 60.606 Hz frames. Vblank covers the last 40 lines.
+pub const CPU_CLOCK: usize = 3_072_000;
+pub const LINE_CYCLES: usize = 384 / 2;
+pub const LINES: usize = 264;
+pub const VBLANK_LINE: usize = 224;
+pub const FRAME_CYCLES: usize = LINES * LINE_CYCLES;
+pub const VBLANK_START: usize = VBLANK_LINE * LINE_CYCLES;
+// The sound chip runs at 96 kHz. Every line starts on one of its ticks, so they are the
+// time base of the scheduler: all other events land on a tick.
+pub const SAMPLE_CYCLES: usize = 32;
+
+pub struct Interconnect<B: Bus> {
+    pub cpu: Cpu<B>,
     pub keypad: Keypad,
     pub frame_count: u32,
+    // Position in the current frame, instructions that run past its end are carried over
+    pub frame_cycle: usize,
+    pub debugger: Debugger,
 }
 
-impl Interconnect {
-    pub fn new() -> Self {
+impl<B: Bus> Interconnect<B> {
+    pub fn new(bus: B) -> Self {
         Self {
-            cpu: Cpu::new(),
+            cpu: Cpu::new(bus),
             keypad: Keypad::new(),
             frame_count: 0,
+            frame_cycle: 0,
+            debugger: Debugger::new(),
         }
     }
 
-    pub fn execute_cpu(&mut self) -> u32 {
-        let vblank: bool = false;
-        // self.cpu.debug = true;
-        let mut cycles_executed: usize = 0;
-        // Cycles per frame should be: 3072000
-        // Divide amount of cycles per frame with 60 FPS
-        // Divide that by 2 to get half cycles per frame (for interrupts)
+    pub fn scanline(&self) -> usize {
+        self.frame_cycle / LINE_CYCLES
+    }
+
+    pub fn in_vblank(&self) -> bool {
+        self.frame_cycle >= VBLANK_START
+    }
+
+    // Runs until the end of the current frame, the vblank interrupt is raised at line 224
+    pub fn execute_cpu(&mut self) -> Result<u32, CpuError> {
+        let frame = self.frame_count;
+        self.debugger.frame = Some(frame);
 
-        while cycles_executed <= 25_600 {
+        while self.frame_count == frame {
+            // A debugger stop ends the frame early, the next call picks it up again
+            let debugging = self.debugger.enabled;
+            if debugging && self.debugger.check(&mut self.cpu) {
+                return Ok(self.frame_count);
+            }
+            let (pc, sp) = (self.cpu.reg.pc, self.cpu.reg.sp);
             let start_cycles = self.cpu.cycles;
-            self.cpu.execute();
+            if self.cpu.halted {
+                // Nothing but NOPs until the next interrupt, burn them up to the next event
+                let tick = (self.frame_cycle / SAMPLE_CYCLES + 1) * SAMPLE_CYCLES;
+                self.cpu.idle(tick - self.frame_cycle);
+            } else {
+                self.cpu.step()?;
+            }
+            if debugging {
+                self.debugger.after_step(&mut self.cpu, pc, sp);
+            }
+            self.advance(start_cycles);
 
-            cycles_executed += self.cpu.cycles - start_cycles;
-            self.cpu.poll_interrupt();
-            if vblank {
-                self.cpu.generate_interrupt();
+            // Accepting an interrupt takes time as well
+            let (pc, start_cycles) = (self.cpu.reg.pc, self.cpu.cycles);
+            if self.cpu.poll_interrupt()? && debugging {
+                self.debugger.after_interrupt(&mut self.cpu, pc);
             }
+            self.advance(start_cycles);
         }
 
-        self.frame_count += 1;
-        return self.frame_count;
+        if self.debugger.enabled {
+            self.debugger.end_frame(&mut self.cpu, self.frame_count);
+        }
+        Ok(self.frame_count)
+    }
+
+    // Moves the frame position by the T states spent since `start_cycles` & fires the
+    // events passed on the way, in order
+    fn advance(&mut self, start_cycles: usize) {
+        let mut from = self.frame_cycle;
+        self.frame_cycle += self.cpu.cycles - start_cycles;
+
+        loop {
+            let at = (from / SAMPLE_CYCLES + 1) * SAMPLE_CYCLES;
+            if at > self.frame_cycle {
+                break;
+            }
+            if at == VBLANK_START && self.cpu.bus.vblank() {
+                self.cpu.generate_interrupt();
+            }
+            if at == FRAME_CYCLES {
+                // End of vblank, the tick belongs to line 0 of the next frame
+                self.cpu.bus.vblank_end();
+                self.frame_cycle -= FRAME_CYCLES;
+                self.frame_count += 1;
+                from = 0;
+            } else {
+                from = at;
+            }
+            self.cpu.bus.sound_tick();
+        }
     }
 
-    pub fn run_tests(&mut self) {
-        self.cpu.execute();
+    pub fn run_tests(&mut self) -> Result<StepInfo, CpuError> {
+        self.cpu.step()
     }
 }
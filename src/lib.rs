@@ -0,0 +1,37 @@
+extern crate minifb;
+
+pub mod assembler;
+pub mod bus;
+pub mod cpm;
+pub mod cpu;
+pub mod dap;
+pub mod debugger;
+pub mod disassembler;
+pub mod error;
+pub mod formatter;
+pub mod gdb;
+pub mod instruction_info;
+pub mod interconnect;
+pub mod memory;
+pub mod savestate;
+pub mod trace;
+
+pub mod pacman {
+    pub mod display;
+    pub mod keypad;
+    #[allow(clippy::module_inception)]
+    pub mod pacman;
+}
+
+pub mod pacman_arcade {
+    pub mod display;
+    pub mod keypad;
+    pub mod pacman;
+}
+
+#[cfg(test)]
+mod single_step;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
@@ -1,27 +1,45 @@
-extern crate minifb;
-extern crate z80_rs;
+use flexi_logger::{FileSpec, Logger};
+use minifb::{Key, KeyRepeat};
+use std::io::Write;
 
-use flexi_logger::{Logger};
-use log::info;
+use pacman_rs::dap::DapServer;
+use pacman_rs::debugger::Repl;
+use pacman_rs::gdb::GdbStub;
+use pacman_rs::pacman_arcade::display::{HEIGHT, WIDTH};
+use pacman_rs::pacman_arcade::pacman::Pacman;
 
-use crate::pacman_arcade::pacman::Pacman;
-use crate::pacman_arcade::display::{WIDTH, HEIGHT};
-use std::time::Duration;
-
-mod pacman_arcade {
-    pub mod display;
-    pub mod keypad;
-    pub mod pacman;
-}
+const SLOT_KEYS: [Key; 8] = [Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8];
 
 fn main() {
-    Logger::with_str("z80")
-        .log_to_file()
-        .directory("log")
+    Logger::try_with_str("z80")
+        .unwrap()
+        .log_to_file(FileSpec::default().directory("log"))
         // .format(default_format)
         .start()
         .unwrap();
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    // --debug reads debugger commands from stdin while the game runs (try "help")
+    let repl = args.iter().any(|arg| arg == "--debug").then(Repl::stdin);
+    args.retain(|arg| arg != "--debug");
+    // --gdb PORT waits for a GDB remote connection on localhost
+    let mut gdb = args.iter().position(|arg| arg == "--gdb").map(|i| {
+        let port = args.drain(i..(i + 2).min(args.len())).nth(1).expect("--gdb PORT");
+        GdbStub::bind(("127.0.0.1", port.parse::<u16>().expect("Bad GDB port"))).unwrap()
+    });
+    // --dap speaks the Debug Adapter Protocol over stdin / stdout, for editors
+    let mut dap = args.iter().any(|arg| arg == "--dap").then(DapServer::stdio);
+    args.retain(|arg| arg != "--dap");
+    if repl.is_some() && dap.is_some() {
+        eprintln!("--debug & --dap can't be combined, both use stdin");
+        std::process::exit(1);
+    }
+    // --no-watchdog keeps the game from being reset when it stops kicking the watchdog
+    let watchdog = !args.iter().any(|arg| arg == "--no-watchdog");
+    args.retain(|arg| arg != "--no-watchdog");
+    // --load-state FILE starts from a save state
+    let state = args.iter().position(|arg| arg == "--load-state").map(|i| {
+        args.drain(i..(i + 2).min(args.len())).nth(1).expect("--load-state FILE")
+    });
     // pac.ctx.cpu.debug = true;
     // pac.ctx.cpu.memory.load_bin(&args);
     let mut pac = Pacman::new();
@@ -29,18 +47,74 @@ fn main() {
     pac.ctx.cpu.reset();
     pac.load_rom(&args);
     pac.init();
+    if let Some(path) = &state {
+        if let Err(e) = pac.load_state(path) {
+            eprintln!("Can't load {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+    pac.ctx.debugger.enabled = repl.is_some() || gdb.is_some() || dap.is_some();
+    // Debug sessions run without the watchdog, a game held at a breakpoint would be reset
+    pac.ctx.cpu.bus.watchdog_enabled = watchdog && !pac.ctx.debugger.enabled;
 
-    let mut sprite = 1;
-    let pal_no = 1;
-    let mut x = 0;
+    let sprite = 1;
+    let _pal_no = 1;
+    let x = 0;
     let mut y = 0;
 
     let mut i = 1;
     loop {
-        pac.ctx.execute_cpu();
-        // pac.draw_screen();
+        let frame = pac.ctx.frame_count;
+        if let Err(e) = pac.ctx.run_frame() {
+            // Keep the machine state around for the log
+            log::error!("{}\n{:?}", e, pac.ctx.cpu);
+            eprintln!("{}", e);
+            if !pac.ctx.debugger.enabled {
+                std::process::exit(1);
+            }
+            // The failing instruction is left at PC for the debugger to inspect
+            pac.ctx.debugger.pause(&mut pac.ctx.cpu);
+        }
+        if let Some(repl) = &repl {
+            print!("{}", pac.ctx.debugger.poll_repl(&mut pac.ctx.cpu, repl));
+            std::io::stdout().flush().ok();
+        }
+        if let Some(stub) = &mut gdb {
+            if let Err(e) = stub.poll(&mut pac.ctx.debugger, &mut pac.ctx.cpu) {
+                log::error!("GDB stub closed: {}", e);
+                gdb = None;
+            }
+        }
+        if let Some(server) = &mut dap {
+            if let Err(e) = server.poll(&mut pac.ctx.debugger, &mut pac.ctx.cpu) {
+                log::error!("DAP server closed: {}", e);
+                dap = None;
+            } else if !server.connected() {
+                dap = None;
+            }
+        }
+        // F1 - F8 load save state slots, Shift + F1 - F8 save them
+        let shift = pac.fb.window.is_key_down(Key::LeftShift) || pac.fb.window.is_key_down(Key::RightShift);
+        for (slot, key) in SLOT_KEYS.iter().enumerate() {
+            if !pac.fb.window.is_key_pressed(*key, KeyRepeat::No) {
+                continue;
+            }
+            let path = format!("state/slot{}.state", slot + 1);
+            let (result, done) = match shift {
+                true => (pac.save_state(&path), "Saved"),
+                false => (pac.load_state(&path), "Loaded"),
+            };
+            match result {
+                // stdout carries the DAP stream
+                Ok(()) => eprintln!("{} {}", done, path),
+                Err(e) => eprintln!("{}: {}", path, e),
+            }
+        }
+        // One picture per vblank, a debugger stop in the middle of a frame keeps the last one
+        if pac.ctx.frame_count != frame {
+            pac.render_tiles();
+        }
         // pac.fb.draw_sprite(y, x, sprite, pal_no);
-        // pac.render_tiles();
         pac.fb.window.is_key_down(minifb::Key::Enter).then(|| {
             pac.fb.draw_sprite(x, y, sprite, i, 1);
             i+=1;
@@ -54,7 +128,7 @@ fn main() {
             y +=8;
         });
 
-        pac.fb.window.update_with_buffer(&pac.fb.raster, WIDTH as usize, HEIGHT as usize).unwrap();
+        pac.fb.window.update_with_buffer(&pac.fb.raster, WIDTH, HEIGHT).unwrap();
         // sleep(Duration::from_micros(16));
     }
 }
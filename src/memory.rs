@@ -1,3 +1,4 @@
+use crate::bus::Bus;
 use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
@@ -36,6 +37,16 @@ impl Index<u16> for Memory {
     }
 }
 
+// Flat 64K address space without any memory mapping (used for CP/M binaries & tests)
+impl Bus for Memory {
+    fn read8(&self, addr: u16) -> u8 {
+        self.rom[addr as usize]
+    }
+    fn write8(&mut self, addr: u16, byte: u8) {
+        self.rom[addr as usize] = byte;
+    }
+}
+
 pub trait MemoryRW {
     fn read8(&self, addr: u16) -> u8;
     fn read16(&self, addr: u16) -> u16;
@@ -43,6 +54,12 @@ pub trait MemoryRW {
     fn write8(&mut self, addr: u16, byte: u8);
 }
 
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Memory {
     pub fn new() -> Memory {
         Memory {
@@ -54,30 +71,26 @@ impl Memory {
         self.rom[v]
     }
 
-    pub fn load_bin(&mut self, rom: &Vec<String>) {
+    pub fn load_bin(&mut self, rom: &[String]) {
         let mut buf = Vec::new();
         let mut collection: Vec<&str> = Vec::new();
 
         for i in rom.iter().skip(1) {
-            collection.push(&i);
+            collection.push(i);
         }
 
         for f in collection.iter() {
             let path = Path::new(f);
-            let mut file = File::open(&path).unwrap();
+            let mut file = File::open(path).unwrap();
             file.read_to_end(&mut buf).expect("Failed to read binary");
-            // self.rom[..buf.len()].clone_from_slice(&buf[..]);
-
-            for i in 0..buf.len() {
-                self.rom[i] = buf[i];
-            }
+            self.rom[..buf.len()].copy_from_slice(&buf[..]);
             println!("Loaded: {:?} Bytes: {:?}", path, buf.len());
         }
     }
 
     pub fn load_tests(&mut self, file: &str) {
         let path = Path::new(file);
-        let mut file = File::open(&path).expect(&*format!("Couldn't load binary file {:?}", path));
+        let mut file = File::open(path).unwrap_or_else(|_| panic!("Couldn't load binary file {:?}", path));
         let mut buf = Vec::new();
 
         file.read_to_end(&mut buf).expect("Failed to read binary");
@@ -32,7 +32,7 @@ pub enum Intensity {
 // As described per: https://www.lomont.org/software/games/pacman/PacmanEmulation.pdf
 impl Color {
     fn get(c: Color, i: Intensity) -> u8 {
-        return match c {
+        match c {
             Color::Red | Color::Green => match i {
                 Intensity::Min => 0x21,
                 Intensity::Max => 0x97,
@@ -43,7 +43,7 @@ impl Color {
                 Intensity::Max => 0xAE,
                 Intensity::Normal => 0x51,
             },
-        };
+        }
     }
 }
 
@@ -63,6 +63,12 @@ impl fmt::UpperHex for Display {
 
 const SCALE: usize = 8;
 
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display {
     pub fn new() -> Self {
         let mut window = Window::new(
@@ -101,14 +107,13 @@ impl Display {
         for byte_number in 0..16 {
             // X wraps around to 0 (at 7) for drawing the second half of the tile
             let x = x + (byte_number % 8);
-            let mut y: u8 = if byte_number >= 8 { y + 4 } else { y + 0 };
+            let y: u8 = if byte_number >= 8 { y + 4 } else { y };
             // 16 & byte number internal tile coordinates?
             let byte = self.tile_rom[tile as usize * 16 + byte_number as usize];
             let strip = self.decode_vertical_strip(byte);
 
-            for pixel_number in 0..4 {
-                self.draw_pixel(strip[pixel_number], x, y);
-                y = y + 1
+            for (i, pixel) in strip.into_iter().enumerate() {
+                self.draw_pixel(pixel, x, y + i as u8);
             }
         }
     }
@@ -116,20 +121,19 @@ impl Display {
         for byte_number in 0..64 {
             // X wraps around to 0 (at 7) for drawing the second half of the tile
             let x = x + (byte_number % 8);
-            let mut y: u8 = if byte_number >= 8 { y + 4 } else { y + 0 };
+            let y: u8 = if byte_number >= 8 { y + 4 } else { y };
             // 16 & byte number internal tile coordinates?
             let byte = self.sprite_rom[sprite as usize * 16 + byte_number as usize];
             let strip = self.decode_vertical_strip(byte);
-            for pixel_number in 0..4 {
-                self.draw_pixel(strip[pixel_number], x, y);
-                y = y + 1
+            for (i, pixel) in strip.into_iter().enumerate() {
+                self.draw_pixel(pixel, x, y + i as u8);
             }
         }
     }
 
     // Takes a byte of tile data and returns one vertical strip (4 pixels)
     pub fn decode_vertical_strip(&self, byte: u8) -> Vec<u8> {
-        let pixel1 = ((byte >> 0) & 1) | (((byte >> 4) & 1) << 1);
+        let pixel1 = (byte & 1) | (((byte >> 4) & 1) << 1);
         let pixel2 = ((byte >> 1) & 1) | (((byte >> 5) & 1) << 1);
         let pixel3 = ((byte >> 2) & 1) | (((byte >> 6) & 1) << 1);
         let pixel4 = ((byte >> 3) & 1) | (((byte >> 7) & 1) << 1);
@@ -157,7 +161,7 @@ impl Display {
         let color = self.color_rom[color_number as usize];
 
         // Bit 0, 1, 2: Red
-        let r = ((color >> 0) & 1) * Color::get(Red, Min)
+        let r = (color & 1) * Color::get(Red, Min)
             + ((color >> 1) & 1) * Color::get(Red, Normal)
             + ((color >> 2) & 1) * Color::get(Red, Max);
         // Bit 3, 4, 5: Green
@@ -1,4 +1,4 @@
-use minifb::{Key, KeyRepeat, Window};
+use minifb::Window;
 
 use crate::cpu::Io;
 
@@ -39,6 +39,12 @@ pub trait Input {
     fn reset_ports(&self, reg: &mut Io);
 }
 
+impl Default for Keypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Keypad {
     pub fn new() -> Keypad {
         Keypad {}
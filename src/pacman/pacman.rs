@@ -1,26 +1,34 @@
-use std::convert::TryInto;
+use log::debug;
 use std::fs::File;
-use std::io::{Error, Read};
+use std::io::Read;
 use std::path::Path;
 
+use crate::bus::Bus;
 use crate::interconnect::Interconnect;
-use crate::memory::MemoryRW;
-use crate::pacman::display::{Display, HEIGHT, WIDTH};
+use crate::memory::Memory;
+use crate::pacman::display::Display;
 
 pub struct Pacman {
-    pub int_vector: u8,
-    pub int_enable: bool, // Vblank or CPU interrupt
-    pub port_in: u8,
-    pub port_out: u8,
-    pub ctx: Interconnect,
+    pub ctx: Interconnect<Board>,
     pub fb: Display,
     pub dip: Dip,
     pub in0: IN0,
     pub in1: IN1,
+}
+
+// The part of the board the CPU sees
+pub struct Board {
+    pub memory: Memory,
+    pub vram: Vec<u8>, // 0x4000 - 0x4FEF
+    pub int_vector: u8,
+    pub int_enable: bool, // Vblank or CPU interrupt
+    pub port_in: u8,
+    pub port_out: u8,
     pub c_lockout: bool,
     pub c_counter: bool,
 }
 
+#[allow(dead_code)]
 #[derive(Default)]
 pub struct Dip {
     coins_per_game: u8,
@@ -30,6 +38,7 @@ pub struct Dip {
     ghost_names: bool,
 }
 
+#[allow(dead_code)]
 #[derive(Default)]
 pub struct IN0 {
     joy_up: bool,
@@ -38,6 +47,7 @@ pub struct IN0 {
     joy_down: bool,
 }
 
+#[allow(dead_code)]
 #[derive(Default)]
 pub struct IN1 {
     joy_up: bool,
@@ -52,23 +62,43 @@ enum Map {
     PaletteRom,
     TileRom,
     Rom,
-    Ram,
 }
 
-impl Pacman {
+impl Default for Pacman {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Board {
     pub fn new() -> Self {
         Self {
+            memory: Memory::new(),
+            vram: vec![0; 0x0FF0],
             int_vector: 0,
             int_enable: false,
             port_in: 0,
             port_out: 0,
-            ctx: Interconnect::new(),
+            c_lockout: false,
+            c_counter: false,
+        }
+    }
+}
+
+impl Pacman {
+    pub fn new() -> Self {
+        Self {
+            ctx: Interconnect::new(Board::new()),
             fb: Display::new(),
             dip: Dip::default(),
             in0: IN0::default(),
             in1: IN1::default(),
-            c_lockout: false,
-            c_counter: false,
         }
     }
     pub fn init(&mut self) {
@@ -81,23 +111,21 @@ impl Pacman {
         let mut buf = Vec::new();
         file.read_to_end(&mut buf).expect("Unable to read file");
 
-        for i in 0..buf.len() {
-            match map {
-                Map::SpriteRom => self.fb.sprite_rom[i] = buf[i],
-                Map::ColorRom => self.fb.color_rom[i] = buf[i],
-                Map::TileRom => self.fb.tile_rom[i] = buf[i],
-                Map::PaletteRom => self.fb.palette_rom[i] = buf[i],
-                Map::Rom => self.ctx.cpu.memory.rom[i + offset] = buf[i],
-                Map::Ram => self.ctx.cpu.memory.ram[i + offset] = buf[i],
-            }
-        }
+        let dst = match map {
+            Map::SpriteRom => &mut self.fb.sprite_rom[..],
+            Map::ColorRom => &mut self.fb.color_rom[..],
+            Map::TileRom => &mut self.fb.tile_rom[..],
+            Map::PaletteRom => &mut self.fb.palette_rom[..],
+            Map::Rom => &mut self.ctx.cpu.bus.memory.rom[offset..],
+        };
+        dst[..buf.len()].copy_from_slice(&buf);
     }
-    pub fn load_rom(&mut self, rom: &Vec<String>) {
+    pub fn load_rom(&mut self, rom: &[String]) {
         let mut collection: Vec<&str> = Vec::new();
 
         // Skip the target directory and use provided args
         for i in rom.iter().skip(1) {
-            collection.push(&i);
+            collection.push(i);
         }
         for y in collection.iter() {
             let path = Path::new(y);
@@ -120,30 +148,30 @@ impl Pacman {
                         _ => {} // Do nothing for non matches
                     }
                 }
-                println!("Rom files found & loaded..");
+                eprintln!("Rom files found & loaded..");
             } else if path.is_file() || !path.is_dir() {
                 eprintln!(
                     "Pacman roms not found, please check your rom directory or provided arguments"
                 );
-                panic!(format!("Attempted to load: {}", path.display()));
+                panic!("Attempted to load: {}", path.display());
             }
         }
     }
 
     pub fn render_sprites(&mut self) {
         let mut sprite = 0;
-        for mut y in 0..8 {
-            for mut x in 0..8 {
+        for y in 0..8 {
+            for x in 0..8 {
                 self.fb.draw_sprite(sprite, x * 8, y * 8);
                 sprite = sprite.wrapping_add(1);
             }
         }
     }
     // Render the whole tile map
-    pub(crate) fn render_tiles(&mut self) {
+    pub fn render_tiles(&mut self) {
         let mut tile = 0;
-        for mut y in 0..16 {
-            for mut x in 0..16 {
+        for y in 0..16 {
+            for x in 0..16 {
                 self.fb.draw_tile(tile, x * 8, y * 8);
                 tile = tile.wrapping_add(1);
             }
@@ -151,58 +179,47 @@ impl Pacman {
     }
 }
 
-// Mapper trait for the Pacman hardware
-pub trait Mapper {
-    fn read(&self, addr: u16) -> u8;
-    fn write(&mut self, addr: u16, byte: u8);
-}
-
-impl Mapper for Pacman {
-    fn read(&self, addr: u16) -> u8 {
+impl Bus for Board {
+    fn read8(&self, addr: u16) -> u8 {
         match addr {
-            0x0000..=0x3FFF => self.ctx.cpu.memory.rom[addr as usize],
-            0x4000..=0x47FF => self.fb.tile_rom[addr as usize - 0x4000],
-            0x4800..=0x4FEF => self.fb.vram[addr as usize - 0x4800],
-            0x4FF0..=0x4FFF => self.fb.sprite_rom[addr as usize - 0x4ff0],
+            0x0000..=0x3FFF => self.memory.rom[addr as usize],
+            0x4000..=0x4FEF => self.vram[addr as usize - 0x4000],
             0x5000 => self.int_enable as u8,
-            0x5006 => self.ctx.cpu.memory.rom[addr as usize],
-            0x5040..=0x507F => unimplemented!(), // self.in0 // Joystick and start buttons
-            _ => unimplemented!(),
+            _ => {
+                debug!("Read from unmapped address {:04X}", addr);
+                0xFF
+            }
         }
     }
 
-    fn write(&mut self, addr: u16, byte: u8) {
+    fn write8(&mut self, addr: u16, byte: u8) {
+        let on = (byte & 0x01) != 0;
         match addr {
-            0x4000..=0x43FF => self.fb.vram[addr as usize] = byte,
-            0x4400..=0x47FF => self.fb.vram[addr as usize] = byte,
-            0x4800..=0x4FEF => self.fb.vram[addr as usize] = byte,
+            0x4000..=0x4FEF => self.vram[addr as usize - 0x4000] = byte,
+            0x5000 => self.int_enable = on,
+            0x5001 => debug!("Stubbed: Sound enable:{}", on),
+            0x5002 => debug!("Stubbed: Aux board enable write."),
+            0x5004 => debug!("Player 1 start lamp:{}", on),
+            0x5005 => debug!("Player 2 start lamp:{}", on),
+            0x5006 => self.c_lockout = on,
+            0x5007 => self.c_counter = on,
+            _ => debug!("Write address:{:02X} Byte:{:02X}", addr, byte),
+        }
+    }
 
-            0x5000 => {
-                self.int_enable = true;
-                self.ctx.cpu.int.irq = (byte & 0x01) != 0;
-            }
-            0x5001 => println!("Stubbed: Sound enable:{}", byte & 0x01 != 0),
-            0x5002 => println!("Stubbed: Aux board enable write."),
-            0x5004 => println!("Player 1 start lamp:{}", byte & 0x01 != 0),
-            0x5005 => println!("Player 2 start lamp:{}", byte & 0x01 != 0),
-            0x5006 => {
-                println!("Coin lockout:{}", (byte & 0x01) != 0);
-                self.ctx.cpu.memory.ram[addr as usize] = byte;
-                if (byte & 0x01) != 0 {
-                    self.c_lockout = true;
-                }
-            }
-            0x5007 => {
-                println!("Coin lockout:{}", (byte & 0x01) != 0);
-                self.ctx.cpu.memory.ram[addr as usize] = byte;
-                if (byte & 0x01) != 0 {
-                    self.c_counter = true;
-                }
-            }
-            _ => unimplemented!(
-                "{}",
-                format!("Write address:{:02X} Byte:{:02X}", addr, byte)
-            ),
+    // A write to port 0 latches the vector put on the data bus for the vblank interrupt
+    fn port_out(&mut self, port: u16, byte: u8) {
+        self.port_out = byte;
+        if port & 0xFF == 0 {
+            self.int_vector = byte;
         }
     }
+
+    fn int_data(&self) -> u8 {
+        self.int_vector
+    }
+
+    fn vblank(&mut self) -> bool {
+        self.int_enable
+    }
 }
@@ -1,37 +1,13 @@
-use std::{fmt, thread};
-use std::collections::{HashMap, HashSet};
-use std::hash::Hash;
-use std::ops::Index;
-use std::time::Duration;
+use std::fmt;
+use std::collections::HashMap;
 
 use crate::minifb::{Scale, Window, WindowOptions};
-use crate::pacman_arcade::pacman::Mapper;
 
-pub const WIDTH: usize = 224;
-pub const HEIGHT: usize = 256;
-
-impl Mapper for Display {
-    fn read(&self, addr: u16) -> u8 {
-        match addr {
-            0x4000..=0x43FF => self.vram[addr as usize - 0x4000],
-            0x4400..=0x47FF => self.vram[addr as usize - 0x4400],
-            0x4800..=0x4BFF => self.vram[addr as usize - 0x4800],
-            _ => unimplemented!("Read to address:{:04X}", addr),
-        }
-    }
-
-    fn write(&mut self, addr: u16, byte: u8) {
-        match addr {
-            0x4000..=0x43FF => self.vram[addr as usize - 0x4000] = byte,
-            0x4400..=0x47FF => self.vram[addr as usize - 0x4400] = byte,
-            0x4800..=0x4FEF => self.vram[addr as usize - 0x4800] = byte,
-            _ => unimplemented!(
-                "{}",
-                format!("Write address:{:02X} Byte:{:02X}", addr, byte)
-            ),
-        }
-    }
-}
+// The monitor is mounted vertically, the screen is 28 x 36 tiles of 8x8 pixels
+pub const COLUMNS: usize = 28;
+pub const ROWS: usize = 36;
+pub const WIDTH: usize = COLUMNS * 8;
+pub const HEIGHT: usize = ROWS * 8;
 
 pub struct Display {
     pub raster: Vec<u32>,
@@ -40,7 +16,6 @@ pub struct Display {
     pub sprite_rom: Vec<u8>,
     pub color_rom: Vec<u8>,
     pub palette_rom: Vec<u8>,
-    pub vram: Vec<u8>,
     pub vblank: bool,
     pub window: Window,
 }
@@ -57,13 +32,6 @@ pub enum Intensity {
     Normal,
 }
 
-impl Index<u16> for Display {
-    type Output = u8;
-    fn index(&self, index: u16) -> &u8 {
-        &self.vram[index as usize]
-    }
-}
-
 // As described per: https://www.lomont.org/software/games/pacman/PacmanEmulation.pdf
 impl Color {
     fn get(c: Color, i: Intensity) -> u8 {
@@ -98,12 +66,18 @@ impl fmt::UpperHex for Display {
 
 const SCALE: usize = 8;
 
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display {
     pub fn new() -> Self {
         let mut window = Window::new(
             "Pacman-rs",
-            WIDTH as usize,
-            HEIGHT as usize,
+            WIDTH,
+            HEIGHT,
             WindowOptions {
                 resize: true,
                 scale: Scale::X4,
@@ -115,13 +89,12 @@ impl Display {
         window.set_position(400, 400);
         Display {
             // TODO: Is there a better way to handle resize / different scaling?
-            raster: vec![0x00FF_FFFF; (WIDTH as usize * HEIGHT as usize) * SCALE],
-            buf: vec![0; WIDTH as usize * HEIGHT as usize],
+            raster: vec![0x00FF_FFFF; (WIDTH * HEIGHT) * SCALE],
+            buf: vec![0; WIDTH * HEIGHT],
             tile_rom: vec![0; 0x1024],
             sprite_rom: vec![0; 0x90024],
             color_rom: vec![0; 0x1024],
             palette_rom: vec![0; 0x1024],
-            vram: vec![0; WIDTH as usize * HEIGHT as usize * SCALE],
             vblank: false,
             window,
         }
@@ -134,32 +107,36 @@ impl Display {
         (r << 16) | (g << 8) | b
     }
 
-    pub fn draw_tile(&mut self, tile_number: u8, x: u8, y: u8, pal_no: u8) {
+    // Draws the whole tilemap from video RAM (tile numbers, then their palettes 0x400 on)
+    pub fn draw_tilemap(&mut self, vram: &[u8]) {
+        for row in 0..ROWS {
+            for col in 0..COLUMNS {
+                let offset = tile_offset(col, row);
+                self.draw_tile(vram[offset], col * 8, row * 8, vram[0x400 + offset] & 0x3F);
+            }
+        }
+    }
+
+    pub fn draw_tile(&mut self, tile_number: u8, x: usize, y: usize, pal_no: u8) {
         // 16 bytes per tile
         for byte_number in 0..16 {
             // X wraps around to 0 (at 7) for drawing the second half of the tile
-            // let mut x = x + 7 - (byte_number % 8);
-            let mut x = x.wrapping_add(7 - (byte_number % 8));
-            let mut y = if byte_number >= 8 {
-                y.wrapping_add(0)
-            } else {
-                y.wrapping_add(4)
-            };
-            let mut byte = self.tile_rom[tile_number as usize * 16 + byte_number as usize];
-            let mut strip: Vec<u8> = self.decode_vertical_strip(byte);
+            let x = x + 7 - (byte_number % 8);
+            let y = if byte_number >= 8 { y } else { y + 4 };
+            let byte = self.tile_rom[tile_number as usize * 16 + byte_number];
+            let strip: Vec<u8> = self.decode_vertical_strip(byte);
             for pixel_number in 0..4 {
-                self.draw_pixel(strip[3 - pixel_number], x, y, pal_no as usize);
-                y = y.wrapping_add(1);
+                self.draw_pixel(strip[3 - pixel_number], x, y + pixel_number, pal_no as usize);
             }
         }
     }
     // Draws a sprite slice ( 8 columns, each column is 4  pixels tall)
-    pub fn draw_slice(&mut self, x: usize, mut y: usize, mut index: usize, pal: usize) {
+    pub fn draw_slice(&mut self, x: usize, y: usize, index: usize, _pal: usize) {
         let sprites = self.create_slice(index);
 
         for byte_number in 0..8 {
             // X wraps around to 0 (at 7) for drawing the second half of the tile
-            let mut x = x.wrapping_add(7 - (byte_number % 8));
+            let x = x.wrapping_add(7 - (byte_number % 8));
             let mut y = if byte_number >= 8 {
                 y.wrapping_add(0)
             } else {
@@ -169,12 +146,12 @@ impl Display {
                 // not sure about reversing the slice bytes here??
                 // Reverse what is stored otherwise it's flipped horizontally
                 // self.draw_pixel(sprites[&(7 -byte_number as u8)][3 - strip_no], x as u8, y as u8, 1 as usize);
-                self.draw_pixel(sprites[&(7 -byte_number as u8)][3 - strip_no], x as u8, y as u8, 1 as usize);
+                self.draw_pixel(sprites[&(7 -byte_number as u8)][3 - strip_no], x, y, 1_usize);
                 y = y.wrapping_add(1);
             }
         }
     }
-    pub fn create_slice(&mut self, mut index: usize) -> HashMap<u8, Vec<u8>> {
+    pub fn create_slice(&mut self, index: usize) -> HashMap<u8, Vec<u8>> {
         let mut hash: HashMap<u8, Vec<u8>> = HashMap::new();
         // Sprite[index] --> [sprite_byte[pixel_data]
         for strip in 0..8 {
@@ -182,10 +159,10 @@ impl Display {
             let sprite_byte = self.sprite_rom[index * 8 + strip as usize];
             hash.insert(strip, self.decode_vertical_strip(sprite_byte));
         }
-        return hash;
+        hash
     }
 
-    pub fn draw_sprite(&mut self, x: usize, mut y: usize, mut sprite: usize, offset: usize, pal_no: usize) {
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: usize, offset: usize, pal_no: usize) {
         /*  Sprite arrangement:
         Stored as 2bbp
         ---------------------------------
@@ -196,12 +173,12 @@ impl Display {
         ----------------------------- */
 
 
-        self.draw_slice(x, y + 0, (sprite + offset) + 0 * 8, pal_no);
-        self.draw_slice(x, y + 4, (sprite + offset) + 1 * 8, pal_no);
+        self.draw_slice(x, y, sprite + offset , pal_no);
+        self.draw_slice(x, y + 4, (sprite + offset) + 8, pal_no);
         self.draw_slice(x, y + 8, (sprite + offset) + 2 * 8, pal_no);
         self.draw_slice(x, y + 12, (sprite + offset) + 3 * 8, pal_no);
 
-        self.draw_slice(x + 8, y + 0, (sprite + offset) + 4 * 8, pal_no);
+        self.draw_slice(x + 8, y, (sprite + offset) + 4 * 8, pal_no);
         self.draw_slice(x + 8, y + 4, (sprite + offset) + 5 * 8, pal_no);
         self.draw_slice(x + 8, y + 8, (sprite + offset) + 6 * 8, pal_no);
         self.draw_slice(x + 8, y + 12, (sprite + offset) + 7 * 8, pal_no);
@@ -224,7 +201,7 @@ impl Display {
     // the respective pixels are composed of bits:  0, 4, 1,5, 2, 6, 3 and 7
     // pixel1: bit 0 and bit 4.
     pub fn decode_vertical_strip(&self, byte: u8) -> Vec<u8> {
-        let pixel1 = ((byte >> 0) & 1) | (((byte >> 4) & 1) << 1);
+        let pixel1 = (byte & 1) | (((byte >> 4) & 1) << 1);
         let pixel2 = ((byte >> 1) & 1) | (((byte >> 5) & 1) << 1);
         let pixel3 = ((byte >> 2) & 1) | (((byte >> 6) & 1) << 1);
         let pixel4 = ((byte >> 3) & 1) | (((byte >> 7) & 1) << 1);
@@ -232,19 +209,17 @@ impl Display {
         vec![pixel1, pixel2, pixel3, pixel4]
     }
 
-    pub fn draw_pixel(&mut self, pixel_number: u8, x: u8, y: u8, offset: usize) {
+    pub fn draw_pixel(&mut self, pixel_number: u8, x: usize, y: usize, pal_no: usize) {
         // pixel number being the 2bbp value from our vertical strip
-        // let color = self.get_palette(self.read((0x4400 + pixel_number) as u16), pixel_number);
-        let color = self.get_palette(1, pixel_number);
+        let color = self.get_palette(pal_no as u8, pixel_number);
         let (r, g, b) = self.get_color(color);
         let v = self.u8_rgb(r, g, b);
-        self.raster[WIDTH as usize * y as usize + x as usize] = v;
+        self.raster[WIDTH * y + x] = v;
     }
 
+    // 64 palettes of 4 entries, each one an index into the 16 colors of the color rom
     pub(crate) fn get_palette(&self, palette_number: u8, color_index: u8) -> u8 {
-       // Color index is the byte // offset
-        // self.read((palette_number as usize * 4 + color_index) as u16)
-        self.palette_rom[palette_number as usize * 4 + color_index as usize]
+        self.palette_rom[palette_number as usize * 4 + color_index as usize] & 0x0F
     }
 
     // Gets the RGB color from color intensity values in color rom
@@ -268,3 +243,16 @@ impl Display {
         (r, g, b)
     }
 }
+
+// Offset in video RAM of the tile at column `col`, row `row` of the screen. The playfield
+// (rows 2 - 33) is stored column by column from the top right, 0x040 - 0x3BF. The two rows
+// at the top (0x3C0 - 0x3FF) & bottom (0x000 - 0x03F) are stored row by row, right to left,
+// the first & last 2 tiles of each 32 tile row are off screen.
+pub fn tile_offset(col: usize, row: usize) -> usize {
+    let col = COLUMNS - 1 - col;
+    match row {
+        0 | 1 => 0x3C2 + row * 0x20 + col,
+        34 | 35 => 0x002 + (row - 34) * 0x20 + col,
+        _ => 0x040 + col * 0x20 + (row - 2),
+    }
+}
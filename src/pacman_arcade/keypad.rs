@@ -1,6 +1,6 @@
-use minifb::{Key, KeyRepeat, Window};
+use minifb::Window;
 
-use crate::z80_rs::cpu::Io;
+use crate::cpu::Io;
 
 /*  Pacman specific dip switch configurations
 *
@@ -39,6 +39,12 @@ pub trait Input {
     fn reset_ports(&self, reg: &mut Io);
 }
 
+impl Default for Keypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Keypad {
     pub fn new() -> Keypad {
         Keypad {}
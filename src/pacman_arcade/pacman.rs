@@ -1,51 +1,189 @@
-use log::{debug, error, info, warn};
+use log::{debug, info, trace, warn};
 use std::fs::File;
-use std::io::{Error, Read};
+use std::io::Read;
 use std::path::Path;
 
+use crate::bus::Bus;
+use crate::error::{CpuError, StateError};
 pub use crate::pacman_arcade::display::{Display, HEIGHT, WIDTH};
-pub use crate::z80_rs::interconnect::Interconnect;
-pub use crate::z80_rs::memory::MemoryRW;
+pub use crate::interconnect::Interconnect;
+pub use crate::memory::{Memory, MemoryRW};
+use crate::savestate::{self, Snapshot, StateReader, StateWriter};
 
 pub struct Pacman {
+    pub ctx: Interconnect<Board>,
+    pub fb: Display,
+}
+
+// Everything on the Pacman main board that is visible to the CPU (the bus)
+pub struct Board {
+    pub memory: Memory, // Work RAM at `ram[0..0x400]` (0x4C00 - 0x4FFF)
+    pub vram: Vec<u8>,  // Video RAM (tiles, 0x4000) followed by color RAM (0x4400)
     pub int_vector: u8,
     // IO port 0x00 to write interrupt vector for CPU
     pub int_enable: bool,
     // Vblank or CPU interrupt
     pub port_in: u8,
     pub port_out: u8,
-    pub ctx: Interconnect,
-    pub fb: Display,
     pub dip: Dip,
     pub in0: IN0,
     pub in1: IN1,
     pub c_lockout: bool,
     pub c_counter: bool,
+    pub sound: Vec<u8>, // 0x5040 - 0x505F: frequency, volume & waveform of the 3 voices
+    pub sprite_coords: Vec<u8>, // 0x5060 - 0x506F: x & y of the 8 sprites
+    pub sound_enable: bool,
+    pub aux_enable: bool,
+    pub flip_screen: bool,
+    pub lamps: [bool; 2], // Player 1 & 2 start lamps
+    pub watchdog: u32,    // Vblanks since the last write to 0x50C0
+    pub watchdog_enabled: bool, // Off while debugging, a stopped game doesn't kick it
+    watchdog_expired: bool,
+    int_cleared: bool, // Set when 0x5000 is cleared, drops a pending vblank interrupt
 }
 
-#[derive(Default)]
+// The watchdog counter pulls RESET once it counts this many vblanks
+pub const WATCHDOG_VBLANKS: u32 = 16;
+
+// DSW1, read at 0x5080
 pub struct Dip {
-    coins_per_game: u8,
-    lives_per_game: u8,
-    bonus_extra_life: u8,
-    difficulty: bool,
-    ghost_names: bool,
+    pub coins_per_game: u8,   // 0 free play, 1 coin 1 game, 1 coin 2 games, 2 coins 1 game
+    pub lives_per_game: u8,   // 1, 2, 3 or 5 lives
+    pub bonus_extra_life: u8, // At 10000, 15000, 20000 points or none
+    pub difficulty: bool,     // Normal, hard when cleared
+    pub ghost_names: bool,    // Normal, alternate when cleared
 }
 
+// Player 1 joystick, coins & service switches, read at 0x5000. Active low on the bus.
 #[derive(Default)]
 pub struct IN0 {
-    joy_up: bool,
-    joy_left: bool,
-    joy_right: bool,
-    joy_down: bool,
+    pub joy_up: bool,
+    pub joy_left: bool,
+    pub joy_right: bool,
+    pub joy_down: bool,
+    pub rack_test: bool,
+    pub coin1: bool,
+    pub coin2: bool,
+    pub credit: bool, // Service credit
 }
 
+// Player 2 joystick, start buttons & cabinet switches, read at 0x5040. Active low except
+// the cabinet type.
 #[derive(Default)]
 pub struct IN1 {
-    joy_up: bool,
-    joy_left: bool,
-    joy_right: bool,
-    joy_down: bool,
+    pub joy_up: bool,
+    pub joy_left: bool,
+    pub joy_right: bool,
+    pub joy_down: bool,
+    pub board_test: bool,
+    pub start1: bool,
+    pub start2: bool,
+    pub cocktail: bool,
+}
+
+// Switches to an active low byte, bit 0 first
+fn active_low(switches: [bool; 8]) -> u8 {
+    switches
+        .iter()
+        .enumerate()
+        .fold(0xFF, |byte, (bit, &on)| if on { byte & !(1 << bit) } else { byte })
+}
+
+// Inverse of `active_low`
+fn switches(byte: u8) -> [bool; 8] {
+    let mut on = [false; 8];
+    for (bit, switch) in on.iter_mut().enumerate() {
+        *switch = byte & (1 << bit) == 0;
+    }
+    on
+}
+
+impl Default for Dip {
+    // 1 coin 1 game, 3 lives, bonus at 10000 points
+    fn default() -> Self {
+        Self {
+            coins_per_game: 1,
+            lives_per_game: 2,
+            bonus_extra_life: 0,
+            difficulty: true,
+            ghost_names: true,
+        }
+    }
+}
+
+impl Dip {
+    pub fn read(&self) -> u8 {
+        (self.coins_per_game & 3)
+            | (self.lives_per_game & 3) << 2
+            | (self.bonus_extra_life & 3) << 4
+            | (self.difficulty as u8) << 6
+            | (self.ghost_names as u8) << 7
+    }
+}
+
+impl IN0 {
+    pub fn read(&self) -> u8 {
+        active_low([
+            self.joy_up,
+            self.joy_left,
+            self.joy_right,
+            self.joy_down,
+            self.rack_test,
+            self.coin1,
+            self.coin2,
+            self.credit,
+        ])
+    }
+
+    // Sets the switches from a byte as read by the CPU
+    pub fn write(&mut self, byte: u8) {
+        let [up, left, right, down, rack_test, coin1, coin2, credit] = switches(byte);
+        *self = IN0 {
+            joy_up: up,
+            joy_left: left,
+            joy_right: right,
+            joy_down: down,
+            rack_test,
+            coin1,
+            coin2,
+            credit,
+        };
+    }
+}
+
+impl IN1 {
+    pub fn read(&self) -> u8 {
+        let byte = active_low([
+            self.joy_up,
+            self.joy_left,
+            self.joy_right,
+            self.joy_down,
+            self.board_test,
+            self.start1,
+            self.start2,
+            false,
+        ]);
+        // Bit 7 is set for an upright cabinet
+        if self.cocktail {
+            byte & 0x7F
+        } else {
+            byte
+        }
+    }
+
+    pub fn write(&mut self, byte: u8) {
+        let [up, left, right, down, board_test, start1, start2, _] = switches(byte);
+        *self = IN1 {
+            joy_up: up,
+            joy_left: left,
+            joy_right: right,
+            joy_down: down,
+            board_test,
+            start1,
+            start2,
+            cocktail: byte & 0x80 == 0,
+        };
+    }
 }
 
 #[derive(Debug)]
@@ -55,54 +193,161 @@ enum Map {
     PaletteRom,
     TileRom,
     Rom,
-    Ram,
 }
 
-impl Pacman {
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Board {
     pub fn new() -> Self {
         Self {
+            memory: Memory::new(),
+            vram: vec![0; 0x800],
             int_vector: 0,
             int_enable: false,
             port_in: 0,
             port_out: 0,
-            ctx: Interconnect::default(),
-            fb: Display::new(),
             dip: Dip::default(),
             in0: IN0::default(),
             in1: IN1::default(),
             c_lockout: false,
             c_counter: false,
+            sound: vec![0; 0x20],
+            sprite_coords: vec![0; 0x10],
+            sound_enable: false,
+            aux_enable: false,
+            flip_screen: false,
+            lamps: [false; 2],
+            watchdog: 0,
+            watchdog_enabled: true,
+            watchdog_expired: false,
+            int_cleared: false,
+        }
+    }
+
+    // RESET clears the latches & the watchdog, RAM keeps its contents
+    pub fn reset(&mut self) {
+        for latch in 0..8 {
+            self.latch(latch, 0);
         }
+        self.watchdog = 0;
+        self.watchdog_expired = false;
     }
+
+    // 0x4FF0 - 0x4FFF: sprite number, X / Y flip & palette of the 8 sprites, 2 bytes each
+    pub fn sprite_attributes(&self) -> &[u8] {
+        &self.memory.ram[0x3F0..0x400]
+    }
+
+    fn latched(&self, index: u16) -> bool {
+        match index {
+            0 => self.int_enable,
+            1 => self.sound_enable,
+            2 => self.aux_enable,
+            3 => self.flip_screen,
+            4 | 5 => self.lamps[index as usize - 4],
+            6 => self.c_lockout,
+            _ => self.c_counter,
+        }
+    }
+
+    // 0x5000 - 0x5007, bit 0 of the byte is latched
+    fn latch(&mut self, index: u16, byte: u8) {
+        let on = byte & 0x01 != 0;
+        match index {
+            0 => {
+                self.int_enable = on;
+                self.int_cleared |= !on;
+            }
+            1 => self.sound_enable = on,
+            2 => self.aux_enable = on,
+            3 => self.flip_screen = on,
+            4 | 5 => self.lamps[index as usize - 4] = on,
+            6 => self.c_lockout = on,
+            _ => self.c_counter = on,
+        }
+        debug!("Latch {}: {}", index, on);
+    }
+}
+
+// Pacman machine control
+impl Interconnect<Board> {
     pub fn init(&mut self) {
-        self.ctx.cpu.cpm_compat = false;
-        self.ctx.cpu.flags.zf = true;
-        self.ctx.cpu.reg.ix = 0xFFFF;
-        self.ctx.cpu.reg.iy = 0xFFFF;
+        self.cpu.flags.zf = true;
+        self.cpu.reg.ix = 0xFFFF;
+        self.cpu.reg.iy = 0xFFFF;
         info!("Initialized z80 core");
     }
 
+    // The RESET line: the CPU starts over at 0 with interrupts off & the board is reset
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+        self.cpu.reg.pc = 0;
+        self.cpu.reg.i = 0;
+        self.cpu.int.irq = false;
+        self.cpu.int.nmi_pending = false;
+        self.cpu.bus.reset();
+        self.debugger.frames.clear();
+        self.init();
+    }
+
+    // Runs a frame, an expired watchdog resets the machine at its end
+    pub fn run_frame(&mut self) -> Result<u32, CpuError> {
+        let frame = self.execute_cpu()?;
+        if std::mem::take(&mut self.cpu.bus.watchdog_expired) {
+            self.reset();
+        }
+        Ok(frame)
+    }
+}
+
+impl Default for Pacman {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pacman {
+    pub fn new() -> Self {
+        Self {
+            ctx: Interconnect::new(Board::new()),
+            fb: Display::new(),
+        }
+    }
+    pub fn init(&mut self) {
+        self.ctx.init();
+    }
+
+    // Complete machine state, see `savestate`
+    pub fn save_state<P: AsRef<Path>>(&self, path: P) -> Result<(), StateError> {
+        savestate::save(self, path)
+    }
+    pub fn load_state<P: AsRef<Path>>(&mut self, path: P) -> Result<(), StateError> {
+        savestate::load(self, path)
+    }
+
     fn load(&mut self, file: &mut File, map: Map, offset: usize) {
         let mut buf = Vec::new();
         file.read_to_end(&mut buf).expect("Unable to read file");
 
-        for i in 0..buf.len() {
-            match map {
-                Map::SpriteRom => self.fb.sprite_rom[i] = buf[i],
-                Map::ColorRom => self.fb.color_rom[i] = buf[i],
-                Map::TileRom => self.fb.tile_rom[i] = buf[i],
-                Map::PaletteRom => self.fb.palette_rom[i] = buf[i],
-                Map::Rom => self.ctx.cpu.memory.rom[i + offset] = buf[i],
-                Map::Ram => self.ctx.cpu.memory.ram[i + offset] = buf[i],
-            }
-        }
+        let dst = match map {
+            Map::SpriteRom => &mut self.fb.sprite_rom[..],
+            Map::ColorRom => &mut self.fb.color_rom[..],
+            Map::TileRom => &mut self.fb.tile_rom[..],
+            Map::PaletteRom => &mut self.fb.palette_rom[..],
+            Map::Rom => &mut self.ctx.cpu.bus.memory.rom[offset..],
+        };
+        dst[..buf.len()].copy_from_slice(&buf);
     }
-    pub fn load_rom(&mut self, rom: &Vec<String>) {
+    pub fn load_rom(&mut self, rom: &[String]) {
         let mut collection: Vec<&str> = Vec::new();
 
         // Skip the target directory and use provided args
         for i in rom.iter().skip(1) {
-            collection.push(&i);
+            collection.push(i);
         }
         for y in collection.iter() {
             let path = Path::new(y);
@@ -125,177 +370,170 @@ impl Pacman {
                         _ => {} // Do nothing for non matches
                     }
                 }
-                println!("Rom files found & loaded..");
+                eprintln!("Rom files found & loaded..");
             } else if path.is_file() || !path.is_dir() {
                 eprintln!(
                     "Pacman roms not found, please check your rom directory or provided arguments"
                 );
-                panic!(format!("Attempted to load: {}", path.display()));
+                panic!("Attempted to load: {}", path.display());
             }
         }
     }
-    pub fn render_sprite(&mut self, x: usize, y: usize, mut index: usize) {
+    pub fn render_sprite(&mut self, _x: usize, _y: usize, mut index: usize) {
         for y in 0..16 {
             for x in 0..16 {
                 self.fb.draw_sprite(x, y, index, index, 1);
                 index = index.wrapping_add(1);
             }
         }
-        self.fb.window.update_with_buffer(&self.fb.raster, WIDTH, HEIGHT);
-
+        self.fb.window.update_with_buffer(&self.fb.raster, WIDTH, HEIGHT).ok();
     }
 
+    // Draws the tilemap as the video hardware sees it, the window is updated once a frame
+    pub fn render_tiles(&mut self) {
+        self.fb.draw_tilemap(&self.ctx.cpu.bus.vram);
+    }
+}
 
-    // Render the whole tile map
-    pub(crate) fn render_tiles(&mut self) {
-        let mut tile: u8 = 0;
-        /*// VRAM screen offset
-        let bottom = 0x4000;
-        let middle = bottom + 64;
-        let top = middle + 0x380;*/
-
-        // AND the address with with 0x3F to wrap at 64 bytes (size of palette rom)
-        let pal_no = self.read(0x4400);
-        for y in 0..16 {
-            for x in 0..16 {
-                self.fb.draw_tile(tile as u8, x * 8, y * 8, 0);
-                tile = tile.wrapping_add(1);
+// Pacman memory map. A15 isn't decoded, 0x8000 - 0xFFFF mirrors 0x0000 - 0x7FFF.
+//   0000 - 3FFF  ROM
+//   4000 - 43FF  video RAM (tile numbers)
+//   4400 - 47FF  color RAM (tile palettes)
+//   4C00 - 4FFF  work RAM, 4FF0 - 4FFF are the sprite attributes
+//   5000 - 503F  read IN0, write the latches 5000 - 5007 (mirrored every 8 bytes)
+//   5040 - 507F  read IN1, write the sound registers (5040 - 505F) & sprite coordinates
+//                (5060 - 506F)
+//   5080 - 50BF  read DSW1
+//   50C0 - 50FF  read DSW2, write the watchdog
+// Nothing else is connected, reads return the pulled up bus & writes are ignored.
+impl Bus for Board {
+    fn read8(&self, addr: u16) -> u8 {
+        let addr = addr & 0x7FFF;
+        match addr {
+            0x0000..=0x3FFF => self.memory.rom[addr as usize],
+            0x4000..=0x47FF => self.vram[addr as usize - 0x4000],
+            0x4C00..=0x4FFF => self.memory.ram[addr as usize - 0x4C00],
+            0x5000..=0x503F => self.in0.read(),
+            0x5040..=0x507F => self.in1.read(),
+            0x5080..=0x50BF => self.dip.read(),
+            0x50C0..=0x50FF => 0xFF, // No DSW2 on Pac-Man boards
+            // The unconnected 0x4800 area reads as 0xBF on real boards
+            0x4800..=0x4BFF => 0xBF,
+            _ => {
+                debug!("Read from unmapped address {:04X}", addr);
+                0xFF
             }
-            self.fb.window.update_with_buffer(&self.fb.raster, WIDTH, HEIGHT).ok();
         }
     }
-    pub fn draw_screen(&mut self) {
-        // Iterate over VRAM
-        for i in 0x4300..=0x4FFF {
-            for shift in 0..8 as isize {
-                // println!("Read i:{:04x}", self.read(i));
-                for y in 0..=HEIGHT as isize {
-                    let x = i % (WIDTH as usize + shift as usize + y as usize);
-                    self.fb.draw_sprite(x as usize, y as usize + shift as usize, 1 as usize, 1, 1);
-                    // self.fb.draw_tile(i as u8, y as u8, x as u8, 1);
-                }
-                self.fb.window.update_with_buffer(&self.fb.raster, WIDTH, HEIGHT).ok();
+
+    fn write8(&mut self, addr: u16, byte: u8) {
+        let addr = addr & 0x7FFF;
+        match addr {
+            0x0000..=0x3FFF => debug!("Attempting write to ROM: {:04x}", addr),
+            0x4000..=0x47FF => self.vram[addr as usize - 0x4000] = byte,
+            0x4C00..=0x4FFF => self.memory.ram[addr as usize - 0x4C00] = byte,
+            0x5000..=0x503F => self.latch(addr & 0x07, byte),
+            // 4 bit registers, only the low nibble is connected
+            0x5040..=0x505F => self.sound[addr as usize - 0x5040] = byte & 0x0F,
+            0x5060..=0x506F => self.sprite_coords[addr as usize - 0x5060] = byte,
+            0x50C0..=0x50FF => {
+                trace!("Watchdog kicked after {} vblanks", self.watchdog);
+                self.watchdog = 0;
             }
+            _ => debug!("Write to unmapped address {:04X}: {:02X}", addr, byte),
         }
     }
-    //To to draw tile 4000, you mask with 1F to get 0 for reversed X,
-    // then mask with 20 (and shift right 5) to get 0 again for Y (within that block).
-    // Then reverse the X (1F-X) to flip the order, add 34 to Y
-    // (because this block is on row 34 of the screen), multiply by the 8x8 tile size, & draw the tile.
-    // Then repeat for tile 4001
-
-    // Bottom:
-    // First 64 addresses draw two rows, right to left, top to bottom
-    // Note Sprites do not have any address encoding unlike tiles.
-    // Each row 32 tiles wide.
-    // Each fruit takes up 4 tiles
-    pub fn draw_fruits(&mut self) {
-        // Get the tile index at address 0x4004;
-        let pal_no = 1;
-        for i in 0x4000..=0x4FFF {
-
-            // self.fb.vblank = true;
-
-            let addr = self.fb.sprite_rom[i] * 64;
-            let mut x = (i & 0x1F) * 8;
-            let mut y = ((i & 0x20) >> 5) * 8 + 34;
-            for row in 0..HEIGHT {
-                self.fb.draw_slice(x, y, i, 1);
-            }
-            self.fb.window.update_with_buffer(&self.fb.raster, WIDTH, HEIGHT).ok();
-
+    // Only the low byte of the port address is decoded on the Pacman board.
+    // A write to port 0 latches the interrupt vector which is later put on the data bus
+    // while the CPU acknowledges the vblank interrupt.
+    fn port_out(&mut self, port: u16, byte: u8) {
+        self.port_out = byte;
+        if port & 0xFF == 0 {
+            self.int_vector = byte;
         }
     }
-}
 
-// Mapper trait for the Pacman hardware
-pub trait Mapper {
-    fn read(&self, addr: u16) -> u8;
-    fn write(&mut self, addr: u16, byte: u8);
-}
+    // The latched vector is put on the data bus during the interrupt acknowledge cycle
+    fn int_data(&self) -> u8 {
+        self.int_vector
+    }
 
-impl Mapper for Pacman {
-    fn read(&self, addr: u16) -> u8 {
-        match addr {
-            0x0000..=0x3FFF => self.ctx.cpu.memory.rom[addr as usize],
-            0x4000..=0x43FF => {
-                debug!("Read to VRAM {:04x}", addr);
-                self.fb.tile_rom[addr as usize - 0x4000]
-                // self.fb.vram[addr as usize - 0x4000]
+    // Clearing the latch at 0x5000 also resets the vblank interrupt flip-flop
+    fn int_cleared(&mut self) -> bool {
+        std::mem::take(&mut self.int_cleared)
+    }
+
+    // The vblank interrupt is gated by the latch at 0x5000, the watchdog counts vblanks
+    fn vblank(&mut self) -> bool {
+        if self.watchdog_enabled {
+            self.watchdog += 1;
+            if self.watchdog >= WATCHDOG_VBLANKS {
+                warn!("Watchdog expired after {} vblanks, resetting", self.watchdog);
+                self.watchdog_expired = true;
             }
-            0x4400..=0x47FF => {
-                debug!("Read to VRAM {:04x}", addr);
-                self.fb.palette_rom[addr as usize - 0x4400]
-                // self.fb.vram[addr as usize - 0x4400]
-            },
-            0x4800..=0x4BFF => {
-                debug!("Read to VRAM {:04x}", addr);
-                self.ctx.cpu.memory.ram[addr as usize - 0x4800]
-            },
-            0x4C00..=0x4FFF => {
-                debug!("Read to WRAM {:04x}", addr);
-                self.ctx.cpu.memory.ram[addr as usize - 0x4C00]
-            },
-            0x5000 => {
-                info!("Read to IO interrupt");
-                self.int_enable as u8
-            },
-            0x5040..=0x507F => unimplemented!(), // self.in0 // Joystick and start buttons
-            _ => unimplemented!("Read to address:{:04X}", addr),
         }
+        self.int_enable
     }
+}
 
-    fn write(&mut self, addr: u16, byte: u8) {
-        match addr {
-            0x4000..=0x43FF => {
-                println!("Write to VRAM: {:04x}, byte: {:02x}", addr, byte);
-                debug!("Write to VRAM: {:04x}, byte: {:02x}", addr, byte);
-                self.fb.vram[addr as usize - 0x4000] = byte
-            }
-            0x4400..=0x47FF => {
-                println!("Write to VRAM: {:04x}, byte: {:02x}", addr, byte);
-                debug!("Write to VRAM: {:04x}, byte: {:02x}", addr, byte);
-                self.fb.vram[addr as usize - 0x4400] = byte
-            }
-            0x4800..=0x4FEF => {
-                println!("Write to VRAM: {:04x}, byte: {:02x}", addr, byte);
-                debug!("Write to VRAM: {:04x}, byte: {:02x}", addr, byte);
-                self.fb.vram[addr as usize - 0x4800] = byte
-            }
-            0x4C00..=0x4FFF => {
-                println!("Write to WRAM: {:04x}, byte: {:02x}", addr, byte);
-                debug!("Write to WRAM: {:04x}, byte: {:02x}", addr, byte);
-                self.ctx.cpu.memory.ram[addr as usize - 0x4C00] = byte
-            }
+// Board section of a save state
+impl Snapshot for Board {
+    fn save(&self, out: &mut StateWriter) {
+        out.bytes(&self.memory.ram);
+        out.bytes(&self.vram);
+        out.bytes(&self.sound);
+        out.bytes(&self.sprite_coords);
+        out.u8(self.int_vector);
+        out.u8(self.port_in);
+        out.u8(self.port_out);
+        let dip = &self.dip;
+        out.u8(dip.coins_per_game);
+        out.u8(dip.lives_per_game);
+        out.u8(dip.bonus_extra_life);
+        out.bool(dip.difficulty);
+        out.bool(dip.ghost_names);
+        out.u8(self.in0.read());
+        out.u8(self.in1.read());
+        for latch in 0..8 {
+            out.bool(self.latched(latch));
+        }
+        out.u32(self.watchdog);
+    }
 
-            0x5000 => {
-                debug!("Enabling interrupts: {:04x}, byte: {:02x}", addr, byte);
-                self.int_enable = true;
-                self.ctx.cpu.int.int = byte & 0x01 != 0;
-                self.ctx.cpu.int.irq = (byte & 0x01) != 0;
-            }
-            0x5001 => println!("Stubbed: Sound enable:{}", byte & 0x01 != 0),
-            0x5002 => println!("Stubbed: Aux board enable write."),
-            0x5004 => println!("Player 1 start lamp:{}", byte & 0x01 != 0),
-            0x5005 => println!("Player 2 start lamp:{}", byte & 0x01 != 0),
-            0x5006 => {
-                println!("Coin lockout:{}", (byte & 0x01) != 0);
-                self.ctx.cpu.memory.ram[addr as usize] = byte;
-                if (byte & 0x01) != 0 {
-                    self.c_lockout = true;
-                }
-            }
-            0x5007 => {
-                println!("Coin lockout:{}", (byte & 0x01) != 0);
-                self.ctx.cpu.memory.ram[addr as usize] = byte;
-                if (byte & 0x01) != 0 {
-                    self.c_counter = true;
-                }
-            }
-            _ => unimplemented!(
-                "{}",
-                format!("Write address:{:02X} Byte:{:02X}", addr, byte)
-            ),
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        input.bytes("RAM", &mut self.memory.ram)?;
+        input.bytes("VRAM", &mut self.vram)?;
+        input.bytes("Sound registers", &mut self.sound)?;
+        input.bytes("Sprite coordinates", &mut self.sprite_coords)?;
+        self.int_vector = input.u8()?;
+        self.port_in = input.u8()?;
+        self.port_out = input.u8()?;
+        let dip = &mut self.dip;
+        dip.coins_per_game = input.u8()?;
+        dip.lives_per_game = input.u8()?;
+        dip.bonus_extra_life = input.u8()?;
+        dip.difficulty = input.bool()?;
+        dip.ghost_names = input.bool()?;
+        self.in0.write(input.u8()?);
+        self.in1.write(input.u8()?);
+        for latch in 0..8 {
+            self.latch(latch, input.u8()?);
         }
+        self.watchdog = input.u32()?;
+        Ok(())
+    }
+}
+
+// The board & CPU, then the display
+impl Snapshot for Pacman {
+    fn save(&self, out: &mut StateWriter) {
+        self.ctx.save(out);
+        out.bool(self.fb.vblank);
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.ctx.load(input)?;
+        self.fb.vblank = input.bool()?;
+        Ok(())
     }
 }
@@ -0,0 +1,291 @@
+// Save states: the complete machine state in a versioned binary file.
+//
+// Every part of a machine implements `Snapshot`, writing its fields in a fixed order. The
+// file is little endian:
+//   header  "Z80S", u16 version
+//   body    the sections of the machine, e.g. for Pac-Man the CPU (registers, shadows,
+//           flags, interrupt state, T states) followed by the board (RAM, VRAM, sprite &
+//           sound registers, interrupt vector & enable, DIP switches & inputs), the frame
+//           count & the position in the current frame
+// ROMs aren't part of a state, they are loaded from their files as usual. VERSION has to
+// change whenever a section changes, older files are rejected instead of misread.
+use crate::bus::Bus;
+use crate::cpu::{Cpu, CpuModel, Flags, Interrupt, Registers};
+use crate::error::StateError;
+use crate::interconnect::{Interconnect, FRAME_CYCLES};
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+pub const MAGIC: &[u8; 4] = b"Z80S";
+pub const VERSION: u16 = 3;
+
+pub trait Snapshot {
+    fn save(&self, out: &mut StateWriter);
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError>;
+}
+
+#[derive(Default)]
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+    pub fn bool(&mut self, value: bool) {
+        self.data.push(value as u8);
+    }
+    pub fn u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    // Length prefixed, see `StateReader::bytes`
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.data.extend_from_slice(bytes);
+    }
+}
+
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < len {
+            return Err(StateError::Corrupt(String::from("Unexpected end of file")));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        Ok(self.u8()? != 0)
+    }
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    // Fills `dst`, the stored length has to match
+    pub fn bytes(&mut self, name: &str, dst: &mut [u8]) -> Result<(), StateError> {
+        let len = self.u32()? as usize;
+        if len != dst.len() {
+            let message = format!("{} is {} bytes, expected {}", name, len, dst.len());
+            return Err(StateError::Corrupt(message));
+        }
+        dst.copy_from_slice(self.take(len)?);
+        Ok(())
+    }
+}
+
+// Header & body of a state
+pub fn encode<S: Snapshot>(state: &S) -> Vec<u8> {
+    let mut out = StateWriter::default();
+    out.data.extend_from_slice(MAGIC);
+    out.u16(VERSION);
+    state.save(&mut out);
+    out.data
+}
+
+// Restores a state from `encode`. On errors the previous state is put back, a bad file
+// never leaves a half loaded machine.
+pub fn decode<S: Snapshot>(state: &mut S, data: &[u8]) -> Result<(), StateError> {
+    if !data.starts_with(MAGIC) {
+        return Err(StateError::NotAState);
+    }
+    let mut input = StateReader { data: &data[4..] };
+    let version = input.u16()?;
+    if version != VERSION {
+        return Err(StateError::Version {
+            found: version,
+            expected: VERSION,
+        });
+    }
+    let backup = encode(state);
+    let result = state.load(&mut input).and_then(|_| match input.data.len() {
+        0 => Ok(()),
+        n => Err(StateError::Corrupt(format!("{} bytes left over", n))),
+    });
+    if result.is_err() {
+        let mut input = StateReader { data: &backup[6..] };
+        state
+            .load(&mut input)
+            .expect("Restoring the previous state");
+    }
+    result
+}
+
+pub fn save<S: Snapshot, P: AsRef<Path>>(state: &S, path: P) -> Result<(), StateError> {
+    if let Some(dir) = path.as_ref().parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(fs::write(path, encode(state))?)
+}
+
+pub fn load<S: Snapshot, P: AsRef<Path>>(state: &mut S, path: P) -> Result<(), StateError> {
+    decode(state, &fs::read(path)?)
+}
+
+impl Snapshot for Registers {
+    fn save(&self, out: &mut StateWriter) {
+        for r in [self.a, self.b, self.c, self.d, self.e, self.h, self.l] {
+            out.u8(r);
+        }
+        for r in [
+            self.a_, self.b_, self.c_, self.d_, self.e_, self.h_, self.l_,
+        ] {
+            out.u8(r);
+        }
+        for r in [self.m, self.i, self.r] {
+            out.u8(r);
+        }
+        for r in [
+            self.pc,
+            self.prev_pc,
+            self.sp,
+            self.ix,
+            self.iy,
+            self.memptr,
+        ] {
+            out.u16(r);
+        }
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        for r in [
+            &mut self.a,
+            &mut self.b,
+            &mut self.c,
+            &mut self.d,
+            &mut self.e,
+            &mut self.h,
+            &mut self.l,
+            &mut self.a_,
+            &mut self.b_,
+            &mut self.c_,
+            &mut self.d_,
+            &mut self.e_,
+            &mut self.h_,
+            &mut self.l_,
+            &mut self.m,
+            &mut self.i,
+            &mut self.r,
+        ] {
+            *r = input.u8()?;
+        }
+        for r in [
+            &mut self.pc,
+            &mut self.prev_pc,
+            &mut self.sp,
+            &mut self.ix,
+            &mut self.iy,
+            &mut self.memptr,
+        ] {
+            *r = input.u16()?;
+        }
+        Ok(())
+    }
+}
+
+// F & F' as bytes
+impl Snapshot for Flags {
+    fn save(&self, out: &mut StateWriter) {
+        out.u8(self.get());
+        out.u8(self.get_shadow());
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.set(input.u8()?);
+        self.set_shadow(input.u8()?);
+        Ok(())
+    }
+}
+
+impl Snapshot for Interrupt {
+    fn save(&self, out: &mut StateWriter) {
+        out.bool(self.irq);
+        out.bool(self.nmi_pending);
+        out.bool(self.iff1);
+        out.bool(self.iff2);
+        out.u8(self.mode);
+        out.bool(self.ei_delay);
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.irq = input.bool()?;
+        self.nmi_pending = input.bool()?;
+        self.iff1 = input.bool()?;
+        self.iff2 = input.bool()?;
+        self.mode = input.u8()?;
+        self.ei_delay = input.bool()?;
+        Ok(())
+    }
+}
+
+// The CPU followed by its bus
+impl<B: Bus + Snapshot> Snapshot for Cpu<B> {
+    fn save(&self, out: &mut StateWriter) {
+        self.reg.save(out);
+        self.flags.save(out);
+        self.int.save(out);
+        out.bool(self.halted);
+        out.u64(self.cycles as u64);
+        out.u16(self.io.port);
+        out.u8(self.io.value);
+        out.u8(match self.model {
+            CpuModel::Z80 => 0,
+            CpuModel::I8080 => 1,
+        });
+        self.bus.save(out);
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.reg.load(input)?;
+        self.flags.load(input)?;
+        self.int.load(input)?;
+        self.halted = input.bool()?;
+        self.cycles = input.u64()? as usize;
+        self.io.port = input.u16()?;
+        self.io.value = input.u8()?;
+        self.model = match input.u8()? {
+            0 => CpuModel::Z80,
+            1 => CpuModel::I8080,
+            model => return Err(StateError::Corrupt(format!("Unknown CPU model {}", model))),
+        };
+        self.bus.load(input)
+    }
+}
+
+impl<B: Bus + Snapshot> Snapshot for Interconnect<B> {
+    fn save(&self, out: &mut StateWriter) {
+        self.cpu.save(out);
+        out.u32(self.frame_count);
+        out.u32(self.frame_cycle as u32);
+    }
+
+    fn load(&mut self, input: &mut StateReader) -> Result<(), StateError> {
+        self.cpu.load(input)?;
+        self.frame_count = input.u32()?;
+        self.frame_cycle = input.u32()? as usize;
+        if self.frame_cycle >= FRAME_CYCLES {
+            let message = format!("Frame position {} out of range", self.frame_cycle);
+            return Err(StateError::Corrupt(message));
+        }
+        Ok(())
+    }
+}
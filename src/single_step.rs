@@ -0,0 +1,252 @@
+// Per instruction golden state tests in the SingleStepTests format
+// (https://github.com/SingleStepTests/z80). Every `<opcode>.json` holds a list of tests: the
+// registers & RAM before & after executing one instruction, the bus activity of every T
+// state and, for I/O instructions, the port accesses.
+//
+// tests/singlestep has vectors for a sample of every prefix group (main, CB, ED, DD, FD,
+// DD CB & FD CB), written from the M cycle timing of the Zilog manual independently of the
+// core. `test_single_step` runs them on every `cargo test`; with SINGLESTEP_DIR set (e.g. to
+// a checkout of the full upstream set) it also runs every file found there.
+//
+// The core isn't T state accurate inside an instruction, so the bus activity is compared as
+// the number of T states & the ordered list of memory & I/O accesses (address, data & read /
+// write pins), idle T states are skipped. Q & the P/V latch of LD A,I / LD A,R after EI
+// aren't modelled.
+//
+// Mismatches are written to target/singlestep/<set>/<opcode>.txt, one line per field in a
+// fixed order so runs can be diffed. SUMMARY.txt has the pass count of every opcode.
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Flat 64K, port reads are answered with the values of the vector. Every access is logged
+// with the pins of the vector format: read / write & memory / I/O request.
+struct TestBus {
+    memory: Vec<u8>,
+    port_reads: VecDeque<u8>,
+    ports: Vec<(u16, u8, String)>,
+    log: RefCell<Vec<(u16, u8, &'static str)>>,
+}
+
+impl Bus for TestBus {
+    fn read8(&self, addr: u16) -> u8 {
+        let byte = self.memory[addr as usize];
+        self.log.borrow_mut().push((addr, byte, "r-m-"));
+        byte
+    }
+    fn write8(&mut self, addr: u16, byte: u8) {
+        self.memory[addr as usize] = byte;
+        self.log.get_mut().push((addr, byte, "-wm-"));
+    }
+    fn port_in(&mut self, port: u16) -> u8 {
+        let byte = self.port_reads.pop_front().unwrap_or(0xFF);
+        self.ports.push((port, byte, String::from("r")));
+        self.log.get_mut().push((port, byte, "r--i"));
+        byte
+    }
+    fn port_out(&mut self, port: u16, byte: u8) {
+        self.ports.push((port, byte, String::from("w")));
+        self.log.get_mut().push((port, byte, "-w-i"));
+    }
+}
+
+// Compared after every test, in this order
+const REGISTERS: [&str; 22] = [
+    "pc", "sp", "a", "f", "b", "c", "d", "e", "h", "l", "i", "r", "ix", "iy", "af_", "bc_", "de_",
+    "hl_", "wz", "iff1", "iff2", "im",
+];
+
+fn number(value: &Value) -> u16 {
+    value.as_u64().unwrap_or(0) as u16
+}
+
+// [[addr, value], ...] lists of "ram" & "ports"
+fn entries(list: &Value) -> impl Iterator<Item = &Vec<Value>> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.as_array())
+}
+
+fn set_state(cpu: &mut Cpu<TestBus>, state: &Value) {
+    let get = |name: &str| number(&state[name]);
+    let reg = &mut cpu.reg;
+    reg.pc = get("pc");
+    reg.sp = get("sp");
+    reg.a = get("a") as u8;
+    reg.b = get("b") as u8;
+    reg.c = get("c") as u8;
+    reg.d = get("d") as u8;
+    reg.e = get("e") as u8;
+    reg.h = get("h") as u8;
+    reg.l = get("l") as u8;
+    reg.i = get("i") as u8;
+    reg.r = get("r") as u8;
+    reg.ix = get("ix");
+    reg.iy = get("iy");
+    reg.a_ = (get("af_") >> 8) as u8;
+    reg.b_ = (get("bc_") >> 8) as u8;
+    reg.c_ = get("bc_") as u8;
+    reg.d_ = (get("de_") >> 8) as u8;
+    reg.e_ = get("de_") as u8;
+    reg.h_ = (get("hl_") >> 8) as u8;
+    reg.l_ = get("hl_") as u8;
+    reg.memptr = get("wz");
+    cpu.flags.set(get("f") as u8);
+    cpu.flags.set_shadow(get("af_") as u8);
+    cpu.int.iff1 = get("iff1") != 0;
+    cpu.int.iff2 = get("iff2") != 0;
+    cpu.int.mode = get("im") as u8;
+    cpu.int.ei_delay = get("ei") != 0;
+    for entry in entries(&state["ram"]) {
+        cpu.bus.memory[number(&entry[0]) as usize] = number(&entry[1]) as u8;
+    }
+}
+
+fn register(cpu: &Cpu<TestBus>, name: &str) -> u16 {
+    let pair = |high: u8, low: u8| (high as u16) << 8 | low as u16;
+    let reg = &cpu.reg;
+    match name {
+        "pc" => reg.pc,
+        "sp" => reg.sp,
+        "a" => reg.a as u16,
+        "f" => cpu.flags.get() as u16,
+        "b" => reg.b as u16,
+        "c" => reg.c as u16,
+        "d" => reg.d as u16,
+        "e" => reg.e as u16,
+        "h" => reg.h as u16,
+        "l" => reg.l as u16,
+        "i" => reg.i as u16,
+        "r" => reg.r as u16,
+        "ix" => reg.ix,
+        "iy" => reg.iy,
+        "af_" => pair(reg.a_, cpu.flags.get_shadow()),
+        "bc_" => pair(reg.b_, reg.c_),
+        "de_" => pair(reg.d_, reg.e_),
+        "hl_" => pair(reg.h_, reg.l_),
+        "wz" => reg.memptr,
+        "iff1" => cpu.int.iff1 as u16,
+        "iff2" => cpu.int.iff2 as u16,
+        "im" => cpu.int.mode as u16,
+        _ => unreachable!("{}", name),
+    }
+}
+
+// Mismatches of a single test, empty if it passed
+fn run(test: &Value) -> Vec<String> {
+    let ports: Vec<(u16, u8, String)> = entries(&test["ports"])
+        .map(|p| {
+            let dir = p[2].as_str().unwrap_or("").to_string();
+            (number(&p[0]), number(&p[1]) as u8, dir)
+        })
+        .collect();
+    let mut cpu = Cpu::new(TestBus {
+        memory: vec![0; 0x1_0000],
+        port_reads: ports.iter().filter(|p| p.2 == "r").map(|p| p.1).collect(),
+        ports: Vec::new(),
+        log: RefCell::new(Vec::new()),
+    });
+    set_state(&mut cpu, &test["initial"]);
+    let cycles = match cpu.step() {
+        Ok(info) => info.cycles,
+        Err(e) => return vec![e.to_string()],
+    };
+
+    let mut diffs = Vec::new();
+    let state = &test["final"];
+    for name in REGISTERS.iter() {
+        let (expected, got) = (number(&state[*name]), register(&cpu, name));
+        if expected != got {
+            diffs.push(format!(
+                "{}: expected {:04X} got {:04X}",
+                name, expected, got
+            ));
+        }
+    }
+    for entry in entries(&state["ram"]) {
+        let addr = number(&entry[0]);
+        let (expected, got) = (number(&entry[1]) as u8, cpu.bus.memory[addr as usize]);
+        if expected != got {
+            diffs.push(format!(
+                "ram[{:04X}]: expected {:02X} got {:02X}",
+                addr, expected, got
+            ));
+        }
+    }
+
+    // Bus activity, one [addr, data, pins] entry per T state. Idle T states ("----") only
+    // count, the accesses are compared in order.
+    let activity = test["cycles"].as_array().cloned().unwrap_or_default();
+    if activity.len() != cycles {
+        let line = format!("cycles: expected {} got {}", activity.len(), cycles);
+        diffs.push(line);
+    }
+    let accesses: Vec<(u16, u8, &str)> = activity
+        .iter()
+        .filter_map(|c| Some((number(&c[0]), number(&c[1]) as u8, c[2].as_str()?)))
+        .filter(|c| c.2 != "----")
+        .collect();
+    let got = cpu.bus.log.get_mut();
+    if accesses != *got {
+        diffs.push(format!("bus: expected {:04X?} got {:04X?}", accesses, got));
+    }
+    if ports != cpu.bus.ports {
+        let got = &cpu.bus.ports;
+        diffs.push(format!("ports: expected {:02X?} got {:02X?}", ports, got));
+    }
+    diffs
+}
+
+// Runs every vector file in `dir`, reports go to target/singlestep/<set>
+pub fn run_vectors(dir: &Path, set: &str) {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "No test vectors in {}", dir.display());
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let reports = root.join("target/singlestep").join(set);
+    let _ = fs::remove_dir_all(&reports);
+    fs::create_dir_all(&reports).unwrap();
+    let mut summary = String::new();
+    let mut failed = Vec::new();
+    for path in &files {
+        let opcode = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let tests: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        let tests = tests.as_array().unwrap();
+        let mut report = String::new();
+        let mut passed = 0;
+        for test in tests {
+            let diffs = run(test);
+            if diffs.is_empty() {
+                passed += 1;
+                continue;
+            }
+            writeln!(report, "{}", test["name"].as_str().unwrap_or("?")).unwrap();
+            for diff in diffs {
+                writeln!(report, "  {}", diff).unwrap();
+            }
+        }
+        writeln!(summary, "{}: {}/{}", opcode, passed, tests.len()).unwrap();
+        if passed < tests.len() {
+            fs::write(reports.join(format!("{}.txt", opcode)), report).unwrap();
+            failed.push(opcode);
+        }
+    }
+    fs::write(reports.join("SUMMARY.txt"), summary).unwrap();
+    assert!(
+        failed.is_empty(),
+        "Failing opcodes (see target/singlestep/{}): {}",
+        set,
+        failed.join(", ")
+    );
+}
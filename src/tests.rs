@@ -1,121 +1,1374 @@
 #[cfg(test)]
 mod tests {
+    use crate::assembler::assemble;
+    use crate::bus::Bus;
+    use crate::cpm::disk::{DiskImage, Drive, HostDir};
+    use crate::cpm::Cpm;
+    use crate::cpu::{CpuModel, StepInfo};
+    use crate::error::CpuError;
     use crate::instruction_info::Register;
     use crate::instruction_info::Register::{BC, DE, HL};
     use crate::interconnect::Interconnect;
-    use crate::memory::MemoryRW;
+    use crate::memory::Memory;
 
     #[test]
     fn test_hf_flag() {
         // Make sure HF flag gets set on accumulator value wrap from FFh to 00h.
-        let mut i = Interconnect::new();
+        let mut i = Interconnect::new(Memory::new());
         i.cpu.reg.a = 0xff;
         i.cpu.inc(Register::A);
-        assert_eq!(i.cpu.flags.hf, true);
+        assert!(i.cpu.flags.hf);
     }
 
     #[test]
     fn test_hf_high_byte() {
         // The half carry flag should be set once we increment HL from 00FFh to 0000h
-        let mut i = Interconnect::new();
+        let mut i = Interconnect::new(Memory::new());
         i.cpu.write_pair_direct(BC, 1); // Set BC to 1 (we will increment HL by 1)
         i.cpu.reg.a = 0xff;
         i.cpu.write_pair_direct(HL, 0x00FF);
         i.cpu.add_hl(BC);
         i.cpu.inc(Register::A);
-        assert_eq!(i.cpu.flags.hf, true);
+        assert!(i.cpu.flags.hf);
     }
 
     #[test]
-    fn fast_z80() {
-        // Assert the tests executed CPU cycle amount vs real hardware cycles
-        assert_eq!(exec_test("tests/prelim.com"), 8721);
-        assert_eq!(exec_test("tests/8080PRE.COM"), 7772);
-        assert_eq!(exec_test("tests/CPUTEST.COM"), 240551424);
+    fn test_pacman_bus_map() {
+        // Writes go through the board memory map, ROM stays read only
+        use crate::pacman_arcade::pacman::Board;
+        let mut i = Interconnect::new(Board::new());
+        i.cpu.bus.memory.rom[0x0000] = 0x32; // LD (4000h), A
+        i.cpu.bus.memory.rom[0x0001] = 0x00;
+        i.cpu.bus.memory.rom[0x0002] = 0x40;
+        i.cpu.bus.memory.rom[0x0003] = 0x32; // LD (0010h), A
+        i.cpu.bus.memory.rom[0x0004] = 0x10;
+        i.cpu.bus.memory.rom[0x0005] = 0x00;
+        i.cpu.reg.a = 0x55;
+        i.run_tests().unwrap();
+        i.run_tests().unwrap();
+        assert_eq!(i.cpu.bus.vram[0], 0x55);
+        assert_eq!(i.cpu.bus.memory.rom[0x0010], 0x00);
+
+        // Same on the older front-end's board, unmapped accesses don't stop the machine
+        let rom = i.cpu.bus.memory.rom.clone();
+        let mut i = Interconnect::new(crate::pacman::pacman::Board::new());
+        i.cpu.bus.memory.rom = rom;
+        i.cpu.reg.a = 0x55;
+        i.run_tests().unwrap();
+        i.run_tests().unwrap();
+        assert_eq!(i.cpu.bus.vram[0], 0x55);
+        assert_eq!(i.cpu.bus.memory.rom[0x0010], 0x00);
+        assert_eq!(i.cpu.bus.read8(0x6000), 0xFF);
+        i.cpu.bus.write8(0x5000, 1);
+        assert!(i.cpu.bus.vblank());
     }
 
-    #[test]
-    #[ignore] // Ignored for now as they do not pass
-    fn z80_precise() {
-        assert_eq!(exec_test("tests/zexdoc.com"), 46734978649);
-        // ^ Bug in LDA_IM ?
-        // assert_eq!(exec_test("tests/zexall.com"), 46734978649);
+    // Test bus that records port writes and answers port reads with the low byte of the port
+    struct PortBus {
+        memory: Memory,
+        out: Vec<(u16, u8)>,
     }
 
-    // #[test]
-    fn all_tests() {
-        assert_eq!(exec_test("tests/prelim.com"), 8721);
-        assert_eq!(exec_test("tests/8080PRE.COM"), 7772);
-        assert_eq!(exec_test("tests/CPUTEST.COM"), 240551424);
-        assert_eq!(exec_test("tests/zexall.com"), 0);
-        assert_eq!(exec_test("tests/zexdoc.com"), 0);
+    impl Bus for PortBus {
+        fn read8(&self, addr: u16) -> u8 {
+            self.memory.rom[addr as usize]
+        }
+        fn write8(&mut self, addr: u16, byte: u8) {
+            self.memory.rom[addr as usize] = byte;
+        }
+        fn port_in(&mut self, port: u16) -> u8 {
+            port as u8
+        }
+        fn port_out(&mut self, port: u16, byte: u8) {
+            self.out.push((port, byte));
+        }
     }
 
-    fn exec_test(bin: &str) -> usize {
-        let mut i = Interconnect::new();
-        i.cpu.reset();
-        i.cpu.memory.load_tests(bin);
+    #[test]
+    fn test_io_ports() {
+        let bus = PortBus {
+            memory: Memory::new(),
+            out: Vec::new(),
+        };
+        let mut i = Interconnect::new(bus);
+        let program = [
+            0xDB, 0x42, // IN A, (42h)
+            0xD3, 0x10, // OUT (10h), A
+            0xED, 0x78, // IN A, (C)
+            0xED, 0x59, // OUT (C), E
+            0xED, 0xB2, // INIR
+        ];
+        i.cpu.bus.memory.rom[..program.len()].copy_from_slice(&program);
+        i.cpu.reg.a = 0x12;
+        i.cpu.write_pair_direct(BC, 0x0234);
+        i.cpu.write_pair_direct(DE, 0x0077);
+        i.cpu.write_pair_direct(HL, 0x8000);
+
+        i.run_tests().unwrap();
+        assert_eq!(i.cpu.reg.a, 0x42);
+        i.run_tests().unwrap();
+        assert_eq!(i.cpu.bus.out[0], (0x4210, 0x42));
+        i.run_tests().unwrap();
+        assert_eq!(i.cpu.reg.a, 0x34);
+        i.run_tests().unwrap();
+        assert_eq!(i.cpu.bus.out[1], (0x0234, 0x77));
 
-        // Patches the test rom(s) to intercept CP/M bdos routine
-        // Inject OUT *, A at 0x0000.
-        // Inject RET (0xC9) at 0x0007 to handle the return call.
-        // Inject IN, A * to store BDOS output
-        // If successful it should return to 0x0007.
+        // INIR repeats until B reaches 0, reading port BC each iteration
+        while i.cpu.reg.pc == 8 {
+            i.run_tests().unwrap();
+        }
+        assert_eq!(i.cpu.reg.b, 0);
+        assert_eq!(i.cpu.get_pair(HL), 0x8002);
+        assert_eq!(i.cpu.bus.memory.rom[0x8000], 0x34);
+        assert!(i.cpu.flags.zf);
+    }
 
-        i.cpu.memory.rom[0x0000] = 0xD3;
-        i.cpu.memory.rom[0x0001] = 0x00;
-        i.cpu.memory.rom[0x0005] = 0xDB;
-        i.cpu.memory.rom[0x0006] = 0x00;
-        i.cpu.memory.rom[0x0007] = 0xC9;
+    #[test]
+    fn test_halt() {
+        // HALT keeps the CPU idle (4 T states and one R increment per NOP) until an NMI arrives
+        let mut i = Interconnect::new(Memory::new());
+        i.cpu.bus.rom[0x0000] = 0x76; // HALT
+        i.cpu.reg.sp = 0x8000;
+        i.run_tests().unwrap();
+        assert!(i.cpu.halted);
+        assert_eq!(i.cpu.reg.pc, 0x0001);
 
-        // All test binaries start at 0x0100.
+        let (cycles, r) = (i.cpu.cycles, i.cpu.reg.r);
+        i.run_tests().unwrap();
+        i.run_tests().unwrap();
+        assert_eq!(i.cpu.reg.pc, 0x0001);
+        assert_eq!(i.cpu.cycles, cycles + 8);
+        assert_eq!(i.cpu.reg.r, r + 2);
+
+        i.cpu.int.nmi_pending = true;
+        i.cpu.poll_interrupt().unwrap();
+        assert!(!i.cpu.halted);
+        assert_eq!(i.cpu.reg.pc, 0x0066);
+        assert_eq!(i.cpu.bus.rom[0x7FFE], 0x01); // Returns to the instruction after HALT
+    }
+
+    #[test]
+    fn test_im2_interrupt() {
+        // The vector latched through port 0 selects the IM2 table entry,
+        // interrupts are only accepted after the instruction following EI
+        use crate::pacman_arcade::pacman::Board;
+        let mut i = Interconnect::new(Board::new());
+        let program = [
+            0xED, 0x5E, // IM 2
+            0x3E, 0x20, // LD A, 20h
+            0xD3, 0x00, // OUT (00h), A
+            0xFB, // EI
+            0x00, // NOP
+            0x00, // NOP
+        ];
+        i.cpu.bus.memory.rom[..program.len()].copy_from_slice(&program);
+        i.cpu.bus.memory.rom[0x0020] = 0x34;
+        i.cpu.bus.memory.rom[0x0021] = 0x12;
+        i.cpu.reg.sp = 0x5000;
+
+        for _ in 0..4 {
+            i.run_tests().unwrap();
+        }
+        i.cpu.generate_interrupt();
+        assert!(!i.cpu.poll_interrupt().unwrap());
+        i.run_tests().unwrap();
+        assert!(i.cpu.poll_interrupt().unwrap());
+        assert_eq!(i.cpu.reg.pc, 0x1234);
+        assert!(!i.cpu.int.iff1);
+        assert_eq!(i.cpu.bus.read8(0x4FFE), 0x08); // Return address
+    }
+
+    #[test]
+    fn test_im0_branches() {
+        // Single byte jumps put on the data bus in IM0 keep the PC they set
+        let mut i = Interconnect::new(IntBus {
+            memory: Memory::new(),
+            data: 0xE9, // JP (HL)
+            acks: 0,
+        });
+        i.cpu.int.iff1 = true;
         i.cpu.reg.pc = 0x0100;
-        let mut reset_counter = 0;
-
-        loop {
-            // Turn CMP Compatability on. This turns off any memory mapping
-            i.cpu.cpm_compat = true;
-            // i.cpu.debug = true;
-            i.run_tests();
-
-            if i.cpu.reg.pc == 0x76 {
-                assert_ne!(i.cpu.reg.pc, 0x76);
-            }
-
-            if i.cpu.reg.pc == 07 {
-                if i.cpu.reg.c == 9 {
-                    let mut de = i.cpu.get_pair(DE);
-                    'print: loop {
-                        let output = i.cpu.memory.rom[de as usize];
-                        if output as char == '$' {
-                            break 'print;
-                        } else if output as char != '$' {
-                            de += 1;
-                        }
-                        print!("{}", output as char);
+        i.cpu.write_pair_direct(HL, 0x1234);
+        i.cpu.generate_interrupt();
+        assert!(i.cpu.poll_interrupt().unwrap());
+        assert_eq!(i.cpu.reg.pc, 0x1234);
+
+        i.cpu.bus.data = 0xC9; // RET
+        i.cpu.bus.memory.rom[0x8000..0x8002].copy_from_slice(&[0x78, 0x56]);
+        i.cpu.reg.sp = 0x8000;
+        i.cpu.int.iff1 = true;
+        i.cpu.generate_interrupt();
+        assert!(i.cpu.poll_interrupt().unwrap());
+        assert_eq!((i.cpu.reg.pc, i.cpu.reg.sp), (0x5678, 0x8002));
+
+        // Anything else leaves PC at the interrupted instruction
+        i.cpu.bus.data = 0x3C; // INC A
+        i.cpu.int.iff1 = true;
+        i.cpu.generate_interrupt();
+        assert!(i.cpu.poll_interrupt().unwrap());
+        assert_eq!(i.cpu.reg.pc, 0x5678);
+        assert_eq!(i.cpu.bus.acks, 3);
+    }
+
+    #[test]
+    fn test_index_instructions() {
+        let mut i = Interconnect::new(Memory::new());
+        let program = [
+            0xDD, 0x21, 0x00, 0x80, // LD IX, 8000h
+            0xDD, 0x36, 0x05, 0x81, // LD (IX+5), 81h
+            0xDD, 0xCB, 0x05, 0x00, // RLC (IX+5), B
+            0xFD, 0x21, 0x10, 0x80, // LD IY, 8010h
+            0xFD, 0x7E, 0xF5, // LD A, (IY-11)
+            0xDD, 0x26, 0x12, // LD IXH, 12h
+            0xDD, 0x84, // ADD A, IXH
+        ];
+        i.cpu.bus.rom[..program.len()].copy_from_slice(&program);
+        for _ in 0..7 {
+            i.run_tests().unwrap();
+        }
+        assert_eq!(i.cpu.bus.rom[0x8005], 0x03);
+        assert_eq!(i.cpu.reg.b, 0x03);
+        assert_eq!(i.cpu.reg.ix, 0x1200);
+        assert_eq!(i.cpu.reg.a, 0x15);
+        assert_eq!(i.cpu.reg.pc, program.len() as u16);
+        assert_eq!(i.cpu.cycles, 108);
+        assert_eq!(i.cpu.reg.r, 14);
+    }
+
+    #[test]
+    fn test_opcode_tables() {
+        // Metadata & execution come from the same table rows
+        use crate::instruction_info::Instruction;
+        let ld = Instruction::decode(0x1A).unwrap();
+        assert_eq!(
+            (ld.name.trim(), ld.opcode, ld.bytes, ld.cycles),
+            ("LD A, (DE)", 0x1A, 1, 7)
+        );
+        let ld = Instruction::decode(0xFD36).unwrap();
+        assert_eq!(
+            (ld.name.trim(), ld.bytes, ld.cycles),
+            ("LD (IY+*), *", 4, 19)
+        );
+        assert!(Instruction::decode(0xDD00).is_none()); // NOP, the prefix is ignored
+
+        let mut i = Interconnect::new(Memory::new());
+        i.cpu.bus.rom[..5].copy_from_slice(&[
+            0x00, // NOP
+            0x18, 0xFD, // JR -3
+            0xDD, 0x00, // NOP (prefix ignored)
+        ]);
+        i.cpu.reg.pc = 1;
+        assert_eq!(i.run_tests().unwrap().cycles, 12);
+        assert_eq!(i.cpu.reg.pc, 0);
+        // The ignored prefix is a step of its own, interrupts wait for the NOP
+        i.cpu.reg.pc = 3;
+        i.cpu.int.iff1 = true;
+        i.cpu.generate_interrupt();
+        assert_eq!(i.run_tests().unwrap().cycles, 4);
+        assert_eq!(i.cpu.reg.pc, 4);
+        assert!(!i.cpu.poll_interrupt().unwrap());
+        assert_eq!(i.run_tests().unwrap().cycles, 4);
+        assert_eq!(i.cpu.reg.pc, 5);
+        assert!(i.cpu.poll_interrupt().unwrap());
+
+        // Runs of prefixes don't nest, memory full of DD is a long line of NOPs
+        let mut i = Interconnect::new(Memory::new());
+        i.cpu.bus.rom.iter_mut().for_each(|byte| *byte = 0xDD);
+        for _ in 0..0x2_0000 {
+            assert_eq!(i.run_tests().unwrap().cycles, 4);
+        }
+        assert_eq!(i.cpu.reg.pc, 0);
+    }
+
+    #[test]
+    fn test_disassembler() {
+        use crate::disassembler::{disassemble, disassemble_range};
+        let mut m = Memory::new();
+        m.rom[0x0A3A..0x0A4D].copy_from_slice(&[
+            0x20, 0x00, // JR NZ,$0A3C
+            0xDD, 0x36, 0x05, 0xFF, // LD (IX+$05),$FF
+            0xFD, 0xCB, 0xFE, 0x46, // BIT 0,(IY-$02)
+            0x01, 0x34, 0x12, // LD BC,$1234
+            0xED, 0x00, // NOP* (undefined ED)
+            0xDD, 0x00, // NOP* (prefix ignored), NOP
+            0x10, 0xED, // DJNZ $0A3A
+        ]);
+
+        let jr = disassemble(&m, 0x0A3A);
+        assert_eq!(
+            (jr.len, jr.bytes, jr.text.as_str()),
+            (2, vec![0x20, 0x00], "JR NZ,$0A3C")
+        );
+        let listing: Vec<String> = disassemble_range(&m, 0x0A3C, 0x0A4B)
+            .iter()
+            .map(|d| d.text.clone())
+            .collect();
+        assert_eq!(
+            listing,
+            [
+                "LD (IX+$05),$FF",
+                "BIT 0,(IY-$02)",
+                "LD BC,$1234",
+                "NOP*",
+                "NOP*",
+                "NOP",
+                "DJNZ $0A3A"
+            ]
+        );
+        assert_eq!(
+            disassemble(&m, 0x0A3C).to_string(),
+            "0A3C  DD 36 05 FF LD (IX+$05),$FF"
+        );
+    }
+
+    #[test]
+    fn test_assembler() {
+        use crate::disassembler::disassemble;
+        let program = assemble(
+            "
+        org 100h
+count   equ 3
+start:  ld ix,data
+        ld b,count
+        xor a
+loop:   add a,(ix+1)
+        djnz loop
+        ld (ix-1),a
+        halt
+        db 0
+data:   db 0, 5
+",
+        )
+        .unwrap();
+        assert_eq!(program.symbols["loop"], 0x107);
+
+        let mut i = Interconnect::new(Memory::new());
+        let origin = program.origin as usize;
+        i.cpu.bus.rom[origin..origin + program.bytes.len()].copy_from_slice(&program.bytes);
+        i.cpu.reg.pc = 0x100;
+        while !i.run_tests().unwrap().halted {}
+        assert_eq!((i.cpu.reg.a, i.cpu.bus.rom[0x110]), (15, 15));
+        assert_eq!(disassemble(&i.cpu.bus, 0x10A).text, "DJNZ $0107");
+
+        // Every table row round trips through the disassembler (mirrored opcodes by text)
+        let mut m = Memory::new();
+        for prefix in [
+            vec![],
+            vec![0xCB],
+            vec![0xED],
+            vec![0xDD],
+            vec![0xFD],
+            vec![0xFD, 0xCB, 0x80],
+        ] {
+            for op in 0..=0xFF {
+                let code = [&prefix[..], &[op, 0x85, 0x34, 0x12]].concat();
+                m.rom[0x8000..0x8000 + code.len()].copy_from_slice(&code);
+                let line = disassemble(&m, 0x8000);
+                if line.text == "NOP*" {
+                    continue; // Undefined ED opcode or ignored index prefix
+                }
+                let source = format!(" org 8000h\n {}", line.text);
+                let program = assemble(&source).unwrap_or_else(|e| panic!("{} {}", line.text, e));
+                m.rom[0x8000..0x8000 + program.bytes.len()].copy_from_slice(&program.bytes);
+                assert_eq!(disassemble(&m, 0x8000).text, line.text);
+            }
+        }
+
+        let e = assemble(" nop\n jr faraway\n").unwrap_err();
+        assert_eq!(e.to_string(), "Line 2: Undefined symbol: faraway");
+    }
+
+    #[test]
+    fn test_assemble_exercisers() {
+        // The shipped binaries were built from the bundled sources. zexall.com is padded
+        // to a whole number of 128 byte CP/M records.
+        for name in ["prelim", "zexdoc", "zexall"].iter() {
+            let source = std::fs::read_to_string(format!("tests/{}.z80", name)).unwrap();
+            let program = assemble(&source).unwrap_or_else(|e| panic!("{}.z80 {}", name, e));
+            let com = std::fs::read(format!("tests/{}.com", name)).unwrap();
+            let len = program.bytes.len();
+            assert_eq!(program.origin, 0x0100);
+            assert!(
+                len <= com.len() && com.len() - len < 128,
+                "{} {}",
+                name,
+                len
+            );
+            assert!(program.bytes == com[..len], "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_step_errors() {
+        // Failures are reported through CpuError & leave the CPU state untouched
+        let mut i = Interconnect::new(Memory::new());
+        i.cpu.bus.rom[0x0000] = 0x3C; // INC A
+        let step = i.run_tests().unwrap();
+        assert_eq!(
+            step,
+            StepInfo {
+                pc: 0,
+                opcode: 0x3C,
+                cycles: 4,
+                halted: false
+            }
+        );
+
+        // Every opcode of every prefix group steps without a panic or error
+        for model in [CpuModel::Z80, CpuModel::I8080] {
+            for prefix in [
+                vec![],
+                vec![0xCB],
+                vec![0xED],
+                vec![0xDD],
+                vec![0xFD],
+                vec![0xDD, 0xCB, 0x05],
+                vec![0xFD, 0xCB, 0xFB],
+            ] {
+                for op in 0..=0xFF {
+                    let mut i = Interconnect::new(Memory::new());
+                    i.cpu.model = model;
+                    let code = [&prefix[..], &[op, 0x34, 0x12]].concat();
+                    i.cpu.bus.rom[0x8000..0x8000 + code.len()].copy_from_slice(&code);
+                    i.cpu.reg.pc = 0x8000;
+                    i.cpu.reg.sp = 0x9000;
+                    let step = i.run_tests();
+                    assert!(step.is_ok(), "{:02X?} {:?}", code, step);
+                }
+            }
+        }
+
+        // CALL 0000h placed on the data bus in IM0 would need its operands from the bus. The
+        // machine loop stops with the error, the device never sees an acknowledge.
+        let program = assemble(
+            "       im 0
+                    ei
+             loop:  jr loop",
+        )
+        .unwrap();
+        let mut i = Interconnect::new(IntBus {
+            memory: Memory::new(),
+            data: 0xCD,
+            acks: 0,
+        });
+        i.cpu.bus.memory.rom[..program.bytes.len()].copy_from_slice(&program.bytes);
+        i.cpu.generate_interrupt();
+        let err = i.execute_cpu().unwrap_err();
+        assert!(matches!(err, CpuError::Unimplemented { .. }));
+        assert_eq!(
+            err.to_string(),
+            "Unimplemented: IM 0 bus instruction CD CALL **"
+        );
+        assert_eq!((i.cpu.bus.acks, i.cpu.int.irq), (0, true));
+        assert_eq!(i.cpu.reg.pc as i32, program.symbols["loop"]);
+
+        // RST 08h is fine
+        i.cpu.bus.data = 0xCF;
+        i.cpu.reg.sp = 0x8000;
+        assert!(i.cpu.poll_interrupt().unwrap());
+        assert_eq!((i.cpu.bus.acks, i.cpu.reg.pc), (1, 0x08));
+    }
+
+    // Test bus putting a fixed byte on the data bus during interrupt acknowledge
+    struct IntBus {
+        memory: Memory,
+        data: u8,
+        acks: usize,
+    }
+
+    impl Bus for IntBus {
+        fn read8(&self, addr: u16) -> u8 {
+            self.memory.rom[addr as usize]
+        }
+        fn write8(&mut self, addr: u16, byte: u8) {
+            self.memory.rom[addr as usize] = byte;
+        }
+        fn int_data(&self) -> u8 {
+            self.data
+        }
+        fn int_ack(&mut self) -> u8 {
+            self.acks += 1;
+            self.data
+        }
+    }
+
+    #[test]
+    fn test_cpm_console() {
+        // BDOS 10 reads a line from the scripted input, BDOS 1 a single character
+        let program = assemble(
+            "
+        org 100h
+        ld de,buffer
+        ld c,10
+        call 5
+        ld c,1
+        call 5
+        ld (char),a
+        ld hl,buffer+1
+        ld e,(hl)
+        ld d,0
+        add hl,de
+        inc hl
+        ld (hl),'$'
+        ld de,buffer+2
+        ld c,9
+        call 5
+        jp 0
+char:   db 0
+buffer: db 16,0
+",
+        )
+        .unwrap();
+        let mut cpm = Cpm::new();
+        cpm.input("hello\nx");
+        cpm.load_com(&program.bytes);
+        cpm.run().unwrap();
+        assert_eq!(cpm.output(), "hello\r\nxhello");
+        assert_eq!(cpm.cpu.bus.memory[program.symbols["char"] as usize], b'x');
+
+        // No interrupt ever ends EI; HALT, run returns with the CPU halted
+        let mut cpm = Cpm::new();
+        cpm.load_com(&[0xFB, 0x76]);
+        cpm.run().unwrap();
+        assert!(cpm.cpu.halted && cpm.cpu.int.iff1);
+
+        // BDOS 9 on memory without a '$' fails instead of printing forever
+        let mut cpm = Cpm::new();
+        cpm.cpu.bus.memory.iter_mut().for_each(|b| *b = b'A');
+        cpm.load_com(&[0x0E, 0x09, 0x11, 0x00, 0x00, 0xCD, 0x05, 0x00]); // LD C,9 LD DE,0 CALL 5
+        let err = cpm.run().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "BDOS function 9: no '$' after the string at 0000"
+        );
+        assert_eq!(cpm.output(), "");
+    }
+
+    #[test]
+    fn test_cpm_system() {
+        // COPY SRC DST through the BDOS file functions, from a host directory to a disk image
+        let copy = assemble(
+            "
+        org 100h
+        ld hl,6ch
+        ld de,dst
+        ld bc,16
+        ldir
+        xor a
+        ld (5ch+32),a
+        ld (dst+32),a
+        ld de,5ch
+        ld c,15
+        call 5
+        inc a
+        jr z,fail
+        ld de,dst
+        ld c,22
+        call 5
+        inc a
+        jr z,fail
+loop:   ld de,5ch
+        ld c,20
+        call 5
+        or a
+        jr nz,done
+        ld de,dst
+        ld c,21
+        call 5
+        or a
+        jr nz,fail
+        jr loop
+done:   ld de,dst
+        ld c,16
+        call 5
+        ret
+fail:   ld de,msg
+        ld c,9
+        call 5
+        ret
+msg:    db 'COPY FAILED$'
+dst:    ds 36
+",
+        )
+        .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("cpm-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("copy.com"), &copy.bytes).unwrap();
+        // Longer than an extent, with a partial last record
+        let text: Vec<u8> = (0..700)
+            .flat_map(|i| format!("Line {:04}\r\n", i).into_bytes())
+            .collect();
+        std::fs::write(dir.join("LINES.TXT"), &text).unwrap();
+
+        let mut cpm = Cpm::system();
+        cpm.attach(0, Box::new(HostDir::new(&dir)));
+        cpm.attach(1, Box::new(DiskImage::blank()));
+        cpm.input(
+            "dir\ncopy lines.txt b:lines.txt\nb:\nren copy.txt=lines.txt\ndir\ntype copy.txt\n",
+        );
+        cpm.input("era *.*\ny\ndir\nfoo\n");
+        cpm.session().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let output = cpm.output();
+        assert!(output.contains("A>dir\r\n\r\nA: COPY     COM : LINES    TXT\r\n"));
+        assert!(!output.contains("COPY FAILED"));
+        assert!(output.contains("B>dir\r\n\r\nB: COPY     TXT\r\n"));
+        let typed = format!(
+            "B>type copy.txt\r\n\r\n{}\r\nB>",
+            String::from_utf8(text).unwrap()
+        );
+        assert!(output.contains(&typed));
+        assert!(output.contains("ALL (Y/N)?y\r\n\r\nB>dir\r\n\r\nNO FILE\r\n"));
+        assert!(output.ends_with("B>foo\r\n\r\nFOO?\r\nB>"));
+
+        // The copy went through the disk image directory & allocation blocks
+        let mut image = DiskImage::blank();
+        assert!(image.create("COPY.COM"));
+        for (record, chunk) in copy.bytes.chunks(128).enumerate() {
+            let mut buffer = [0; 128];
+            buffer[..chunk.len()].copy_from_slice(chunk);
+            assert!(image.write("COPY.COM", record, &buffer));
+        }
+        assert_eq!(image.files(), vec!["COPY.COM"]);
+        assert_eq!(image.records("COPY.COM"), Some(2));
+        // First directory sector: track 2, sector 1
+        let entry = image.read_sector(2, 1).unwrap();
+        assert_eq!(&entry[..16], b"\0COPY    COM\0\0\0\x02");
+        assert_eq!(entry[16], 2);
+    }
+
+    #[test]
+    fn test_trace_diff() {
+        use crate::trace::{compare, open, TraceWriter, REGISTERS};
+        use std::fmt::Write;
+        let dir = std::env::temp_dir().join(format!("trace-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ours = dir.join("prelim.bin");
+        let mut cpm = Cpm::new();
+        cpm.load_com(&std::fs::read("tests/prelim.com").unwrap());
+        cpm.cpu.trace = Some(TraceWriter::create(&ours).unwrap());
+        cpm.run().unwrap();
+        let steps = cpm.cpu.trace.take().unwrap().records();
+        assert_eq!(steps, 899);
+        assert!(compare(open(&ours).unwrap(), open(&ours).unwrap(), 3)
+            .unwrap()
+            .is_none());
+
+        // MAME style text trace of the same run, starting at another T state count, with
+        // a collapsed loop & HL corrupted at step 500
+        let mut text = String::from("; trace header\n");
+        for (i, entry) in open(&ours).unwrap().enumerate() {
+            let record = match entry.unwrap() {
+                crate::trace::Entry::Step(record) => record,
+                _ => unreachable!(),
+            };
+            match i {
+                21 => {
+                    text.push_str("   (loops for 5 instructions)\n");
+                    continue;
+                }
+                22..=25 => continue,
+                _ => {}
+            }
+            for (j, (name, reg)) in REGISTERS.iter().zip(record.regs.iter()).enumerate() {
+                let value = if i == 500 && j == 3 {
+                    0x1234
+                } else {
+                    reg.unwrap()
+                };
+                write!(text, "{}={:04x} ", name, value).unwrap();
+            }
+            let cycles = record.cycles.unwrap() + 1000;
+            writeln!(text, "CYC={} {:04X}: nop", cycles, record.pc).unwrap();
+        }
+        let theirs = dir.join("prelim.tr");
+        std::fs::write(&theirs, text).unwrap();
+
+        let divergence = compare(open(&ours).unwrap(), open(&theirs).unwrap(), 3)
+            .unwrap()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(divergence.step, 500);
+        assert_eq!(divergence.context.len(), 3);
+        assert_eq!(divergence.differences.len(), 1);
+        assert!(divergence.differences[0].starts_with("HL "));
+        assert!(divergence.to_string().contains("HL=1234"));
+    }
+
+    #[test]
+    fn test_debugger() {
+        let program = assemble(
+            "       ld sp, 8000h
+             start: call sub
+                    ld (4000h), a
+                    jr start
+             sub:   inc a
+                    call sub2
+                    ret
+             sub2:  out (10h), a
+                    ret",
+        )
+        .unwrap();
+        let mut i = Interconnect::new(Memory::new());
+        i.cpu.bus.rom[..program.bytes.len()].copy_from_slice(&program.bytes);
+        i.debugger.enabled = true;
+        // Runs a command & the machine until the debugger stops it again
+        fn debug(i: &mut Interconnect<Memory>, line: &str) -> String {
+            let mut out = i.debugger.command(&mut i.cpu, line);
+            i.execute_cpu().unwrap();
+            if let Some(stop) = i.debugger.take_stop() {
+                out += &i.debugger.report(&i.cpu, &stop);
+            }
+            out
+        }
+
+        assert!(debug(&mut i, "b 3").starts_with("Breakpoint 0\nBreakpoint 0 at 0003, hit 1\n"));
+        assert!(debug(&mut i, "s").ends_with("000B  3C          INC A\n"));
+        assert!(debug(&mut i, "bt").contains("#1  0006  call 000B from 0003\n"));
+        debug(&mut i, "n");
+        assert!(debug(&mut i, "n").ends_with("000F  C9          RET\n"));
+        assert!(debug(&mut i, "finish").ends_with("0006  32 00 40    LD ($4000),A\n"));
+        let out = debug(&mut i, "w w 4000-40FF");
+        assert!(out.starts_with("Watchpoint 0\n"), "{}", out);
+        let out = debug(&mut i, "c");
+        assert!(out.starts_with("Watchpoint 0: Write 4000 = 01 by the instruction at 0006\n"));
+
+        // Hit counts & conditions, counted from here on
+        debug(&mut i, "dw 0");
+        debug(&mut i, "d 0");
+        debug(&mut i, "b 10 hits 3");
+        assert!(debug(&mut i, "c").contains("Breakpoint 0 at 0010, hit 3"));
+        assert_eq!(i.cpu.reg.a, 4);
+        assert_eq!(i.debugger.frames.len(), 2);
+        assert!(debug(&mut i, "up 2").contains("0006  32 00 40"));
+        assert!(i.debugger.frames.is_empty());
+        debug(&mut i, "d 0");
+        // Video frames, the stop lands at the end of the frame
+        let frame = i.frame_count;
+        assert!(debug(&mut i, "frame").starts_with(&format!("Frame {} done\n", frame + 1)));
+        assert_eq!((i.frame_count, i.frame_cycle < 32), (frame + 1, true));
+        i.debugger
+            .command(&mut i.cpu, &format!("frame {}", frame + 3));
+        while !i.debugger.is_stopped() {
+            i.execute_cpu().unwrap();
+        }
+        assert_eq!(i.frame_count, frame + 3);
+        i.debugger.take_stop();
+        assert!(debug(&mut i, "frame 1").starts_with("Frame 1 is already done"));
+        debug(&mut i, "b 3 if A==6 && (4000)==6");
+        assert!(debug(&mut i, "c").contains("Breakpoint 0 at 0003"));
+        assert_eq!(i.cpu.reg.a, 6);
+        debug(&mut i, "w io 10");
+        assert!(
+            debug(&mut i, "c").starts_with("Watchpoint 0: Io 0010 = 07 by the instruction at 0010")
+        );
+        assert!(debug(&mut i, "info").contains("Breakpoint 0 at 0003 if A==6 && (4000)==6, 1 hits"));
+
+        // Editing
+        assert!(debug(&mut i, "set hl 1234").contains("HL=1234"));
+        assert_eq!(debug(&mut i, "poke 5000 AA 0BBh"), "5000  AA BB\n");
+        assert!(debug(&mut i, "b zz").starts_with("Bad address zz"));
+        assert!(i.cpu.breakpoint);
+    }
+
+    #[test]
+    fn test_gdb_stub() {
+        use crate::gdb::GdbStub;
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+        let program = assemble(
+            "       org 100h
+                    ld sp, 8000h
+                    ld b, 3
+             loop:  call sub
+                    ld (4000h), a
+                    djnz loop
+                    jp 0
+             sub:   inc a
+                    ret",
+        )
+        .unwrap();
+        let mut cpm = Cpm::new();
+        cpm.load_com(&program.bytes);
+        let stub = GdbStub::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(stub.local_addr().unwrap()).unwrap();
+        cpm.gdb = Some(stub);
+
+        // Scripted GDB session, the machine runs on this thread
+        let session = std::thread::spawn(move || {
+            client
+                .set_read_timeout(Some(std::time::Duration::from_secs(10)))
+                .unwrap();
+            let mut request = |packet: &str| -> String {
+                let sum = packet.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+                write!(client, "${}#{:02x}", packet, sum).unwrap();
+                let mut reply = Vec::new();
+                let mut byte = [0];
+                while !(reply.len() > 3 && reply[reply.len() - 3] == b'#') {
+                    client.read_exact(&mut byte).unwrap();
+                    if !(reply.is_empty() && byte[0] == b'+') {
+                        reply.push(byte[0]);
                     }
                 }
-                if i.cpu.reg.c == 2 {
-                    print!("{}", i.cpu.reg.e as char);
+                client.write_all(b"+").unwrap();
+                String::from_utf8(reply[1..reply.len() - 3].to_vec()).unwrap()
+            };
+            assert_eq!(request("?"), "S05");
+            assert_eq!(request("Z0,110,1"), "OK");
+            assert_eq!(request("c"), "S05");
+            let regs = request("g");
+            assert_eq!(regs.len(), 13 * 4);
+            assert_eq!(&regs[16..24], "fe7f1001"); // SP & PC
+            assert_eq!(request("m7ffe,2"), "0801");
+            assert_eq!(request("s"), "S05");
+            assert_eq!(&request("g")[20..24], "1101");
+            assert_eq!(request("z0,110,1"), "OK");
+            assert_eq!(request("Z2,4000,1"), "OK");
+            assert_eq!(request("c"), "T05watch:4000;");
+            assert_eq!(request("M5000,2:aabb"), "OK");
+            assert_eq!(request("m5000,2"), "aabb");
+            // Malformed packets get an error instead of leaving GDB waiting
+            for packet in ["M5000,2", "cxyz", "sxyz", "Z0", "z0,xyz,1"] {
+                assert_eq!(request(packet), "E01");
+            }
+            let regs = request("g");
+            assert_eq!(
+                request(&format!("G{}3412{}", &regs[..12], &regs[16..])),
+                "OK"
+            );
+            assert_eq!(request("z2,4000,1"), "OK");
+            assert_eq!(request("D"), "OK");
+        });
+        cpm.run().unwrap();
+        session.join().unwrap();
+        assert_eq!(cpm.cpu.get_pair(HL), 0x1234);
+        assert_eq!(cpm.cpu.reg.a, 2); // From FF after reset, the program ran to completion
+        assert!(!cpm.debugger.is_stopped());
+    }
+
+    #[test]
+    fn test_dap_server() {
+        use crate::dap::DapServer;
+        use serde_json::{json, Value};
+        use std::collections::VecDeque;
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::{TcpListener, TcpStream};
+
+        // DAP client, events arriving before a response are kept for `event`
+        struct Client {
+            input: BufReader<TcpStream>,
+            output: TcpStream,
+            seq: u64,
+            events: VecDeque<Value>,
+        }
+        impl Client {
+            fn receive(&mut self) -> Value {
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    self.input.read_line(&mut line).unwrap();
+                    match line.trim_end().strip_prefix("Content-Length: ") {
+                        Some(value) => length = value.parse().unwrap(),
+                        None if line.trim_end().is_empty() => break,
+                        None => {}
+                    }
+                }
+                let mut body = vec![0; length];
+                self.input.read_exact(&mut body).unwrap();
+                serde_json::from_slice(&body).unwrap()
+            }
+            fn request(&mut self, command: &str, arguments: Value) -> Value {
+                self.seq += 1;
+                let message = json!({"seq": self.seq, "type": "request",
+                    "command": command, "arguments": arguments})
+                .to_string();
+                write!(
+                    self.output,
+                    "Content-Length: {}\r\n\r\n{}",
+                    message.len(),
+                    message
+                )
+                .unwrap();
+                loop {
+                    let message = self.receive();
+                    if message["type"] == "event" {
+                        self.events.push_back(message);
+                    } else if message["request_seq"] == self.seq {
+                        assert_eq!(message["success"], true, "{}", message);
+                        return message["body"].clone();
+                    }
                 }
             }
-            if i.cpu.opcode == 0xD3 {
-                break;
-            } else if i.cpu.reg.pc == 0 {
-                {
-                    println!(
-                        "\nBDOS routine called, jumped to: 0 from {:04X}",
-                        i.cpu.reg.prev_pc
-                    );
-                    reset_counter += 1;
+            fn event(&mut self, event: &str) -> Value {
+                loop {
+                    let message = match self.events.pop_front() {
+                        Some(message) => message,
+                        None => self.receive(),
+                    };
+                    if message["event"] == event {
+                        return message["body"].clone();
+                    }
                 }
             }
-            if reset_counter > 1 {
-                break;
+            // Stop reason & the addresses of the stack frames, e.g. "step 0111 0108"
+            fn stopped(&mut self) -> String {
+                let mut out = self.event("stopped")["reason"]
+                    .as_str()
+                    .unwrap()
+                    .to_string();
+                let trace = self.request("stackTrace", json!({"threadId": 1}));
+                for frame in trace["stackFrames"].as_array().unwrap() {
+                    let addr = frame["instructionPointerReference"].as_str().unwrap();
+                    out += &format!(" {}", addr.trim_start_matches("0x"));
+                }
+                out
             }
         }
-        println!("Cycles executed: {}\n", i.cpu.cycles);
 
-        i.cpu.cycles
+        let program = assemble(
+            "       org 100h
+                    ld sp, 8000h
+                    ld b, 3
+             loop:  call sub
+                    ld (4000h), a
+                    djnz loop
+                    jp 0
+             sub:   inc a
+                    ret",
+        )
+        .unwrap();
+        let mut cpm = Cpm::new();
+        cpm.load_com(&program.bytes);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        cpm.dap = Some(DapServer::new(server.try_clone().unwrap(), server));
+
+        // Scripted editor session, the machine runs on this thread
+        let session = std::thread::spawn(move || {
+            client
+                .set_read_timeout(Some(std::time::Duration::from_secs(10)))
+                .unwrap();
+            let mut client = Client {
+                input: BufReader::new(client.try_clone().unwrap()),
+                output: client,
+                seq: 0,
+                events: VecDeque::new(),
+            };
+            let capabilities = client.request("initialize", json!({"adapterID": "z80"}));
+            assert_eq!(capabilities["supportsDisassembleRequest"], true);
+            client.event("initialized");
+            client.request("launch", json!({"stopOnEntry": true}));
+            let breakpoints = json!({"breakpoints": [{"instructionReference": "0x0110"}]});
+            let set = client.request("setInstructionBreakpoints", breakpoints);
+            assert_eq!(set["breakpoints"][0]["verified"], true);
+            client.request("configurationDone", json!({}));
+            assert_eq!(client.stopped(), "entry 0100");
+
+            client.request("continue", json!({"threadId": 1}));
+            // In sub, called from 105
+            assert_eq!(client.stopped(), "instruction breakpoint 0110 0108");
+            let scopes = client.request("scopes", json!({"frameId": 0}));
+            let reference = scopes["scopes"][0]["variablesReference"].clone();
+            let registers = client.request("variables", json!({"variablesReference": reference}));
+            let mut variables = registers["variables"].as_array().unwrap().iter();
+            let sp = variables.find(|v| v["name"] == "SP").unwrap();
+            assert_eq!(sp["value"], "0x7FFE");
+            let memory = json!({"memoryReference": "0x7FFE", "count": 2});
+            assert_eq!(client.request("readMemory", memory)["data"], "CAE="); // 08 01
+            let listing = json!({"memoryReference": "0x0110", "instructionOffset": -1,
+                "instructionCount": 3});
+            let listing = client.request("disassemble", listing)["instructions"].clone();
+            assert_eq!(listing[0]["address"], "0x010D");
+            assert_eq!(listing[1]["instruction"], "INC A");
+            assert_eq!(listing[2]["address"], "0x0111");
+
+            client.request("setInstructionBreakpoints", json!({"breakpoints": []}));
+            client.request("next", json!({"threadId": 1}));
+            assert_eq!(client.stopped(), "step 0111 0108");
+            client.request("stepOut", json!({"threadId": 1}));
+            assert_eq!(client.stopped(), "step 0108");
+            let hl = json!({"variablesReference": reference, "name": "HL", "value": "0x1234"});
+            assert_eq!(client.request("setVariable", hl)["value"], "0x1234");
+            let a = client.request("evaluate", json!({"expression": "A", "context": "hover"}));
+            assert_eq!(a["result"], "0x00");
+            client.request("disconnect", json!({}));
+        });
+        cpm.run().unwrap();
+        session.join().unwrap();
+        assert_eq!(cpm.cpu.get_pair(HL), 0x1234);
+        assert_eq!(cpm.cpu.reg.a, 2);
+        assert!(cpm.dap.is_none() && !cpm.debugger.is_stopped());
+    }
+
+    #[test]
+    fn test_save_state() {
+        use crate::error::StateError;
+        use crate::pacman_arcade::pacman::Board;
+        use crate::savestate::{decode, encode, VERSION};
+
+        let mut machine = Interconnect::new(Board::new());
+        let cpu = &mut machine.cpu;
+        cpu.reset();
+        cpu.write_pair_direct(HL, 0x4C10);
+        cpu.reg.h_ = 0x12;
+        cpu.reg.ix = 0xBEEF;
+        cpu.flags.set_shadow(0x41);
+        cpu.int.mode = 2;
+        cpu.int.iff1 = true;
+        cpu.cycles = 123_456_789;
+        cpu.bus.memory.ram[0x10] = 0x55;
+        cpu.bus.vram[0x7FF] = 0x1F;
+        cpu.bus.sound[0x15] = 0x0A;
+        cpu.bus.sprite_coords[3] = 0x80;
+        cpu.bus.int_vector = 0xCF;
+        cpu.bus.int_enable = true;
+        machine.frame_count = 42;
+        let state = encode(&machine);
+
+        let mut loaded = Interconnect::new(Board::new());
+        decode(&mut loaded, &state).unwrap();
+        assert_eq!(encode(&loaded), state);
+        assert_eq!(loaded.cpu.get_pair(HL), 0x4C10);
+        assert_eq!(loaded.cpu.reg.ix, 0xBEEF);
+        assert_eq!(loaded.cpu.flags.get_shadow(), 0x41);
+        assert_eq!((loaded.cpu.int.mode, loaded.cpu.int.iff1), (2, true));
+        assert_eq!(loaded.cpu.bus.sound[0x15], 0x0A);
+        assert_eq!(loaded.cpu.bus.int_vector, 0xCF);
+        assert_eq!(loaded.frame_count, 42);
+
+        // Bad files are rejected & leave the machine as it was
+        let mut newer = state.clone();
+        newer[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        let error = decode(&mut loaded, &newer).unwrap_err();
+        assert!(matches!(error, StateError::Version { found, expected }
+            if found == VERSION + 1 && expected == VERSION));
+        assert!(error.to_string().contains("incompatible"));
+        let error = decode(&mut loaded, &state[..state.len() - 1]).unwrap_err();
+        assert!(matches!(error, StateError::Corrupt(_)));
+        assert!(matches!(
+            decode(&mut loaded, b"PNG"),
+            Err(StateError::NotAState)
+        ));
+        assert_eq!(encode(&loaded), state);
+    }
+
+    #[test]
+    fn test_pacman_memory_map() {
+        use crate::pacman_arcade::pacman::Board;
+
+        let mut board = Board::new();
+        board.memory.rom[0x1234] = 0x3E;
+        board.write8(0x1234, 0);
+        assert_eq!(board.read8(0x9234), 0x3E); // A15 mirror
+
+        // Video & color RAM are separate, the sprite attributes are the end of work RAM
+        board.write8(0x4001, 0x40);
+        board.write8(0x4401, 0x1F);
+        board.write8(0xCC00, 0x77);
+        board.write8(0x4FF2, 0xFC);
+        assert_eq!((board.read8(0x4001), board.read8(0xC401)), (0x40, 0x1F));
+        assert_eq!(board.read8(0x4C00), 0x77);
+        assert_eq!(board.sprite_attributes()[2], 0xFC);
+        assert_eq!(board.read8(0x4800), 0xBF);
+
+        // Inputs are active low
+        assert_eq!(board.read8(0x5000), 0xFF);
+        board.in0.coin1 = true;
+        board.in1.start1 = true;
+        assert_eq!(board.read8(0x5000), 0xDF);
+        assert_eq!(board.read8(0x507F), 0xDF);
+        assert_eq!(board.read8(0x5080), 0xC9); // 1 coin 1 game, 3 lives, 10000
+        assert_eq!(board.read8(0x50C0), 0xFF);
+
+        board.write8(0x5000, 1);
+        board.write8(0x5003, 1);
+        board.write8(0x503E, 1); // Mirror of 5006
+        assert!(board.int_enable && board.flip_screen && board.c_lockout);
+        board.write8(0x5000, 0xFE);
+        assert!(!board.int_enable);
+
+        board.write8(0x5045, 0xA7);
+        board.write8(0x5062, 0x80);
+        assert_eq!((board.sound[5], board.sprite_coords[2]), (0x07, 0x80));
+        board.watchdog = 10;
+        board.write8(0x50C0, 0);
+        assert_eq!(board.watchdog, 0);
+
+        // Unconnected addresses don't panic
+        board.write8(0x6000, 0x12);
+        assert_eq!(board.read8(0x6000), 0xFF);
+    }
+
+    #[test]
+    fn test_pacman_vblank_interrupt() {
+        // The game's setup: IM 2, the vector through OUT (0),A & the 0x5000 latch. Every
+        // vblank runs the handler which counts frames in work RAM.
+        use crate::pacman_arcade::pacman::Board;
+        let program = assemble(
+            "       org 0
+                    ld sp, 4FC0h
+                    im 2
+                    ld a, 0
+                    ld i, a
+                    ld a, 40h
+                    out (0), a
+                    ld a, 1
+                    ld (5000h), a
+                    ei
+             loop:  jr loop
+
+                    org 40h
+                    dw vblank
+             vblank: push af
+                    xor a
+                    ld (5000h), a
+                    ld hl, 4C00h
+                    inc (hl)
+                    inc a
+                    ld (5000h), a
+                    pop af
+                    ei
+                    reti",
+        )
+        .unwrap();
+        let mut i = Interconnect::new(Board::new());
+        i.cpu.bus.memory.rom[..program.bytes.len()].copy_from_slice(&program.bytes);
+        for _ in 0..10 {
+            i.execute_cpu().unwrap();
+        }
+        // Accepted right away at line 224 of every frame
+        assert_eq!(i.cpu.bus.read8(0x4C00), 10);
+        assert_eq!(i.cpu.bus.int_vector, 0x40);
+
+        // Masked while the latch is clear, the setup writes 0 instead of 1
+        let mut i = Interconnect::new(Board::new());
+        i.cpu.bus.memory.rom[..program.bytes.len()].copy_from_slice(&program.bytes);
+        i.cpu.bus.memory.rom[0x0E] = 0; // LD A, 1
+        for _ in 0..5 {
+            i.execute_cpu().unwrap();
+        }
+        assert_eq!(i.cpu.bus.read8(0x4C00), 0);
+    }
+
+    #[test]
+    fn test_pacman_watchdog() {
+        // Counts its boots in work RAM, which survives the watchdog reset
+        use crate::pacman_arcade::pacman::{Board, WATCHDOG_VBLANKS};
+        let boot = |kick: bool, enabled: bool| {
+            let program = assemble(
+                "       ld hl, 4C00h
+                        inc (hl)
+                        ld a, 1
+                        ld (5000h), a
+                 loop:  ld (50C0h), a
+                        jr loop",
+            )
+            .unwrap();
+            let mut i = Interconnect::new(Board::new());
+            i.cpu.bus.memory.rom[..program.bytes.len()].copy_from_slice(&program.bytes);
+            if !kick {
+                i.cpu.bus.memory.rom[9..12].copy_from_slice(&[0; 3]); // LD (50C0h),A to NOPs
+            }
+            i.cpu.bus.watchdog_enabled = enabled;
+            for _ in 0..WATCHDOG_VBLANKS * 2 + 8 {
+                i.run_frame().unwrap();
+            }
+            (i.cpu.bus.read8(0x4C00), i.cpu.bus.int_enable)
+        };
+        assert_eq!(boot(true, true), (1, true));
+        assert_eq!(boot(false, true), (3, true)); // Reset after 16 & 32 frames
+        assert_eq!(boot(false, false), (1, true));
+    }
+
+    #[test]
+    fn test_pacman_int_latch() {
+        // A vblank interrupt raised while DI is dropped when the game clears 0x5000, EI
+        // later doesn't take it. Leaving the latch set keeps it pending.
+        use crate::pacman_arcade::pacman::Board;
+        let program = assemble(
+            "       ld (5000h), a
+                    ei
+                    nop
+                    nop",
+        )
+        .unwrap();
+        for (latch, taken) in [(0, false), (1, true)] {
+            let mut i = Interconnect::new(Board::new());
+            i.cpu.bus.memory.rom[..program.bytes.len()].copy_from_slice(&program.bytes);
+            i.cpu.reg.sp = 0x5000;
+            i.cpu.reg.a = latch;
+            i.cpu.int.mode = 1;
+            i.cpu.bus.write8(0x5000, 1);
+            i.cpu.generate_interrupt();
+            let mut accepted = false;
+            for _ in 0..4 {
+                i.run_tests().unwrap();
+                accepted |= i.cpu.poll_interrupt().unwrap();
+            }
+            assert_eq!((accepted, i.cpu.int.irq), (taken, false));
+        }
+    }
+
+    #[test]
+    fn test_frame_scheduler() {
+        // LD A,3Eh over & over (7 T states) never lines up with the frame, counts the events
+        use crate::interconnect::{FRAME_CYCLES, SAMPLE_CYCLES};
+        #[derive(Default)]
+        struct Clock {
+            ticks: usize,
+            vblanks: usize,
+            ends: usize,
+        }
+        impl Bus for Clock {
+            fn read8(&self, _addr: u16) -> u8 {
+                0x3E
+            }
+            fn write8(&mut self, _addr: u16, _byte: u8) {}
+            fn vblank(&mut self) -> bool {
+                self.vblanks += 1;
+                false
+            }
+            fn vblank_end(&mut self) {
+                self.ends += 1;
+                assert_eq!(self.ends, self.vblanks);
+            }
+            fn sound_tick(&mut self) {
+                self.ticks += 1;
+            }
+        }
+        let mut i = Interconnect::new(Clock::default());
+        for frame in 1..=10 {
+            assert_eq!(i.execute_cpu().unwrap(), frame);
+            // The overshoot is carried, no T state gets lost
+            assert!(i.frame_cycle < 7);
+            assert_eq!(i.cpu.cycles, frame as usize * FRAME_CYCLES + i.frame_cycle);
+            assert_eq!((i.scanline(), i.in_vblank()), (0, false));
+        }
+        assert_eq!((i.cpu.bus.vblanks, i.cpu.bus.ends), (10, 10));
+        assert_eq!(i.cpu.bus.ticks, 10 * FRAME_CYCLES / SAMPLE_CYCLES);
+    }
+
+    #[test]
+    fn test_pacman_tilemap() {
+        use crate::pacman_arcade::display::{tile_offset, COLUMNS, ROWS};
+        // Corners of the top rows, the playfield & the bottom rows
+        assert_eq!((tile_offset(0, 0), tile_offset(27, 0)), (0x3DD, 0x3C2));
+        assert_eq!((tile_offset(0, 1), tile_offset(27, 1)), (0x3FD, 0x3E2));
+        assert_eq!((tile_offset(0, 2), tile_offset(27, 2)), (0x3A0, 0x040));
+        assert_eq!((tile_offset(0, 33), tile_offset(27, 33)), (0x3BF, 0x05F));
+        assert_eq!((tile_offset(0, 34), tile_offset(27, 35)), (0x01D, 0x022));
+        // Every tile on screen has its own byte
+        let mut seen = vec![false; 0x400];
+        for row in 0..ROWS {
+            for col in 0..COLUMNS {
+                assert!(!std::mem::replace(&mut seen[tile_offset(col, row)], true));
+            }
+        }
+        assert_eq!(seen.iter().filter(|&&s| s).count(), 36 * 28);
+    }
+
+    #[test]
+    fn test_single_step() {
+        use crate::single_step::run_vectors;
+        use std::path::Path;
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        run_vectors(&root.join("tests/singlestep"), "vendored");
+        // The full upstream set is too big to vendor, point SINGLESTEP_DIR at a checkout
+        if let Some(dir) = std::env::var_os("SINGLESTEP_DIR") {
+            run_vectors(Path::new(&dir), "external");
+        }
+    }
+
+    #[test]
+    fn fast_8080() {
+        let tst8080 = exec_model_test("tests/TST8080.COM", CpuModel::I8080);
+        assert_eq!(tst8080.cpu.cycles, 4924);
+        assert!(tst8080.output().ends_with("CPU IS OPERATIONAL"));
+        let pre8080 = exec_model_test("tests/8080PRE.COM", CpuModel::I8080);
+        assert_eq!(pre8080.cpu.cycles, 7817);
+        assert_eq!(pre8080.output(), "8080 Preliminary tests complete");
+        let cputest = exec_model_test("tests/CPUTEST.COM", CpuModel::I8080);
+        assert_eq!(cputest.cpu.cycles, 255653383);
+        assert!(cputest.output().contains("CPU IS 8080/8085\r\n"));
+        assert!(cputest.output().ends_with("CPU TESTS OK\r\n"));
+    }
+
+    #[test]
+    fn ex8080() {
+        // 8080EXM & 8080EX1 check against CRCs taken on a real 8080 & KR580VM80A
+        let exm = exec_model_test("tests/8080EXM.COM", CpuModel::I8080);
+        assert_eq!(exm.cpu.cycles, 23803381171);
+        assert!(exm.output().ends_with("Tests complete"));
+        let ex1 = exec_model_test("tests/8080EX1.COM", CpuModel::I8080);
+        assert!(ex1.output().ends_with("Tests complete"));
+
+        // 8080EXER has no CRC table (all expected values are 0), it has to find the CRCs
+        // 8080EXM verified
+        let mut exer = Cpm::new();
+        exer.cpu.model = CpuModel::I8080;
+        exer.cpu.reset();
+        exer.load_com(&std::fs::read("tests/8080EXER.COM").unwrap());
+        exer.run().unwrap();
+        let crcs = |output: &str| -> Vec<String> {
+            output
+                .lines()
+                .filter(|line| line.contains("crc"))
+                .filter_map(|line| line.rsplit(':').next().map(str::to_string))
+                .collect()
+        };
+        assert_eq!(crcs(exer.output()).len(), 25);
+        assert_eq!(crcs(exer.output()), crcs(exm.output()));
+    }
+
+    #[test]
+    fn fast_z80() {
+        // Assert the tests executed CPU cycle amount vs real hardware cycles
+        let prelim = exec_test("tests/prelim.com");
+        assert_eq!(prelim.cpu.cycles, 8721);
+        assert_eq!(prelim.output(), "Preliminary tests complete");
+        let pre8080 = exec_test("tests/8080PRE.COM");
+        assert_eq!(pre8080.cpu.cycles, 7772);
+        assert_eq!(pre8080.output(), "8080 Preliminary tests complete");
+        let cputest = exec_test("tests/CPUTEST.COM");
+        assert_eq!(cputest.cpu.cycles, 240551424);
+        assert!(cputest.output().contains("CPU IS Z80\r\n"));
+        assert!(cputest.output().ends_with("CPU TESTS OK\r\n"));
+    }
+
+    #[test]
+    fn zexdoc() {
+        let zexdoc = exec_test("tests/zexdoc.com");
+        assert_eq!(zexdoc.cpu.cycles, 46734978649);
+        assert!(zexdoc.output().ends_with("Tests complete"));
+    }
+
+    #[test]
+    fn zexall() {
+        // Also verifies the undocumented X & Y flags (including MEMPTR leaking through BIT n, (HL))
+        let zexall = exec_test("tests/zexall.com");
+        assert_eq!(zexall.cpu.cycles, 46734978649);
+        assert!(zexall.output().ends_with("Tests complete"));
+    }
+
+    fn exec_test(bin: &str) -> Cpm {
+        exec_model_test(bin, CpuModel::Z80)
+    }
+
+    fn exec_model_test(bin: &str, model: CpuModel) -> Cpm {
+        let mut cpm = Cpm::new();
+        cpm.cpu.model = model;
+        cpm.cpu.reset();
+        cpm.load_com(&std::fs::read(bin).unwrap_or_else(|_| panic!("Couldn't load {}", bin)));
+
+        if let Err(e) = cpm.run() {
+            panic!("{}\n{:?}", e, cpm.cpu);
+        }
+        println!("{}", cpm.output());
+        println!("Cycles executed: {}\n", cpm.cpu.cycles);
+        assert!(!cpm.output().contains("ERROR"), "{}", cpm.output());
+        cpm
     }
 }
@@ -0,0 +1,416 @@
+// Execution traces for diffing runs against other emulators.
+//
+// `TraceWriter` stores one fixed size record per executed instruction, `Cpu::trace` hooks
+// it into `Cpu::step`. The binary format is little endian:
+//   header  "Z80T", u16 version
+//   record  PC, number of opcode bytes & 4 opcode bytes, AF BC DE HL IX IY SP & the T state
+//           count before the instruction (29 bytes)
+//
+// `TextTrace` reads MAME style text traces, e.g. from
+//   trace z80.tr,maincpu,,{tracelog "AF=%04X BC=%04X DE=%04X HL=%04X IX=%04X IY=%04X SP=%04X ",af,bc,de,hl,ix,iy,sp}
+// Any line with a "XXXX:" address is an instruction, KEY=hex fields fill in the registers
+// & CYC=n the T state count, "(loops for N instructions)" marks instructions MAME left out.
+//
+// `compare` streams two traces & stops at the first divergence. Only the current records &
+// the context window are kept, memory use doesn't grow with the length of the traces.
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::disassembler::disassemble;
+use crate::instruction_info::Register::{AF, BC, DE, HL, IX, IY, SP};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+pub const MAGIC: &[u8; 4] = b"Z80T";
+pub const VERSION: u16 = 1;
+const RECORD_SIZE: usize = 29;
+
+// Register order of `Record::regs`
+pub const REGISTERS: [&str; 7] = ["AF", "BC", "DE", "HL", "IX", "IY", "SP"];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+    pub pc: u16,
+    pub len: u8, // Opcode bytes in `bytes`, 0 if the trace doesn't have them
+    pub bytes: [u8; 4],
+    pub regs: [Option<u16>; 7], // Text traces may leave registers out
+    pub cycles: Option<u64>,
+}
+
+impl Record {
+    // State before the instruction at PC is executed
+    pub fn capture<B: Bus>(cpu: &Cpu<B>) -> Self {
+        let pc = cpu.reg.pc;
+        let instruction = disassemble(&cpu.bus, pc);
+        let len = instruction.bytes.len().min(4);
+        let mut bytes = [0; 4];
+        bytes[..len].copy_from_slice(&instruction.bytes[..len]);
+        let mut regs = [None; 7];
+        for (reg, pair) in regs.iter_mut().zip([AF, BC, DE, HL, IX, IY, SP]) {
+            *reg = Some(cpu.get_pair(pair));
+        }
+        Record {
+            pc,
+            len: len as u8,
+            bytes,
+            regs,
+            cycles: Some(cpu.cycles as u64),
+        }
+    }
+
+    fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut out = [0; RECORD_SIZE];
+        out[0..2].copy_from_slice(&self.pc.to_le_bytes());
+        out[2] = self.len;
+        out[3..7].copy_from_slice(&self.bytes);
+        for (i, reg) in self.regs.iter().enumerate() {
+            let pos = 7 + i * 2;
+            out[pos..pos + 2].copy_from_slice(&reg.unwrap_or(0).to_le_bytes());
+        }
+        out[21..29].copy_from_slice(&self.cycles.unwrap_or(0).to_le_bytes());
+        out
+    }
+
+    fn decode(data: &[u8; RECORD_SIZE]) -> Self {
+        let word = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]);
+        let mut regs = [None; 7];
+        for (i, reg) in regs.iter_mut().enumerate() {
+            *reg = Some(word(7 + i * 2));
+        }
+        let mut cycles = [0; 8];
+        cycles.copy_from_slice(&data[21..29]);
+        Record {
+            pc: word(0),
+            len: data[2].min(4),
+            bytes: [data[3], data[4], data[5], data[6]],
+            regs,
+            cycles: Some(u64::from_le_bytes(cycles)),
+        }
+    }
+}
+
+// "0100  31 00 F0     AF=0044 BC=0000 ... CYC=17", "----" for registers left out
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes[..self.len as usize]
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        write!(f, "{:04X}  {:<12}", self.pc, bytes.join(" "))?;
+        for (name, reg) in REGISTERS.iter().zip(self.regs.iter()) {
+            match reg {
+                Some(value) => write!(f, " {}={:04X}", name, value)?,
+                None => write!(f, " {}=----", name)?,
+            }
+        }
+        if let Some(cycles) = self.cycles {
+            write!(f, " CYC={}", cycles)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry {
+    Step(Record),
+    Skip(u64), // Instructions executed but not traced (MAME loop detection)
+}
+
+pub struct TraceWriter<W: Write> {
+    out: BufWriter<W>,
+    records: u64,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(out: W) -> io::Result<Self> {
+        let mut out = BufWriter::new(out);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        Ok(TraceWriter { out, records: 0 })
+    }
+
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        self.records += 1;
+        self.out.write_all(&record.encode())
+    }
+
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl TraceWriter<Box<dyn Write>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(Box::new(File::create(path)?))
+    }
+}
+
+// Binary traces written by `TraceWriter`
+pub struct TraceReader<R: Read> {
+    input: BufReader<R>,
+}
+
+impl<R: Read> TraceReader<R> {
+    pub fn new(input: R) -> io::Result<Self> {
+        let mut input = BufReader::new(input);
+        let mut header = [0; 6];
+        input.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Not a binary trace"));
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version != VERSION {
+            let message = format!("Unsupported trace version {}", version);
+            return Err(io::Error::new(ErrorKind::InvalidData, message));
+        }
+        Ok(TraceReader { input })
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut data = [0; RECORD_SIZE];
+        match self.input.fill_buf() {
+            Ok([]) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        Some(
+            self.input
+                .read_exact(&mut data)
+                .map(|_| Entry::Step(Record::decode(&data))),
+        )
+    }
+}
+
+// MAME style text traces, lines without an instruction address are ignored
+pub struct TextTrace<R: BufRead> {
+    lines: io::Lines<R>,
+}
+
+impl<R: BufRead> TextTrace<R> {
+    pub fn new(input: R) -> Self {
+        TextTrace {
+            lines: input.lines(),
+        }
+    }
+}
+
+pub fn parse_line(line: &str) -> Option<Entry> {
+    let line = line.trim();
+    if let Some(count) = line
+        .strip_prefix("(loops for ")
+        .and_then(|rest| rest.split_whitespace().next())
+    {
+        return count.parse().ok().map(Entry::Skip);
+    }
+
+    let mut record = Record::default();
+    let mut pc = None;
+    for token in line.split_whitespace() {
+        let token = token.trim_end_matches(',');
+        if let Some((key, value)) = token.split_once('=') {
+            let key = key.to_ascii_uppercase();
+            if let Some(i) = REGISTERS.iter().position(|&name| name == key) {
+                record.regs[i] = u16::from_str_radix(value, 16).ok();
+            } else if key == "CYC" {
+                record.cycles = value.parse().ok();
+            } else if key == "PC" && pc.is_none() {
+                pc = u16::from_str_radix(value, 16).ok();
+            }
+        } else if let Some(addr) = token.strip_suffix(':') {
+            if pc.is_none() && (1..=4).contains(&addr.len()) {
+                pc = u16::from_str_radix(addr, 16).ok();
+            }
+        }
+    }
+    record.pc = pc?;
+    Some(Entry::Step(record))
+}
+
+impl<R: BufRead> Iterator for TextTrace<R> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in &mut self.lines {
+            match line {
+                Ok(line) => match parse_line(&line) {
+                    Some(entry) => return Some(Ok(entry)),
+                    None => continue,
+                },
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+pub type Entries = Box<dyn Iterator<Item = io::Result<Entry>>>;
+
+// Binary or text trace, depending on the header
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Entries> {
+    let mut input = BufReader::new(File::open(path)?);
+    if input.fill_buf()?.starts_with(MAGIC) {
+        Ok(Box::new(TraceReader::new(input)?))
+    } else {
+        Ok(Box::new(TextTrace::new(input)))
+    }
+}
+
+pub struct Divergence {
+    pub step: u64, // Index of the instruction in the first trace
+    pub ours: Option<Record>,
+    pub theirs: Option<Record>, // None if a trace ended early
+    pub differences: Vec<String>,
+    pub context: Vec<Record>, // Matching instructions before the divergence, oldest first
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Traces diverge at step {}", self.step)?;
+        for record in &self.context {
+            writeln!(f, "              {}", record)?;
+        }
+        let show = |record: &Option<Record>| match record {
+            Some(record) => record.to_string(),
+            None => String::from("end of trace"),
+        };
+        writeln!(f, "  ours:       {}", show(&self.ours))?;
+        writeln!(f, "  theirs:     {}", show(&self.theirs))?;
+        write!(f, "  {}", self.differences.join(", "))
+    }
+}
+
+// One trace being compared. `pending` instructions are dropped because the other trace
+// skipped them.
+struct Side<I> {
+    entries: I,
+    pending: u64,
+    index: u64,
+    base: Option<u64>, // T states of the first record, counts are compared from there
+}
+
+impl<I: Iterator<Item = io::Result<Entry>>> Side<I> {
+    fn new(entries: I) -> Self {
+        Side {
+            entries,
+            pending: 0,
+            index: 0,
+            base: None,
+        }
+    }
+
+    fn next(&mut self, other_pending: &mut u64) -> io::Result<Option<Record>> {
+        while let Some(entry) = self.entries.next().transpose()? {
+            match entry {
+                Entry::Skip(count) => *other_pending += count,
+                Entry::Step(record) => {
+                    self.index += 1;
+                    if self.base.is_none() {
+                        self.base = record.cycles;
+                    }
+                    if self.pending == 0 {
+                        return Ok(Some(record));
+                    }
+                    self.pending -= 1;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn cycles(&self, record: &Record) -> Option<u64> {
+        Some(record.cycles? - self.base?)
+    }
+}
+
+// Fields present in both records that differ
+fn differences<A, B>(ours: &Record, a: &Side<A>, theirs: &Record, b: &Side<B>) -> Vec<String>
+where
+    A: Iterator<Item = io::Result<Entry>>,
+    B: Iterator<Item = io::Result<Entry>>,
+{
+    let mut out = Vec::new();
+    if ours.pc != theirs.pc {
+        out.push(format!("PC {:04X} != {:04X}", ours.pc, theirs.pc));
+    }
+    let (x, y) = (ours.len as usize, theirs.len as usize);
+    if x > 0 && y > 0 && ours.bytes[..x] != theirs.bytes[..y] {
+        let message = format!(
+            "opcode {:02X?} != {:02X?}",
+            &ours.bytes[..x],
+            &theirs.bytes[..y]
+        );
+        out.push(message);
+    }
+    for (i, name) in REGISTERS.iter().enumerate() {
+        if let (Some(x), Some(y)) = (ours.regs[i], theirs.regs[i]) {
+            if x != y {
+                out.push(format!("{} {:04X} != {:04X}", name, x, y));
+            }
+        }
+    }
+    if let (Some(x), Some(y)) = (a.cycles(ours), b.cycles(theirs)) {
+        if x != y {
+            out.push(format!("T states since start {} != {}", x, y));
+        }
+    }
+    out
+}
+
+// Streams both traces until the first divergence, None if they match. Up to `context`
+// matching instructions before it are kept for the report.
+pub fn compare<A, B>(ours: A, theirs: B, context: usize) -> io::Result<Option<Divergence>>
+where
+    A: Iterator<Item = io::Result<Entry>>,
+    B: Iterator<Item = io::Result<Entry>>,
+{
+    let (mut a, mut b) = (Side::new(ours), Side::new(theirs));
+    let mut history = VecDeque::with_capacity(context + 1);
+    let mut held = None; // Their record while our side catches up with a skip
+    loop {
+        let ours = a.next(&mut b.pending)?;
+        let theirs = match held.take() {
+            Some(_) if b.pending > 0 => {
+                b.pending -= 1;
+                b.next(&mut a.pending)?
+            }
+            Some(record) => Some(record),
+            None => b.next(&mut a.pending)?,
+        };
+        if ours.is_some() && a.pending > 0 {
+            a.pending -= 1;
+            held = theirs;
+            continue;
+        }
+
+        let differences = match (&ours, &theirs) {
+            (None, None) => return Ok(None),
+            (Some(x), Some(y)) => differences(x, &a, y, &b),
+            _ => vec![String::from("trace ends")],
+        };
+        if !differences.is_empty() {
+            return Ok(Some(Divergence {
+                step: a.index - ours.is_some() as u64,
+                ours,
+                theirs,
+                differences,
+                context: history.into_iter().collect(),
+            }));
+        }
+        if context > 0 {
+            if history.len() == context {
+                history.pop_front();
+            }
+            history.extend(ours);
+        }
+    }
+}